@@ -0,0 +1,314 @@
+// Activation token module
+//
+// Under Wayland an app may only raise its window with an xdg-activation
+// token obtained from the surface the user interacted with. Version 1.2 of
+// the notification spec hands the token to the app in the ActivationToken
+// signal, sent just before ActionInvoked.
+//
+// Token requests go through a trait so tests and non-Wayland sessions can
+// substitute their own provider, and the signals answering an action go
+// through `ActionSignals`, so the order they are sent in can be tested.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use cosmic::iced::{window, Task};
+
+use crate::dbus::PortalOrigin;
+use crate::manager::ClosedNotification;
+
+/// How long the compositor gets to issue a token
+///
+/// The action is answered without a token after this, so a compositor that
+/// never responds can't keep the app from hearing about the click.
+pub const TOKEN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Source of xdg-activation tokens
+pub trait ActivationTokenProvider: std::fmt::Debug {
+    /// Request a token for activating another app from `surface`
+    ///
+    /// Resolves to None when no token can be obtained, e.g. when the
+    /// compositor refuses the request.
+    fn request_token(&self, surface: Option<window::Id>) -> Task<Option<String>>;
+}
+
+/// Requests tokens from the compositor through xdg-activation
+#[derive(Debug, Clone)]
+pub struct XdgActivation {
+    /// App ID the token is requested for (the applet's own)
+    app_id: String,
+}
+
+impl XdgActivation {
+    /// Create a provider requesting tokens on behalf of `app_id`
+    pub fn new(app_id: impl Into<String>) -> Self {
+        Self {
+            app_id: app_id.into(),
+        }
+    }
+}
+
+impl ActivationTokenProvider for XdgActivation {
+    fn request_token(&self, surface: Option<window::Id>) -> Task<Option<String>> {
+        cosmic::iced::platform_specific::shell::commands::activation::request_token(
+            Some(self.app_id.clone()),
+            surface,
+        )
+    }
+}
+
+/// Provider that always hands out the same token, or none
+#[derive(Debug, Clone, Default)]
+pub struct StaticToken(pub Option<String>);
+
+impl ActivationTokenProvider for StaticToken {
+    fn request_token(&self, _surface: Option<window::Id>) -> Task<Option<String>> {
+        Task::done(self.0.clone())
+    }
+}
+
+/// An invoked action, waiting to be reported to the app that sent it
+#[derive(Debug, Clone)]
+pub struct InvokedAction {
+    /// Notification the action belongs to
+    pub notification_id: u32,
+
+    /// Key of the invoked action
+    pub action_key: String,
+
+    /// Unique bus name of the sending app, if known
+    pub sender: Option<String>,
+
+    /// Portal or org.gtk.Notifications origin; such apps are activated
+    /// instead of being sent signals
+    pub portal: Option<PortalOrigin>,
+
+    /// Closures caused by invoking the action (non-resident notifications)
+    pub closed: Vec<ClosedNotification>,
+}
+
+/// Where the answer to an invoked action is sent
+///
+/// Implementations report their own failures; the answer carries on with
+/// the next step either way.
+pub trait ActionSignals: Send + Sync + 'static {
+    /// Send `ActivationToken` for notification `id` to `destination`
+    fn activation_token(
+        &self,
+        id: u32,
+        token: &str,
+        destination: Option<&str>,
+    ) -> impl Future<Output = ()> + Send;
+
+    /// Send `ActionInvoked` for notification `id` to `destination`
+    fn action_invoked(
+        &self,
+        id: u32,
+        action_key: &str,
+        destination: Option<&str>,
+    ) -> impl Future<Output = ()> + Send;
+
+    /// Activate the action on a portal or org.gtk.Notifications app
+    fn activate(
+        &self,
+        origin: &PortalOrigin,
+        action_key: &str,
+        token: Option<&str>,
+    ) -> impl Future<Output = ()> + Send;
+
+    /// Report a closure caused by the action
+    fn notification_closed(&self, closed: &ClosedNotification) -> impl Future<Output = ()> + Send;
+}
+
+/// Answer an invoked action in the order the spec requires
+///
+/// Sends `ActivationToken` (when there is a token), then `ActionInvoked`,
+/// then the closures. Portal and org.gtk.Notifications apps are activated
+/// with the token instead of receiving the first two signals.
+pub async fn answer_action<S: ActionSignals>(
+    signals: &S,
+    action: InvokedAction,
+    token: Option<String>,
+) {
+    let id = action.notification_id;
+    match &action.portal {
+        Some(origin) => {
+            signals
+                .activate(origin, &action.action_key, token.as_deref())
+                .await
+        }
+        None => {
+            let destination = action.sender.as_deref();
+            match &token {
+                Some(token) => signals.activation_token(id, token, destination).await,
+                None => tracing::debug!("No activation token for notification {}", id),
+            }
+            signals
+                .action_invoked(id, &action.action_key, destination)
+                .await;
+        }
+    }
+
+    for closed in &action.closed {
+        signals.notification_closed(closed).await;
+    }
+}
+
+/// Request a token for `surface`, then answer `action` through `signals`
+///
+/// A request left unanswered for TOKEN_TIMEOUT is answered without a token,
+/// and a token arriving after that is dropped.
+pub fn request_and_answer<S: ActionSignals>(
+    provider: &dyn ActivationTokenProvider,
+    surface: Option<window::Id>,
+    action: InvokedAction,
+    signals: S,
+) -> Task<()> {
+    let waiting = Arc::new(Mutex::new(Some((action, signals))));
+    let timeout = Task::future(tokio::time::sleep(TOKEN_TIMEOUT)).map(|()| None);
+
+    Task::batch([provider.request_token(surface), timeout]).then(move |token| {
+        // Whichever resolves first answers, the other finds nothing to do
+        let Some((action, signals)) = waiting
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+        else {
+            return Task::none();
+        };
+        Task::future(async move { answer_action(&signals, action, token).await })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::CloseReason;
+    use futures::StreamExt;
+
+    /// Records the signals it is asked to send
+    #[derive(Debug, Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Recorder {
+        fn push(&self, signal: String) {
+            self.0.lock().unwrap().push(signal);
+        }
+
+        fn signals(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl ActionSignals for Recorder {
+        async fn activation_token(&self, id: u32, token: &str, destination: Option<&str>) {
+            self.push(format!("ActivationToken({id}, {token}) -> {destination:?}"));
+        }
+
+        async fn action_invoked(&self, id: u32, action_key: &str, destination: Option<&str>) {
+            self.push(format!(
+                "ActionInvoked({id}, {action_key}) -> {destination:?}"
+            ));
+        }
+
+        async fn activate(&self, origin: &PortalOrigin, action_key: &str, token: Option<&str>) {
+            self.push(format!(
+                "Activate({}, {action_key}, {token:?})",
+                origin.app_id
+            ));
+        }
+
+        async fn notification_closed(&self, closed: &ClosedNotification) {
+            self.push(format!("NotificationClosed({})", closed.id));
+        }
+    }
+
+    /// A compositor that never answers
+    #[derive(Debug)]
+    struct Unanswered;
+
+    impl ActivationTokenProvider for Unanswered {
+        fn request_token(&self, _surface: Option<window::Id>) -> Task<Option<String>> {
+            Task::future(std::future::pending())
+        }
+    }
+
+    fn invoked(portal: Option<PortalOrigin>) -> InvokedAction {
+        InvokedAction {
+            notification_id: 7,
+            action_key: "open".to_string(),
+            sender: Some(":1.42".to_string()),
+            portal,
+            closed: vec![ClosedNotification {
+                id: 7,
+                reason: CloseReason::Dismissed,
+                sender: Some(":1.42".to_string()),
+                portal: false,
+            }],
+        }
+    }
+
+    /// Run the token request and return the signals of the first answer
+    async fn answer(provider: &dyn ActivationTokenProvider, action: InvokedAction) -> Vec<String> {
+        let recorder = Recorder::default();
+        let task = request_and_answer(provider, None, action, recorder.clone());
+        let mut actions = cosmic::iced_runtime::task::into_stream(task).expect("task runs");
+        actions.next().await;
+        recorder.signals()
+    }
+
+    #[tokio::test]
+    async fn test_token_is_sent_before_action_invoked() {
+        let provider = StaticToken(Some("token-1".to_string()));
+        assert_eq!(
+            answer(&provider, invoked(None)).await,
+            vec![
+                "ActivationToken(7, token-1) -> Some(\":1.42\")",
+                "ActionInvoked(7, open) -> Some(\":1.42\")",
+                "NotificationClosed(7)",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_action_invoked_without_token() {
+        assert_eq!(
+            answer(&StaticToken(None), invoked(None)).await,
+            vec![
+                "ActionInvoked(7, open) -> Some(\":1.42\")",
+                "NotificationClosed(7)",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_portal_apps_are_activated_with_token() {
+        let origin = PortalOrigin {
+            app_id: "org.example.Chat".to_string(),
+            portal_id: "new-message".to_string(),
+            actions: Default::default(),
+        };
+        let provider = StaticToken(Some("token-1".to_string()));
+        assert_eq!(
+            answer(&provider, invoked(Some(origin))).await,
+            vec![
+                "Activate(org.example.Chat, open, Some(\"token-1\"))",
+                "NotificationClosed(7)",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_token_request_times_out() {
+        let started = std::time::Instant::now();
+        assert_eq!(
+            answer(&Unanswered, invoked(None)).await,
+            vec![
+                "ActionInvoked(7, open) -> Some(\":1.42\")",
+                "NotificationClosed(7)",
+            ]
+        );
+        assert!(started.elapsed() >= TOKEN_TIMEOUT);
+    }
+}
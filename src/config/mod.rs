@@ -190,6 +190,10 @@ pub struct AppletConfig {
     #[serde(default)]
     pub play_sound: bool,
 
+    /// Sound theme to play sounds from (None = follow the desktop setting)
+    #[serde(default)]
+    pub sound_theme: Option<String>,
+
     /// Show notification preview (brief on-screen display)
     #[serde(default = "default_true")]
     pub show_preview: bool,
@@ -221,6 +225,21 @@ pub struct AppletConfig {
     /// Animation configuration
     #[serde(default)]
     pub animations: AnimationConfig,
+
+    // D-Bus Settings
+    /// Own org.freedesktop.Notifications instead of listening to another daemon
+    ///
+    /// Enable when running without cosmic-notifications (or any other
+    /// notification daemon) so the applet acts as the notification server.
+    #[serde(default)]
+    pub daemon_mode: bool,
+
+    /// Serve org.gtk.Notifications alongside the freedesktop interface
+    ///
+    /// GApplication apps send their notifications there when the name is
+    /// owned, so enabling this takes them away from any other daemon.
+    #[serde(default)]
+    pub gtk_notifications: bool,
 }
 
 impl Default for AppletConfig {
@@ -236,6 +255,7 @@ impl Default for AppletConfig {
             do_not_disturb: false,
             default_timeout: None,
             play_sound: false,
+            sound_theme: None,
             show_preview: true,
             history_enabled: true,
             max_history_items: default_max_history(),
@@ -243,6 +263,8 @@ impl Default for AppletConfig {
             app_filters: HashMap::new(),
             min_urgency_level: 0, // Show all (Low, Normal, Critical)
             animations: AnimationConfig::default(),
+            daemon_mode: false,
+            gtk_notifications: false,
         }
     }
 }
@@ -0,0 +1,466 @@
+// COSMIC Notification Applet control tool
+//
+// Talks to the running applet over its control interface
+// (com.system76.CosmicAppletNotifications). History queries fall back to
+// reading the history file while the applet is not running.
+//
+// Usage: cosmic-notifications-ctl <command> [options]; see `USAGE`.
+
+use std::process::ExitCode;
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Duration, Local};
+use cosmic_applet_notifications::dbus::control::{
+    NotificationSummary, CONTROL_BUS_NAME, CONTROL_INTERFACE, CONTROL_OBJECT_PATH,
+};
+use cosmic_applet_notifications::dbus::MetricsSnapshot;
+use cosmic_applet_notifications::manager::storage::HistoryStorage;
+
+const USAGE: &str = "\
+Usage: cosmic-notifications-ctl <command> [options]
+
+Commands:
+  dnd on|off|toggle|status      Control Do Not Disturb
+  list [--json]                 List active notifications
+  history [--app NAME] [--since DURATION] [--json]
+                                List notification history (also while the
+                                applet is not running)
+  dismiss ID                    Dismiss a notification
+  invoke ID ACTION              Invoke a notification action
+  clear [--history]             Dismiss all notifications, or clear history
+  metrics [--json]              Show notification listener health counters
+  help                          Show this help
+
+DURATION is a number followed by s, m, h, d or w, e.g. 30m or 1h.";
+
+/// A parsed command line
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Dnd(DndCommand),
+    List { json: bool },
+    History(HistoryQuery),
+    Dismiss(u32),
+    Invoke { id: u32, action_key: String },
+    Clear { history: bool },
+    Metrics { json: bool },
+    Help,
+}
+
+/// What to do with Do Not Disturb
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DndCommand {
+    On,
+    Off,
+    Toggle,
+    Status,
+}
+
+/// Filters and format for the `history` command
+#[derive(Debug, Clone, Default, PartialEq)]
+struct HistoryQuery {
+    /// Only entries from this app (name as listed, any case)
+    app: Option<String>,
+
+    /// Only entries newer than this
+    since: Option<Duration>,
+
+    json: bool,
+}
+
+/// Parse the arguments following the program name
+fn parse_args(args: impl IntoIterator<Item = String>) -> anyhow::Result<Command> {
+    let mut args = args.into_iter();
+    let Some(command) = args.next() else {
+        return Ok(Command::Help);
+    };
+
+    let command = match command.as_str() {
+        "dnd" => {
+            let dnd = match args.next().as_deref() {
+                Some("on") => DndCommand::On,
+                Some("off") => DndCommand::Off,
+                Some("toggle") => DndCommand::Toggle,
+                Some("status") | None => DndCommand::Status,
+                Some(other) => {
+                    bail!("unknown dnd command '{other}' (expected on, off, toggle or status)")
+                }
+            };
+            Command::Dnd(dnd)
+        }
+        "list" => {
+            let mut json = false;
+            for arg in args.by_ref() {
+                match arg.as_str() {
+                    "--json" => json = true,
+                    other => bail!("unknown option '{other}' for list"),
+                }
+            }
+            Command::List { json }
+        }
+        "history" => {
+            let mut query = HistoryQuery::default();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--app" => query.app = Some(args.next().context("--app needs a name")?),
+                    "--since" => {
+                        let since = args.next().context("--since needs a duration")?;
+                        query.since = Some(parse_duration(&since)?);
+                    }
+                    "--json" => query.json = true,
+                    other => bail!("unknown option '{other}' for history"),
+                }
+            }
+            Command::History(query)
+        }
+        "dismiss" => Command::Dismiss(parse_id(args.next())?),
+        "invoke" => Command::Invoke {
+            id: parse_id(args.next())?,
+            action_key: args.next().context("invoke needs an action key")?,
+        },
+        "clear" => {
+            let mut history = false;
+            for arg in args.by_ref() {
+                match arg.as_str() {
+                    "--history" => history = true,
+                    other => bail!("unknown option '{other}' for clear"),
+                }
+            }
+            Command::Clear { history }
+        }
+        "metrics" => {
+            let mut json = false;
+            for arg in args.by_ref() {
+                match arg.as_str() {
+                    "--json" => json = true,
+                    other => bail!("unknown option '{other}' for metrics"),
+                }
+            }
+            Command::Metrics { json }
+        }
+        "help" | "--help" | "-h" => Command::Help,
+        other => bail!("unknown command '{other}'"),
+    };
+
+    if let Some(extra) = args.next() {
+        bail!("unexpected argument '{extra}'");
+    }
+    Ok(command)
+}
+
+/// Parse a notification ID argument
+fn parse_id(arg: Option<String>) -> anyhow::Result<u32> {
+    let arg = arg.context("missing notification ID")?;
+    arg.parse()
+        .with_context(|| format!("invalid notification ID '{arg}'"))
+}
+
+/// Parse a duration like `90s`, `30m`, `1h`, `2d` or `1w`
+fn parse_duration(text: &str) -> anyhow::Result<Duration> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (amount, unit) = text.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .with_context(|| format!("invalid duration '{text}'"))?;
+
+    let duration = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => bail!("invalid duration '{text}' (use a unit of s, m, h, d or w)"),
+    };
+    duration.with_context(|| format!("duration '{text}' is too long"))
+}
+
+/// Select history entries matching a query, oldest first
+fn filter_history(
+    history: Vec<NotificationSummary>,
+    query: &HistoryQuery,
+    now: DateTime<Local>,
+) -> Vec<NotificationSummary> {
+    let cutoff = query.since.map(|since| (now - since).timestamp());
+    history
+        .into_iter()
+        .filter(|notification| {
+            query
+                .app
+                .as_deref()
+                .is_none_or(|app| notification.app_name.eq_ignore_ascii_case(app))
+        })
+        .filter(|notification| cutoff.is_none_or(|cutoff| notification.timestamp >= cutoff))
+        .collect()
+}
+
+/// Print notifications as JSON, or one tab-separated line each
+fn print_notifications(notifications: &[NotificationSummary], json: bool) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(notifications)?);
+        return Ok(());
+    }
+
+    for notification in notifications {
+        let time = DateTime::from_timestamp(notification.timestamp, 0)
+            .map(|time| {
+                time.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        println!(
+            "{}\t{}\t{}\t{}",
+            notification.id, time, notification.app_name, notification.summary
+        );
+    }
+    Ok(())
+}
+
+/// Print listener metrics as JSON, or one tab-separated counter per line
+fn print_metrics(metrics: &MetricsSnapshot, json: bool) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(metrics)?);
+        return Ok(());
+    }
+
+    let counters = [
+        ("received", metrics.received),
+        ("parsed", metrics.parsed),
+        ("parse_failures", metrics.parse_failures),
+        ("receive_errors", metrics.receive_errors),
+        ("unpaired_evictions", metrics.unpaired_evictions),
+        ("reply_timeouts", metrics.reply_timeouts),
+        ("reconnects", metrics.reconnects),
+        ("queue_stalls", metrics.queue_stalls),
+        ("peak_queue_depth", metrics.peak_queue_depth),
+    ];
+    for (name, value) in counters {
+        println!("{name}\t{value}");
+    }
+    Ok(())
+}
+
+/// Check whether the applet is serving its control interface
+///
+/// Without a session bus nothing can serve it, so that counts as not running.
+async fn applet_running() -> bool {
+    let Ok(connection) = zbus::Connection::session().await else {
+        return false;
+    };
+    let owner = async {
+        let dbus = zbus::fdo::DBusProxy::new(&connection).await.ok()?;
+        let name = zbus::names::BusName::try_from(CONTROL_BUS_NAME).ok()?;
+        dbus.name_has_owner(name).await.ok()
+    };
+    owner.await.unwrap_or(false)
+}
+
+/// Call a method on the running applet's control interface
+async fn call<B, R>(method: &str, body: &B) -> anyhow::Result<R>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+    R: for<'d> zbus::zvariant::DynamicDeserialize<'d>,
+{
+    let connection = zbus::Connection::session()
+        .await
+        .context("cannot connect to the session bus")?;
+    let reply = connection
+        .call_method(
+            Some(CONTROL_BUS_NAME),
+            CONTROL_OBJECT_PATH,
+            Some(CONTROL_INTERFACE),
+            method,
+            body,
+        )
+        .await;
+    let reply = match reply {
+        Ok(reply) => reply,
+        Err(zbus::Error::MethodError(name, ..))
+            if name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown" =>
+        {
+            bail!("{method} failed: the notification applet is not running")
+        }
+        Err(e) => return Err(e).with_context(|| format!("{method} failed")),
+    };
+    Ok(reply.body().deserialize()?)
+}
+
+/// Run a parsed command
+async fn run(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Dnd(DndCommand::On) => call("SetDnd", &(true,)).await,
+        Command::Dnd(DndCommand::Off) => call("SetDnd", &(false,)).await,
+        Command::Dnd(DndCommand::Toggle) => call("ToggleDnd", &()).await,
+        Command::Dnd(DndCommand::Status) => {
+            let enabled: bool = call("GetDnd", &()).await?;
+            println!("{}", if enabled { "on" } else { "off" });
+            Ok(())
+        }
+        Command::List { json } => {
+            let active: Vec<NotificationSummary> = call("ListActive", &()).await?;
+            print_notifications(&active, json)
+        }
+        Command::History(query) => {
+            let history: Vec<NotificationSummary> = if applet_running().await {
+                call("ListHistory", &()).await?
+            } else {
+                // The applet saves its history, read that instead
+                HistoryStorage::new()
+                    .load()
+                    .iter()
+                    .map(NotificationSummary::from)
+                    .collect()
+            };
+            let entries = filter_history(history, &query, Local::now());
+            print_notifications(&entries, query.json)
+        }
+        Command::Dismiss(id) => call("Dismiss", &(id,)).await,
+        Command::Invoke { id, action_key } => call("InvokeAction", &(id, action_key)).await,
+        Command::Clear { history: false } => call("ClearAll", &()).await,
+        Command::Clear { history: true } => call("ClearHistory", &()).await,
+        Command::Metrics { json } => {
+            let metrics: MetricsSnapshot = call("ListenerMetrics", &()).await?;
+            print_metrics(&metrics, json)
+        }
+        Command::Help => {
+            println!("{USAGE}");
+            Ok(())
+        }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    // Keep stdout for results; only warnings go to stderr
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::from_default_env()
+                .add_directive(tracing::Level::WARN.into()),
+        )
+        .init();
+
+    let result = match parse_args(std::env::args().skip(1)) {
+        Ok(command) => run(command).await,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("cosmic-notifications-ctl: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_applet_notifications::Notification;
+
+    fn args(line: &str) -> anyhow::Result<Command> {
+        parse_args(line.split_whitespace().map(str::to_string))
+    }
+
+    fn entry(app_name: &str, age_minutes: i64, now: DateTime<Local>) -> Notification {
+        let mut notification = Notification::from_notify_args(
+            1,
+            (
+                app_name.to_string(),
+                0,
+                String::new(),
+                String::new(),
+                String::new(),
+                vec![],
+                Default::default(),
+                -1,
+            ),
+        );
+        notification.timestamp = now - Duration::minutes(age_minutes);
+        notification
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(args("").unwrap(), Command::Help);
+        assert_eq!(args("dnd on").unwrap(), Command::Dnd(DndCommand::On));
+        assert_eq!(args("dnd").unwrap(), Command::Dnd(DndCommand::Status));
+        assert_eq!(args("list --json").unwrap(), Command::List { json: true });
+        assert_eq!(args("dismiss 42").unwrap(), Command::Dismiss(42));
+        assert_eq!(
+            args("metrics --json").unwrap(),
+            Command::Metrics { json: true }
+        );
+        assert_eq!(
+            args("invoke 42 reply").unwrap(),
+            Command::Invoke {
+                id: 42,
+                action_key: "reply".to_string()
+            }
+        );
+        assert_eq!(
+            args("clear --history").unwrap(),
+            Command::Clear { history: true }
+        );
+        assert_eq!(
+            args("history --app Firefox --since 1h --json").unwrap(),
+            Command::History(HistoryQuery {
+                app: Some("Firefox".to_string()),
+                since: Some(Duration::hours(1)),
+                json: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(args("dnd maybe").is_err());
+        assert!(args("dismiss").is_err());
+        assert!(args("dismiss abc").is_err());
+        assert!(args("list --yaml").is_err());
+        assert!(args("history --app").is_err());
+        assert!(args("clear now").is_err());
+        assert!(args("dnd on off").is_err());
+        assert!(args("frobnicate").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::seconds(90));
+        assert_eq!(parse_duration("30m").unwrap(), Duration::minutes(30));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::days(2));
+        assert_eq!(parse_duration("1w").unwrap(), Duration::weeks(1));
+        assert!(parse_duration("1").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1y").is_err());
+    }
+
+    #[test]
+    fn test_filter_history() {
+        let now = Local::now();
+        let history: Vec<NotificationSummary> = [
+            entry("firefox", 120, now),
+            entry("Slack", 30, now),
+            entry("firefox", 5, now),
+        ]
+        .iter()
+        .map(NotificationSummary::from)
+        .collect();
+
+        let all = filter_history(history.clone(), &HistoryQuery::default(), now);
+        assert_eq!(all.len(), 3);
+
+        let query = HistoryQuery {
+            app: Some("Firefox".to_string()),
+            since: Some(Duration::hours(1)),
+            json: false,
+        };
+        let recent_firefox = filter_history(history.clone(), &query, now);
+        assert_eq!(recent_firefox, vec![history[2].clone()]);
+    }
+}
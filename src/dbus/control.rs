@@ -0,0 +1,596 @@
+// Control interface for scripting the applet
+//
+// Exports com.system76.CosmicAppletNotifications so scripts can toggle Do Not
+// Disturb, list notifications, and dismiss or act on them without opening
+// the popup.
+//
+// Like the daemon mode server, the interface object only forwards requests
+// over a channel; the application answers them from its NotificationManager.
+// Queries carry a `Reply` the application answers through. Listener metrics
+// are the exception: the interface reads them straight from `metrics`.
+
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use cosmic::iced;
+use futures::channel::{mpsc, oneshot};
+use futures::stream::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use zbus::object_server::SignalContext;
+use zbus::zvariant::Type;
+use zbus::{interface, Connection};
+
+use crate::dbus::metrics::{self, MetricsSnapshot};
+use crate::dbus::types::Notification;
+use crate::manager::NotificationManager;
+
+/// Well-known bus name of the control interface
+pub const CONTROL_BUS_NAME: &str = "com.system76.CosmicAppletNotifications";
+
+/// Object path of the control interface
+pub const CONTROL_OBJECT_PATH: &str = "/com/system76/CosmicAppletNotifications";
+
+/// Interface name of the control interface
+pub const CONTROL_INTERFACE: &str = "com.system76.CosmicAppletNotifications";
+
+/// Buffer size for requests forwarded to the application
+const CONTROL_REQUEST_BUFFER_SIZE: usize = 32;
+
+/// Delay before retrying when the name cannot be claimed
+const CONTROL_RETRY_DELAY_SECS: u64 = 5;
+
+/// Connection the control interface is served on, while it is
+static CONTROL_CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// A request made through the control interface
+///
+/// Each maps onto an existing application message; see `Message::Control`.
+#[derive(Debug, Clone)]
+pub enum ControlRequest {
+    /// Toggle Do Not Disturb
+    ToggleDnd,
+
+    /// Turn Do Not Disturb on or off
+    SetDnd(bool),
+
+    /// Get whether Do Not Disturb is on
+    GetDnd(Reply<bool>),
+
+    /// List the active notifications, oldest first
+    ListActive(Reply<Vec<NotificationSummary>>),
+
+    /// List the notification history, oldest first
+    ListHistory(Reply<Vec<NotificationSummary>>),
+
+    /// Dismiss a notification; answered with whether it is active
+    Dismiss { id: u32, reply: Reply<bool> },
+
+    /// Dismiss all active notifications
+    ClearAll,
+
+    /// Clear the notification history
+    ClearHistory,
+
+    /// Invoke an action of a notification; answered with whether the
+    /// notification is active and offers the action
+    InvokeAction {
+        id: u32,
+        action_key: String,
+        reply: Reply<bool>,
+    },
+}
+
+/// One-shot reply to a control request
+///
+/// Messages must be `Clone`, so the sender is shared; the first `send()`
+/// answers the call and later ones are ignored.
+pub struct Reply<T>(Arc<Mutex<Option<oneshot::Sender<T>>>>);
+
+impl<T> Reply<T> {
+    /// Create a reply and the receiver the answer arrives on
+    pub fn new() -> (Self, oneshot::Receiver<T>) {
+        let (sender, receiver) = oneshot::channel();
+        (Self(Arc::new(Mutex::new(Some(sender)))), receiver)
+    }
+
+    /// Answer the request
+    pub fn send(&self, value: T) {
+        let sender = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        if let Some(sender) = sender {
+            // The caller may have gone away; nothing to answer then
+            let _ = sender.send(value);
+        }
+    }
+}
+
+impl<T> Clone for Reply<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T> std::fmt::Debug for Reply<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Reply")
+    }
+}
+
+/// A notification as listed by `ListActive` and `ListHistory`
+///
+/// Sent as `(usssyxa(ss))`: id, app name, summary, body, urgency (0-2),
+/// Unix timestamp and the `(key, label)` action pairs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct NotificationSummary {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    pub urgency: u8,
+    pub timestamp: i64,
+    pub actions: Vec<(String, String)>,
+}
+
+impl From<&Notification> for NotificationSummary {
+    fn from(notification: &Notification) -> Self {
+        Self {
+            id: notification.id,
+            app_name: notification.display_name().to_string(),
+            summary: notification.summary.clone(),
+            body: notification.body.clone(),
+            urgency: notification.urgency().to_u8(),
+            timestamp: notification.timestamp.timestamp(),
+            actions: notification
+                .actions
+                .iter()
+                .map(|action| (action.key.clone(), action.label.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// What the control signals report on, taken after each update
+///
+/// Compared with the previous snapshot to decide whether to emit `Changed`
+/// and `DndChanged`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ControlSnapshot {
+    /// ID and timestamp of each active notification (updates change the timestamp)
+    active: Vec<(u32, DateTime<Local>)>,
+
+    /// History length and newest entry
+    history: (usize, Option<u32>),
+
+    do_not_disturb: bool,
+}
+
+impl ControlSnapshot {
+    /// Take a snapshot of the manager's state
+    pub fn of(manager: &NotificationManager) -> Self {
+        let history = manager.history();
+        Self {
+            active: manager
+                .get_active_notifications()
+                .iter()
+                .map(|n| (n.id, n.timestamp))
+                .collect(),
+            history: (history.len(), history.back().map(|n| n.id)),
+            do_not_disturb: manager.is_do_not_disturb(),
+        }
+    }
+
+    /// Check whether the active notifications or the history changed
+    pub fn lists_changed(&self, previous: &Self) -> bool {
+        self.active != previous.active || self.history != previous.history
+    }
+
+    /// Get the new Do Not Disturb state, if it changed
+    pub fn dnd_changed(&self, previous: &Self) -> Option<bool> {
+        (self.do_not_disturb != previous.do_not_disturb).then_some(self.do_not_disturb)
+    }
+}
+
+/// The com.system76.CosmicAppletNotifications interface object
+pub struct ControlServer {
+    requests: mpsc::Sender<ControlRequest>,
+}
+
+impl ControlServer {
+    /// Create a server that forwards to `requests`
+    pub fn new(requests: mpsc::Sender<ControlRequest>) -> Self {
+        Self { requests }
+    }
+
+    /// Forward a request to the application
+    async fn forward(&mut self, request: ControlRequest) -> zbus::fdo::Result<()> {
+        use futures::SinkExt;
+
+        self.requests
+            .send(request)
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Applet is not accepting requests: {e}")))
+    }
+
+    /// Forward a query and wait for the application's answer
+    async fn query<T>(
+        &mut self,
+        request: impl FnOnce(Reply<T>) -> ControlRequest,
+    ) -> zbus::fdo::Result<T> {
+        let (reply, answer) = Reply::new();
+        self.forward(request(reply)).await?;
+        answer
+            .await
+            .map_err(|_| zbus::fdo::Error::Failed("Applet dropped the request".to_string()))
+    }
+}
+
+#[interface(name = "com.system76.CosmicAppletNotifications")]
+impl ControlServer {
+    /// Toggle Do Not Disturb
+    async fn toggle_dnd(&mut self) -> zbus::fdo::Result<()> {
+        self.forward(ControlRequest::ToggleDnd).await
+    }
+
+    /// Turn Do Not Disturb on or off
+    async fn set_dnd(&mut self, enabled: bool) -> zbus::fdo::Result<()> {
+        self.forward(ControlRequest::SetDnd(enabled)).await
+    }
+
+    /// Whether Do Not Disturb is on
+    async fn get_dnd(&mut self) -> zbus::fdo::Result<bool> {
+        self.query(ControlRequest::GetDnd).await
+    }
+
+    /// List the active notifications, oldest first
+    async fn list_active(&mut self) -> zbus::fdo::Result<Vec<NotificationSummary>> {
+        self.query(ControlRequest::ListActive).await
+    }
+
+    /// List the notification history, oldest first
+    async fn list_history(&mut self) -> zbus::fdo::Result<Vec<NotificationSummary>> {
+        self.query(ControlRequest::ListHistory).await
+    }
+
+    /// Dismiss a notification, as if its close button was pressed
+    ///
+    /// Fails with `InvalidArgs` if no active notification has this ID.
+    async fn dismiss(&mut self, id: u32) -> zbus::fdo::Result<()> {
+        if self
+            .query(|reply| ControlRequest::Dismiss { id, reply })
+            .await?
+        {
+            Ok(())
+        } else {
+            Err(zbus::fdo::Error::InvalidArgs(format!(
+                "No active notification with ID {id}"
+            )))
+        }
+    }
+
+    /// Dismiss all active notifications
+    async fn clear_all(&mut self) -> zbus::fdo::Result<()> {
+        self.forward(ControlRequest::ClearAll).await
+    }
+
+    /// Clear the notification history
+    async fn clear_history(&mut self) -> zbus::fdo::Result<()> {
+        self.forward(ControlRequest::ClearHistory).await
+    }
+
+    /// Invoke an action of a notification, as if its button was pressed
+    ///
+    /// Fails with `InvalidArgs` if no active notification has this ID, or it
+    /// doesn't offer the action.
+    async fn invoke_action(&mut self, id: u32, action_key: String) -> zbus::fdo::Result<()> {
+        let error = format!("Notification {id} has no action '{action_key}'");
+        let found = self
+            .query(|reply| ControlRequest::InvokeAction {
+                id,
+                action_key,
+                reply,
+            })
+            .await?;
+        if found {
+            Ok(())
+        } else {
+            Err(zbus::fdo::Error::InvalidArgs(error))
+        }
+    }
+
+    /// Health counters of the notification listener, as `(ttttttttt)`
+    ///
+    /// Received, parsed, parse failures, receive errors, unpaired evictions,
+    /// reply timeouts, reconnects, queue stalls and peak queue depth. All zero
+    /// in daemon mode.
+    async fn listener_metrics(&self) -> MetricsSnapshot {
+        metrics::listener_metrics()
+    }
+
+    /// Emitted when the active notifications or the history change
+    #[zbus(signal)]
+    async fn changed(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+
+    /// Emitted when Do Not Disturb is turned on or off
+    #[zbus(signal)]
+    async fn dnd_changed(ctxt: &SignalContext<'_>, enabled: bool) -> zbus::Result<()>;
+}
+
+/// Emit `Changed` on the control interface
+///
+/// Does nothing while the interface is not being served.
+pub async fn emit_changed() -> zbus::Result<()> {
+    emit_signal("Changed", &()).await
+}
+
+/// Emit `DndChanged` on the control interface
+///
+/// Does nothing while the interface is not being served.
+pub async fn emit_dnd_changed(enabled: bool) -> zbus::Result<()> {
+    emit_signal("DndChanged", &(enabled,)).await
+}
+
+/// Broadcast a control signal from the connection serving the interface
+async fn emit_signal<B>(name: &str, body: &B) -> zbus::Result<()>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    let connection = CONTROL_CONNECTION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    let Some(connection) = connection else {
+        return Ok(());
+    };
+
+    connection
+        .emit_signal(
+            None::<()>,
+            CONTROL_OBJECT_PATH,
+            CONTROL_INTERFACE,
+            name,
+            body,
+        )
+        .await
+}
+
+/// Subscription ID for the control interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ControlSubscription;
+
+impl Hash for ControlSubscription {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+}
+
+/// Create a subscription that serves the control interface
+///
+/// Claims the control bus name and yields every request as a
+/// `ControlRequest`. If the name is taken (e.g. by a second applet
+/// instance), the error is logged and the claim is retried periodically.
+pub fn subscribe<Message>() -> iced::Subscription<Message>
+where
+    Message: 'static + Send + Clone + From<ControlRequest>,
+{
+    iced::Subscription::run_with_id(ControlSubscription, control_stream().map(Message::from))
+}
+
+/// Serve the interface and stream forwarded requests
+///
+/// The connection is kept alive inside the stream state, so the bus name is
+/// released when iced drops the subscription.
+fn control_stream() -> impl Stream<Item = ControlRequest> {
+    futures::stream::once(async {
+        loop {
+            let (sender, receiver) = mpsc::channel(CONTROL_REQUEST_BUFFER_SIZE);
+            match serve(ControlServer::new(sender)).await {
+                Ok(connection) => {
+                    // Keep the connection alive for as long as requests are consumed
+                    return futures::stream::unfold(
+                        (ControlConnection::new(connection), receiver),
+                        |(connection, mut receiver)| async move {
+                            let request = receiver.next().await?;
+                            Some((request, (connection, receiver)))
+                        },
+                    );
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to claim {}: {} (retrying in {}s)",
+                        CONTROL_BUS_NAME,
+                        e,
+                        CONTROL_RETRY_DELAY_SECS
+                    );
+                    tokio::time::sleep(Duration::from_secs(CONTROL_RETRY_DELAY_SECS)).await;
+                }
+            }
+        }
+    })
+    .flatten()
+}
+
+/// Registers the control connection for outgoing signals while it is alive
+struct ControlConnection(Connection);
+
+impl ControlConnection {
+    fn new(connection: Connection) -> Self {
+        *CONTROL_CONNECTION
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(connection.clone());
+        Self(connection)
+    }
+}
+
+impl Drop for ControlConnection {
+    fn drop(&mut self) {
+        let mut current = CONTROL_CONNECTION
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if current.as_ref().map(Connection::unique_name) == Some(self.0.unique_name()) {
+            *current = None;
+        }
+    }
+}
+
+/// Export the interface on the session bus and claim the well-known name
+async fn serve(server: ControlServer) -> zbus::Result<Connection> {
+    let connection = zbus::connection::Builder::session()?
+        .serve_at(CONTROL_OBJECT_PATH, server)?
+        .name(CONTROL_BUS_NAME)?
+        .build()
+        .await?;
+
+    tracing::info!("Serving {} on the session bus", CONTROL_BUS_NAME);
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::types::Urgency;
+
+    fn test_server() -> (ControlServer, mpsc::Receiver<ControlRequest>) {
+        let (sender, receiver) = mpsc::channel(CONTROL_REQUEST_BUFFER_SIZE);
+        (ControlServer::new(sender), receiver)
+    }
+
+    fn notification(id: u32, summary: &str) -> Notification {
+        let mut notification = Notification::from_notify_args(
+            id,
+            (
+                "mail".to_string(),
+                0,
+                String::new(),
+                summary.to_string(),
+                "body".to_string(),
+                vec!["open".to_string(), "Open".to_string()],
+                Default::default(),
+                -1,
+            ),
+        );
+        notification.hints.urgency = Urgency::Critical;
+        notification
+    }
+
+    #[tokio::test]
+    async fn test_commands_are_forwarded() {
+        let (mut server, mut receiver) = test_server();
+        let responder = tokio::spawn(async move {
+            assert!(matches!(
+                receiver.next().await,
+                Some(ControlRequest::SetDnd(true))
+            ));
+            match receiver.next().await {
+                Some(ControlRequest::Dismiss { id, reply }) => {
+                    assert_eq!(id, 7);
+                    reply.send(true);
+                }
+                other => panic!("unexpected request: {:?}", other),
+            }
+            match receiver.next().await {
+                Some(ControlRequest::InvokeAction {
+                    id,
+                    action_key,
+                    reply,
+                }) => {
+                    assert_eq!((id, action_key.as_str()), (7, "open"));
+                    reply.send(true);
+                }
+                other => panic!("unexpected request: {:?}", other),
+            }
+        });
+
+        server.set_dnd(true).await.unwrap();
+        server.dismiss(7).await.unwrap();
+        server.invoke_action(7, "open".to_string()).await.unwrap();
+        responder.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unknown_notifications_are_invalid_args() {
+        let (mut server, mut receiver) = test_server();
+        let responder = tokio::spawn(async move {
+            while let Some(request) = receiver.next().await {
+                match request {
+                    ControlRequest::Dismiss { reply, .. }
+                    | ControlRequest::InvokeAction { reply, .. } => reply.send(false),
+                    other => panic!("unexpected request: {:?}", other),
+                }
+            }
+        });
+
+        assert!(matches!(
+            server.dismiss(99).await,
+            Err(zbus::fdo::Error::InvalidArgs(_))
+        ));
+        assert!(matches!(
+            server.invoke_action(7, "missing".to_string()).await,
+            Err(zbus::fdo::Error::InvalidArgs(_))
+        ));
+        drop(server);
+        responder.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_active_waits_for_reply() {
+        let (mut server, mut receiver) = test_server();
+        let responder = tokio::spawn(async move {
+            match receiver.next().await {
+                Some(ControlRequest::ListActive(reply)) => {
+                    let summary = NotificationSummary::from(&notification(3, "Hello"));
+                    // A second answer is ignored
+                    reply.clone().send(vec![summary]);
+                    reply.send(vec![]);
+                }
+                other => panic!("unexpected request: {:?}", other),
+            }
+        });
+
+        let listed = server.list_active().await.unwrap();
+        responder.await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, 3);
+        assert_eq!(listed[0].summary, "Hello");
+        assert_eq!(listed[0].urgency, 2);
+        assert_eq!(
+            listed[0].actions,
+            vec![("open".to_string(), "Open".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dropped_reply_is_an_error() {
+        let (mut server, mut receiver) = test_server();
+        let responder = tokio::spawn(async move {
+            // Drop the request without answering
+            receiver.next().await;
+        });
+
+        assert!(server.list_history().await.is_err());
+        responder.await.unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_changes() {
+        let mut manager = NotificationManager::new();
+        let empty = ControlSnapshot::of(&manager);
+
+        manager.add_notification(notification(0, "First"));
+        let added = ControlSnapshot::of(&manager);
+        assert!(added.lists_changed(&empty));
+        assert_eq!(added.dnd_changed(&empty), None);
+
+        manager.set_do_not_disturb(true);
+        let dnd = ControlSnapshot::of(&manager);
+        assert!(!dnd.lists_changed(&added));
+        assert_eq!(dnd.dnd_changed(&added), Some(true));
+
+        manager.remove_notification(1);
+        assert!(ControlSnapshot::of(&manager).lists_changed(&dnd));
+    }
+}
@@ -0,0 +1,373 @@
+// org.gtk.Notifications server
+//
+// GApplication sends notifications through org.gtk.Notifications whenever
+// that name is owned, instead of org.freedesktop.Notifications. Apps
+// identify notifications by string IDs and describe actions as GAction
+// names, so a pressed button activates the app itself through
+// org.freedesktop.Application rather than emitting a signal.
+//
+// The notification dictionary is the one the desktop portal uses, so it is
+// mapped by the portal adapter. Enabled through
+// `AppletConfig::gtk_notifications`.
+//
+// Reference: https://gitlab.gnome.org/GNOME/glib/-/blob/main/gio/gnotificationbackend.c
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use cosmic::iced;
+use futures::channel::mpsc;
+use futures::stream::{Stream, StreamExt};
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{interface, Connection};
+
+use crate::dbus::portal::{notification_from_portal, removal_from_portal, PortalOrigin};
+use crate::dbus::sender;
+use crate::dbus::types::NotificationEvent;
+
+/// Well-known bus name GApplication sends notifications to
+pub const GTK_NOTIFICATIONS_BUS_NAME: &str = "org.gtk.Notifications";
+
+/// Object path of the org.gtk.Notifications interface
+pub const GTK_NOTIFICATIONS_OBJECT_PATH: &str = "/org/gtk/Notifications";
+
+/// Interface GApplications export for activation
+const APPLICATION_INTERFACE: &str = "org.freedesktop.Application";
+
+/// Prefix of actions activated on the application itself
+const APP_ACTION_PREFIX: &str = "app.";
+
+/// Buffer size for requests forwarded from the interface to the application
+///
+/// Matches the daemon mode server.
+const GTK_EVENT_BUFFER_SIZE: usize = 128;
+
+/// Delay before retrying when the name cannot be claimed
+const GTK_RETRY_DELAY_SECS: u64 = 5;
+
+/// The org.gtk.Notifications interface object
+///
+/// Maps each request to a `NotificationEvent` and forwards it to the
+/// application over a bounded channel.
+pub struct GtkNotificationServer {
+    events: mpsc::Sender<NotificationEvent>,
+}
+
+impl GtkNotificationServer {
+    /// Create a server that forwards to `events`
+    pub fn new(events: mpsc::Sender<NotificationEvent>) -> Self {
+        Self { events }
+    }
+
+    /// Forward an event to the application
+    async fn forward(&mut self, event: NotificationEvent) -> zbus::fdo::Result<()> {
+        use futures::SinkExt;
+
+        self.events
+            .send(event)
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Applet is not accepting events: {e}")))
+    }
+}
+
+#[interface(name = "org.gtk.Notifications")]
+impl GtkNotificationServer {
+    /// Show a notification, replacing any with the same app and ID
+    async fn add_notification(
+        &mut self,
+        app_id: String,
+        id: String,
+        notification: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        // Actions activate the app itself, the caller gets no freedesktop signals
+        let notification = notification_from_portal((app_id, id, notification));
+
+        tracing::debug!(
+            "AddNotification from {}: {} (id {})",
+            notification.app_name,
+            notification.summary,
+            notification.id
+        );
+
+        self.forward(notification.into()).await
+    }
+
+    /// Withdraw a notification
+    async fn remove_notification(&mut self, app_id: String, id: String) -> zbus::fdo::Result<()> {
+        tracing::debug!("RemoveNotification from {} for '{}'", app_id, id);
+
+        self.forward(removal_from_portal(&app_id, &id)).await
+    }
+}
+
+/// Get the object path a GApplication exports for its app ID
+///
+/// Follows `g_application_get_dbus_object_path()`: dots become slashes and
+/// dashes underscores, e.g. `org.gnome.Char-Map` → `/org/gnome/Char_Map`.
+pub fn application_object_path(app_id: &str) -> String {
+    format!("/{}", app_id.replace('.', "/").replace('-', "_"))
+}
+
+/// Activate the action behind `action_key` on the app that sent a notification
+///
+/// `app.` actions are activated with `ActivateAction`, passing their
+/// target. Any other action just activates the app, as GNOME Shell does.
+/// The activation token lets the app raise its window.
+pub async fn activate_action(
+    origin: &PortalOrigin,
+    action_key: &str,
+    activation_token: Option<&str>,
+) -> zbus::Result<()> {
+    let mut platform_data: HashMap<&str, Value<'_>> = HashMap::new();
+    if let Some(token) = activation_token {
+        platform_data.insert("activation-token", Value::from(token));
+        platform_data.insert("desktop-startup-id", Value::from(token));
+    }
+
+    let path = application_object_path(&origin.app_id);
+    let connection = sender::signal_connection().await?;
+    let destination = Some(origin.app_id.as_str());
+
+    match app_action(origin, action_key) {
+        Some((name, parameter)) => {
+            tracing::debug!("Activating action '{}' on {}", name, origin.app_id);
+            connection
+                .call_method(
+                    destination,
+                    path.as_str(),
+                    Some(APPLICATION_INTERFACE),
+                    "ActivateAction",
+                    &(name, parameter, platform_data),
+                )
+                .await?;
+        }
+        None => {
+            tracing::debug!("Activating {}", origin.app_id);
+            connection
+                .call_method(
+                    destination,
+                    path.as_str(),
+                    Some(APPLICATION_INTERFACE),
+                    "Activate",
+                    &(platform_data,),
+                )
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the `ActivateAction` arguments for an action key
+///
+/// Returns the action name without its `app.` prefix and the target, if
+/// any. None for unknown keys and actions outside the `app.` namespace.
+fn app_action<'a>(
+    origin: &'a PortalOrigin,
+    action_key: &str,
+) -> Option<(&'a str, Vec<Value<'static>>)> {
+    let action = origin.actions.get(action_key)?;
+    let name = action.name.strip_prefix(APP_ACTION_PREFIX)?;
+    let parameter = action
+        .target
+        .as_ref()
+        .and_then(|target| target.to_value())
+        .into_iter()
+        .collect();
+    Some((name, parameter))
+}
+
+/// Subscription ID for the org.gtk.Notifications server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GtkSubscription;
+
+impl Hash for GtkSubscription {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+}
+
+/// Create a subscription that serves org.gtk.Notifications
+///
+/// Claims the bus name and yields every `AddNotification` and
+/// `RemoveNotification` request as a `NotificationEvent`. If the name is
+/// owned by someone else, the error is logged and the claim is retried
+/// periodically.
+pub fn subscribe<Message>() -> iced::Subscription<Message>
+where
+    Message: 'static + Send + Clone + From<NotificationEvent>,
+{
+    iced::Subscription::run_with_id(GtkSubscription, gtk_stream().map(Message::from))
+}
+
+/// Serve the interface and stream forwarded events
+///
+/// The connection is kept alive inside the stream state, so the bus name is
+/// released when iced drops the subscription.
+fn gtk_stream() -> impl Stream<Item = NotificationEvent> {
+    futures::stream::once(async {
+        loop {
+            let (sender, receiver) = mpsc::channel(GTK_EVENT_BUFFER_SIZE);
+            match serve(GtkNotificationServer::new(sender)).await {
+                Ok(connection) => {
+                    // Keep the connection alive for as long as events are consumed
+                    return futures::stream::unfold(
+                        (connection, receiver),
+                        |(connection, mut receiver)| async move {
+                            let event = receiver.next().await?;
+                            Some((event, (connection, receiver)))
+                        },
+                    );
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to claim {}: {} (retrying in {}s)",
+                        GTK_NOTIFICATIONS_BUS_NAME,
+                        e,
+                        GTK_RETRY_DELAY_SECS
+                    );
+                    tokio::time::sleep(Duration::from_secs(GTK_RETRY_DELAY_SECS)).await;
+                }
+            }
+        }
+    })
+    .flatten()
+}
+
+/// Export the interface on the session bus and claim the well-known name
+async fn serve(server: GtkNotificationServer) -> zbus::Result<Connection> {
+    let connection = zbus::connection::Builder::session()?
+        .serve_at(GTK_NOTIFICATIONS_OBJECT_PATH, server)?
+        .name(GTK_NOTIFICATIONS_BUS_NAME)?
+        .build()
+        .await?;
+
+    tracing::info!("Serving {} on the session bus", GTK_NOTIFICATIONS_BUS_NAME);
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::portal::{portal_notification_id, PortalAction};
+    use crate::dbus::sender::CloseReason;
+    use crate::dbus::types::{HintValue, Urgency, DEFAULT_ACTION_KEY};
+
+    fn test_server() -> (GtkNotificationServer, mpsc::Receiver<NotificationEvent>) {
+        let (sender, receiver) = mpsc::channel(GTK_EVENT_BUFFER_SIZE);
+        (GtkNotificationServer::new(sender), receiver)
+    }
+
+    fn origin(actions: Vec<(&str, &str, Option<HintValue>)>) -> PortalOrigin {
+        PortalOrigin {
+            app_id: "org.gnome.Char-Map".to_string(),
+            portal_id: "copied".to_string(),
+            actions: actions
+                .into_iter()
+                .map(|(key, name, target)| {
+                    (
+                        key.to_string(),
+                        PortalAction {
+                            name: name.to_string(),
+                            target,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_notification() {
+        let (mut server, mut receiver) = test_server();
+        let fields = HashMap::from([
+            (
+                "title".to_string(),
+                OwnedValue::try_from(Value::from("Copied")).unwrap(),
+            ),
+            (
+                "priority".to_string(),
+                OwnedValue::try_from(Value::from("urgent")).unwrap(),
+            ),
+            (
+                "default-action".to_string(),
+                OwnedValue::try_from(Value::from("app.show")).unwrap(),
+            ),
+        ]);
+
+        server
+            .add_notification(
+                "org.gnome.Characters".to_string(),
+                "copied".to_string(),
+                fields,
+            )
+            .await
+            .unwrap();
+
+        let id = portal_notification_id("org.gnome.Characters", "copied");
+        match receiver.next().await {
+            Some(NotificationEvent::Notify(notification)) => {
+                assert_eq!(notification.id, id);
+                assert_eq!(notification.summary, "Copied");
+                assert_eq!(notification.urgency(), Urgency::Critical);
+                assert_eq!(notification.sender, None);
+                assert!(notification.default_action().is_some());
+                assert_eq!(
+                    notification.portal.unwrap().actions[DEFAULT_ACTION_KEY].name,
+                    "app.show"
+                );
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        server
+            .remove_notification("org.gnome.Characters".to_string(), "copied".to_string())
+            .await
+            .unwrap();
+        match receiver.next().await {
+            Some(NotificationEvent::Closed { id: closed, reason }) => {
+                assert_eq!(closed, id);
+                assert_eq!(reason, CloseReason::Closed);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_application_object_path() {
+        assert_eq!(
+            application_object_path("org.gnome.Char-Map"),
+            "/org/gnome/Char_Map"
+        );
+        assert_eq!(
+            application_object_path("org.example.App"),
+            "/org/example/App"
+        );
+    }
+
+    #[test]
+    fn test_app_action_arguments() {
+        let origin = origin(vec![
+            (
+                DEFAULT_ACTION_KEY,
+                "app.open",
+                Some(HintValue::String("U+2603".to_string())),
+            ),
+            ("button-0", "app.dismiss", None),
+            ("button-1", "win.close", None),
+        ]);
+
+        let (name, parameter) = app_action(&origin, DEFAULT_ACTION_KEY).unwrap();
+        assert_eq!(name, "open");
+        assert_eq!(parameter, vec![Value::from("U+2603")]);
+
+        let (name, parameter) = app_action(&origin, "button-0").unwrap();
+        assert_eq!(name, "dismiss");
+        assert!(parameter.is_empty());
+
+        // Window actions and unknown keys fall back to plain activation
+        assert!(app_action(&origin, "button-1").is_none());
+        assert!(app_action(&origin, "missing").is_none());
+    }
+}
@@ -1,7 +1,22 @@
 // D-Bus notification listener using iced Subscription pattern
 //
-// This module implements a subscription-based D-Bus listener that monitors
-// for org.freedesktop.Notifications signals on the session bus.
+// This module implements a subscription-based D-Bus listener that mirrors
+// org.freedesktop.Notifications traffic on the session bus.
+//
+// Listening strategy:
+// 1. Monitor mode: org.freedesktop.DBus.Monitoring.BecomeMonitor captures the
+//    Notify and CloseNotification method calls sent to whichever daemon owns
+//    the notification name, along with that daemon's replies and signals.
+// 2. Signal mode (fallback): when the bus refuses monitoring, a match rule for
+//    org.freedesktop.Notifications signals is used instead.
+//
+// Both modes yield NotificationEvents, so withdrawn notifications
+// (NotificationClosed, CloseNotification) reach the application too.
+// Monitor mode also captures the desktop portal's AddNotification and
+// RemoveNotification calls to its backend, so notifications from sandboxed
+// apps are shown as well (see `portal`). Backends that forward them to the
+// notification daemon produce a Notify call for the same notification; that
+// copy is shown instead, so each notification appears once.
 //
 // Architecture: Uses iced's Subscription pattern instead of separate threads.
 // This integrates directly with the iced event loop for automatic lifecycle
@@ -11,15 +26,21 @@
 
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use chrono::Local;
 use cosmic::iced;
-use futures::stream::{Stream, StreamExt};
-use zbus::zvariant::OwnedValue;
+use futures::stream::{BoxStream, Stream, StreamExt};
+use zbus::fdo::{DBusProxy, MonitoringProxy};
+use zbus::names::BusName;
 use zbus::{Connection, MatchRule, MessageStream, MessageType};
 
-use crate::dbus::types::{parse_actions, parse_hints, Notification};
+use crate::dbus::metrics::{ListenerMetrics, LISTENER_METRICS};
+use crate::dbus::portal::{
+    notification_from_portal, removal_from_portal, AddNotificationArgs, PORTAL_BACKEND_INTERFACE,
+};
+use crate::dbus::sender::CloseReason;
+use crate::dbus::server::NOTIFICATIONS_BUS_NAME;
+use crate::dbus::types::{ListenerStatus, Notification, NotificationEvent, NotifyArgs};
 
 /// Notification buffer size for backpressure management
 ///
@@ -48,15 +69,16 @@ const INITIAL_RECONNECT_DELAY_MS: u64 = 100;
 /// - Maximum outage tolerance: ~5 minutes before user notices
 /// - Log spam prevention: 30s interval generates ~10 log entries per 5 minutes
 ///
-/// If D-Bus is down longer than 5 minutes, user intervention is likely needed anyway.
+/// The listener never gives up: during a longer outage it keeps retrying at
+/// this interval, so notifications resume once the bus is back.
 const MAX_RECONNECT_DELAY_MS: u64 = 30_000;
 
-/// Maximum connection retry attempts before giving up
+/// How long a listening session must last to count as healthy
 ///
-/// After this many failed attempts, the listener stops trying to reconnect.
-/// User can restart the panel to retry. Prevents infinite resource consumption
-/// during permanent D-Bus failures (system shutdown, service removal).
-const MAX_CONNECTION_RETRIES: u32 = 10;
+/// A session that ends sooner (e.g. the bus accepts the connection and drops
+/// it straight away) backs off like a failed connection attempt, so a
+/// flapping bus doesn't turn reconnection into a busy loop.
+const MIN_HEALTHY_SESSION: Duration = Duration::from_secs(5);
 
 /// Reconnection backoff multiplier
 ///
@@ -64,6 +86,20 @@ const MAX_CONNECTION_RETRIES: u32 = 10;
 /// Value of 2.0 provides exponential backoff: 100ms → 200ms → 400ms → ...
 const RECONNECT_BACKOFF_MULTIPLIER: f64 = 2.0;
 
+/// How long a monitored Notify call waits for the daemon's reply
+///
+/// Daemons answer Notify immediately, so a missing reply after this long
+/// means it was lost (e.g. the daemon crashed or the monitor queue overflowed).
+/// The notification is then shown with a fallback ID rather than not at all.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a portal notification waits for its backend to forward it
+///
+/// Backends such as xdg-desktop-portal-gtk pass each portal notification on
+/// as a Notify call right away, and that copy is shown instead. Portal
+/// notifications that no backend forwards are shown once this passes.
+const PORTAL_FORWARD_TIMEOUT: Duration = Duration::from_millis(500);
+
 /// Subscription ID for the notification listener
 /// This ensures only one listener instance exists
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -75,12 +111,12 @@ impl Hash for ListenerSubscription {
     }
 }
 
-/// Create a subscription that listens for D-Bus notification signals
+/// Create a subscription that listens for D-Bus notification traffic
 ///
 /// This function returns an iced Subscription that will automatically:
 /// - Connect to the D-Bus session bus
-/// - Subscribe to org.freedesktop.Notifications signals
-/// - Parse incoming notifications
+/// - Monitor (or subscribe to) org.freedesktop.Notifications traffic
+/// - Parse notifications, closures and action invocations
 /// - Yield them as Messages to the application
 ///
 /// The subscription is managed by iced's runtime - no manual cleanup needed.
@@ -98,7 +134,7 @@ impl Hash for ListenerSubscription {
 /// ```
 pub fn subscribe<Message>() -> iced::Subscription<Message>
 where
-    Message: 'static + Send + Clone + From<Notification>,
+    Message: 'static + Send + Clone + From<NotificationEvent>,
 {
     iced::Subscription::run_with_id(
         ListenerSubscription,
@@ -111,155 +147,852 @@ where
 /// Create an async stream of notifications from D-Bus with automatic reconnection
 ///
 /// This is the core async function that:
-/// 1. Connects to the D-Bus session bus (with retry logic)
-/// 2. Sets up a match rule for notification signals
-/// 3. Creates a MessageStream to receive signals
-/// 4. Parses each signal into a Notification
-/// 5. Yields notifications as a stream
-/// 6. Automatically reconnects on connection drop (unfold triggers reconnection)
+/// 1. Connects to the D-Bus session bus
+/// 2. Becomes a bus monitor for Notify calls, or falls back to a signal match rule
+/// 3. Creates a MessageStream to receive the matched messages
+/// 4. Parses each message into a NotificationEvent
+/// 5. Yields events as a stream
+/// 6. Starts a new session whenever the current one ends
 ///
 /// Uses a simpler two-layer approach:
-/// - Outer unfold: Manages connection lifecycle (connect → stream → reconnect)
-/// - Inner stream: Processes notifications from current connection
+/// - Outer unfold: Supervises sessions (connect → stream → reconnect), forever
+/// - Inner stream: Processes notifications from the current session
 ///
-/// When the connection drops, unfold automatically calls retry_connect() again.
-async fn notification_stream() -> impl Stream<Item = Notification> {
-    futures::stream::unfold((), |_| async {
-        // Attempt connection with exponential backoff
-        let connection = retry_connect().await?;
+/// A session ends when the connection drops, or when the owner of
+/// org.freedesktop.Notifications changes (the daemon restarted), since the
+/// monitor's match rules name the old daemon. Each session starts by
+/// reporting `ListenerStatus::Connected`; every failed attempt reports
+/// `ListenerStatus::Disconnected` before backing off.
+async fn notification_stream() -> impl Stream<Item = NotificationEvent> {
+    futures::stream::unfold(Supervisor::new(), |mut supervisor| async move {
+        if let Some(delay) = supervisor.delay_before_attempt(Instant::now()) {
+            tokio::time::sleep(delay).await;
+        }
 
-        // Create notification stream for this connection
-        let stream = create_notification_stream(connection).await?;
+        let events = match start_session().await {
+            Ok(session) => {
+                supervisor.session_started(Instant::now());
+                futures::stream::once(async {
+                    NotificationEvent::Status(ListenerStatus::Connected)
+                })
+                .chain(session)
+                .boxed()
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to listen on the session bus: {} (attempt {}, retrying)",
+                    e,
+                    supervisor.failed_attempts + 1
+                );
+                supervisor.attempt_failed();
+                futures::stream::once(async {
+                    NotificationEvent::Status(ListenerStatus::Disconnected)
+                })
+                .boxed()
+            }
+        };
 
-        // When stream ends (connection drop), unfold calls this function again
-        Some((stream, ()))
+        // When the events end (session over), unfold calls this function again
+        Some((events, supervisor))
     })
     .flatten()
     .boxed()
 }
 
-/// Attempt to connect to D-Bus with exponential backoff
+/// Connect to the session bus and start listening for notification traffic
+async fn start_session() -> zbus::Result<BoxStream<'static, NotificationEvent>> {
+    let connection = Connection::session().await?;
+    tracing::info!("Connected to D-Bus session bus");
+
+    // Prefer monitoring; a refused BecomeMonitor leaves the connection usable
+    match create_monitor_stream(&connection).await {
+        Ok(stream) => Ok(stream),
+        Err(e) => {
+            tracing::warn!(
+                "Bus refused monitoring: {} (falling back to signal listener)",
+                e
+            );
+            create_notification_stream(connection).await
+        }
+    }
+}
+
+/// How the previous listening attempt ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LastAttempt {
+    /// Nothing was attempted yet
+    None,
+    /// Connecting or setting up the stream failed
+    Failed,
+    /// A session started at this instant and has since ended
+    Session(Instant),
+}
+
+/// Decides when the listener tries again
 ///
-/// Retries up to MAX_CONNECTION_RETRIES times with exponential backoff.
-/// Returns None if all retry attempts fail (listener will stop).
-async fn retry_connect() -> Option<Connection> {
-    let mut delay_ms = INITIAL_RECONNECT_DELAY_MS;
+/// Reconnects right away after a healthy session, and with exponential
+/// backoff (capped at MAX_RECONNECT_DELAY_MS) after failures or sessions
+/// shorter than MIN_HEALTHY_SESSION. There is no retry limit.
+#[derive(Debug)]
+struct Supervisor {
+    last: LastAttempt,
+    delay_ms: u64,
+    failed_attempts: u32,
+}
 
-    for attempt in 1..=MAX_CONNECTION_RETRIES {
-        match Connection::session().await {
-            Ok(conn) => {
-                tracing::info!(
-                    "Connected to D-Bus session bus (attempt {}/{})",
-                    attempt,
-                    MAX_CONNECTION_RETRIES
-                );
-                return Some(conn);
+impl Supervisor {
+    fn new() -> Self {
+        Self {
+            last: LastAttempt::None,
+            delay_ms: INITIAL_RECONNECT_DELAY_MS,
+            failed_attempts: 0,
+        }
+    }
+
+    /// How long to wait before the next attempt, if at all
+    fn delay_before_attempt(&mut self, now: Instant) -> Option<Duration> {
+        match self.last {
+            LastAttempt::None => None,
+            LastAttempt::Session(started) if now.duration_since(started) >= MIN_HEALTHY_SESSION => {
+                tracing::info!("Notification listener session ended, reconnecting");
+                self.delay_ms = INITIAL_RECONNECT_DELAY_MS;
+                None
             }
-            Err(e) => {
-                tracing::warn!(
-                    "Connection attempt {}/{} failed: {} (retrying in {}ms)",
-                    attempt,
-                    MAX_CONNECTION_RETRIES,
-                    e,
-                    delay_ms
+            LastAttempt::Session(_) | LastAttempt::Failed => {
+                let delay = Duration::from_millis(self.delay_ms);
+                self.delay_ms = ((self.delay_ms as f64 * RECONNECT_BACKOFF_MULTIPLIER) as u64)
+                    .min(MAX_RECONNECT_DELAY_MS);
+                Some(delay)
+            }
+        }
+    }
+
+    fn session_started(&mut self, now: Instant) {
+        if self.last != LastAttempt::None {
+            LISTENER_METRICS.record_reconnect();
+        }
+        if self.failed_attempts > 0 {
+            tracing::info!(
+                "Notification listener recovered after {} failed attempt(s)",
+                self.failed_attempts
+            );
+        }
+        self.last = LastAttempt::Session(now);
+        self.failed_attempts = 0;
+    }
+
+    fn attempt_failed(&mut self) {
+        self.last = LastAttempt::Failed;
+        self.failed_attempts = self.failed_attempts.saturating_add(1);
+    }
+}
+
+/// Match rule for ownership changes of org.freedesktop.Notifications
+fn owner_changed_rule() -> zbus::Result<MatchRule<'static>> {
+    Ok(MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")?
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg(0, NOTIFICATIONS_BUS_NAME)?
+        .build())
+}
+
+/// Whether a message reports a new owner for org.freedesktop.Notifications
+fn is_owner_change(message: &zbus::Message) -> bool {
+    let header = message.header();
+    message.message_type() == MessageType::Signal
+        && header.interface().map(|i| i.as_str()) == Some("org.freedesktop.DBus")
+        && header.member().map(|m| m.as_str()) == Some("NameOwnerChanged")
+        && message
+            .body()
+            .deserialize::<(String, String, String)>()
+            .is_ok_and(|(name, _, _)| name == NOTIFICATIONS_BUS_NAME)
+}
+
+/// Create a notification stream by turning the connection into a bus monitor
+///
+/// Captures `Notify` and `CloseNotification` method calls addressed to the
+/// notification daemon, plus the daemon's replies and signals. Replies are
+/// paired with `Notify` calls so notifications carry the ID the daemon
+/// assigned. The daemon is resolved before monitoring starts, because a
+/// monitor connection can no longer send messages.
+///
+/// Returns an error if the bus refuses `BecomeMonitor` (e.g. restrictive bus
+/// policy); the connection is then still a regular connection.
+async fn create_monitor_stream(
+    connection: &Connection,
+) -> zbus::Result<BoxStream<'static, NotificationEvent>> {
+    // Resolve the current daemon so its replies can be matched by sender
+    let daemon = DBusProxy::new(connection)
+        .await?
+        .get_name_owner(BusName::try_from(NOTIFICATIONS_BUS_NAME)?)
+        .await
+        .ok();
+
+    let mut rules = Vec::new();
+    for member in ["Notify", "CloseNotification"] {
+        rules.push(
+            MatchRule::builder()
+                .msg_type(MessageType::MethodCall)
+                .interface("org.freedesktop.Notifications")?
+                .member(member)?
+                .build(),
+        );
+    }
+
+    // Notifications from sandboxed apps, as the portal hands them to its backend
+    for member in ["AddNotification", "RemoveNotification"] {
+        rules.push(
+            MatchRule::builder()
+                .msg_type(MessageType::MethodCall)
+                .interface(PORTAL_BACKEND_INTERFACE)?
+                .member(member)?
+                .build(),
+        );
+    }
+
+    let signals = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .interface("org.freedesktop.Notifications")?;
+
+    // The daemon's replies carry the IDs it assigned; errors cancel a call
+    match &daemon {
+        Some(owner) => {
+            for msg_type in [MessageType::MethodReturn, MessageType::Error] {
+                rules.push(
+                    MatchRule::builder()
+                        .msg_type(msg_type)
+                        .sender(owner.as_str())?
+                        .build(),
                 );
+            }
+            rules.push(signals.sender(owner.as_str())?.build());
+        }
+        None => {
+            tracing::debug!(
+                "{} has no owner yet, monitoring calls and signals only",
+                NOTIFICATIONS_BUS_NAME
+            );
+            rules.push(signals.build());
+        }
+    }
 
-                // Wait before next retry
-                tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+    // A new daemon means new match rules: the session ends on owner changes
+    rules.push(owner_changed_rule()?);
 
-                // Calculate next delay with exponential backoff
-                delay_ms = ((delay_ms as f64 * RECONNECT_BACKOFF_MULTIPLIER) as u64)
-                    .min(MAX_RECONNECT_DELAY_MS);
+    MonitoringProxy::new(connection)
+        .await?
+        .become_monitor(&rules, 0)
+        .await?;
+
+    tracing::info!("Monitoring notification traffic on the session bus");
+
+    // A monitor receives everything on the unfiltered connection queue
+    let mut monitor = connection.clone();
+    monitor.set_max_queued(NOTIFICATION_BUFFER_SIZE);
+
+    let pairing = NotifyPairing::new(daemon.is_some());
+    let messages = measure_queue(MessageStream::from(monitor), pairing.metrics);
+    Ok(pair_notifications(messages, pairing))
+}
+
+/// Turn monitored messages into events, pairing Notify calls with replies
+///
+/// A Notify call is held back until the daemon's method return arrives, so the
+/// emitted Notification carries the ID the sending app got back. Calls whose
+/// reply doesn't arrive within REPLY_TIMEOUT are emitted with a locally
+/// generated ID instead. Portal notifications are emitted once
+/// PORTAL_FORWARD_TIMEOUT passes without the backend forwarding them. Other
+/// calls and signals are emitted as they arrive.
+///
+/// The stream ends when the notification daemon's owner changes, after
+/// emitting the calls still waiting for a reply with fallback IDs.
+fn pair_notifications(
+    messages: BoxStream<'static, zbus::Result<zbus::Message>>,
+    pairing: NotifyPairing,
+) -> BoxStream<'static, NotificationEvent> {
+    futures::stream::unfold(
+        (messages, pairing, false),
+        |(mut messages, mut pairing, owner_changed)| async move {
+            if owner_changed {
+                return None;
+            }
+
+            loop {
+                let message = match pairing.next_deadline() {
+                    Some(deadline) => {
+                        let deadline = tokio::time::Instant::from_std(deadline);
+                        match tokio::time::timeout_at(deadline, messages.next()).await {
+                            Ok(message) => message,
+                            Err(_) => {
+                                let expired: Vec<NotificationEvent> = pairing
+                                    .expire(Instant::now())
+                                    .into_iter()
+                                    .map(NotificationEvent::from)
+                                    .collect();
+                                if expired.is_empty() {
+                                    continue;
+                                }
+                                pairing.metrics.record_parsed(expired.len());
+                                return Some((expired, (messages, pairing, false)));
+                            }
+                        }
+                    }
+                    None => messages.next().await,
+                };
+
+                // Stream ended (connection drop): the outer unfold reconnects
+                let ready = match message? {
+                    Ok(msg) if is_owner_change(&msg) => {
+                        tracing::info!(
+                            "{} changed owner, monitoring the new daemon",
+                            NOTIFICATIONS_BUS_NAME
+                        );
+                        let ready: Vec<NotificationEvent> = pairing
+                            .drain()
+                            .into_iter()
+                            .map(NotificationEvent::from)
+                            .collect();
+                        pairing.metrics.record_parsed(ready.len());
+                        return Some((ready, (messages, pairing, true)));
+                    }
+                    Ok(msg) => {
+                        pairing.metrics.record_received();
+                        pairing.handle_message(&msg, Instant::now())
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to receive D-Bus message: {}", e);
+                        pairing.metrics.record_receive_error();
+                        Vec::new()
+                    }
+                };
+
+                if !ready.is_empty() {
+                    pairing.metrics.record_parsed(ready.len());
+                    return Some((ready, (messages, pairing, false)));
+                }
             }
+        },
+    )
+    .flat_map(futures::stream::iter)
+    .boxed()
+}
+
+/// A Notify call waiting for the daemon's reply
+struct PendingNotify {
+    args: NotifyArgs,
+    received: Instant,
+}
+
+/// A portal notification waiting to see whether its backend forwards it
+struct PendingPortal {
+    notification: Notification,
+    /// Unique name of the backend the portal called, when it was addressed by one
+    backend: Option<String>,
+    received: Instant,
+}
+
+/// Matches Notify calls to method returns by (caller, serial)
+///
+/// The daemon's reply is addressed to the caller's unique name and carries the
+/// call's serial as its reply serial; together they identify the call.
+///
+/// Portal notifications are held back for PORTAL_FORWARD_TIMEOUT too, and
+/// dropped when the backend forwards them as a Notify call.
+struct NotifyPairing {
+    /// Whether replies are monitored at all (false when the daemon is unknown)
+    enabled: bool,
+    pending: HashMap<(String, u32), PendingNotify>,
+    /// Portal notifications by ID, until forwarded or overdue
+    portal: HashMap<u32, PendingPortal>,
+    /// Where parse failures, unpaired evictions and reply timeouts are counted
+    metrics: &'static ListenerMetrics,
+}
+
+impl NotifyPairing {
+    fn new(enabled: bool) -> Self {
+        Self::with_metrics(enabled, &LISTENER_METRICS)
+    }
+
+    fn with_metrics(enabled: bool, metrics: &'static ListenerMetrics) -> Self {
+        Self {
+            enabled,
+            pending: HashMap::new(),
+            portal: HashMap::new(),
+            metrics,
         }
     }
 
-    // All retry attempts exhausted
-    tracing::error!(
-        "Failed to connect to D-Bus after {} attempts, giving up",
-        MAX_CONNECTION_RETRIES
-    );
-    None
+    /// Handle one monitored message, returning events ready to emit
+    fn handle_message(&mut self, message: &zbus::Message, now: Instant) -> Vec<NotificationEvent> {
+        let header = message.header();
+
+        match message.message_type() {
+            MessageType::MethodCall
+                if header.interface().map(|i| i.as_str()) == Some(PORTAL_BACKEND_INTERFACE) =>
+            {
+                match parse_portal_call(message) {
+                    Ok(NotificationEvent::Notify(notification)) => {
+                        let backend = header
+                            .destination()
+                            .map(|backend| backend.to_string())
+                            .filter(|backend| backend.starts_with(':'));
+                        self.hold_portal(*notification, backend, now);
+                        Vec::new()
+                    }
+                    // Withdrawn before it was shown: nothing to close
+                    Ok(NotificationEvent::Closed { id, .. })
+                        if self.portal.remove(&id).is_some() =>
+                    {
+                        Vec::new()
+                    }
+                    Ok(event) => vec![event],
+                    Err(e) => {
+                        tracing::warn!("Failed to parse portal notification call: {}", e);
+                        self.metrics.record_parse_failure();
+                        Vec::new()
+                    }
+                }
+            }
+            MessageType::MethodCall
+                if header.member().map(|m| m.as_str()) == Some("CloseNotification") =>
+            {
+                match message.body().deserialize::<u32>() {
+                    Ok(id) => vec![NotificationEvent::Closed {
+                        id,
+                        reason: CloseReason::Closed,
+                    }],
+                    Err(e) => {
+                        tracing::warn!("Failed to parse CloseNotification call: {}", e);
+                        self.metrics.record_parse_failure();
+                        Vec::new()
+                    }
+                }
+            }
+            MessageType::MethodCall => {
+                let args = match parse_notify_args(message) {
+                    Ok(args) => args,
+                    Err(e) => {
+                        tracing::warn!("Failed to parse Notify call: {}", e);
+                        self.metrics.record_parse_failure();
+                        return Vec::new();
+                    }
+                };
+
+                let expects_reply = !message
+                    .primary_header()
+                    .flags()
+                    .contains(zbus::message::Flags::NoReplyExpected);
+
+                let caller = header.sender().map(|caller| caller.to_string());
+                if let Some(forwarded) = self.take_forwarded(&args, caller.as_deref()) {
+                    tracing::debug!(
+                        "Portal notification {} was forwarded to the daemon, showing that copy",
+                        forwarded.id
+                    );
+                }
+
+                let ready = match caller {
+                    Some(caller) if expects_reply => self.insert(
+                        caller,
+                        message.primary_header().serial_num().get(),
+                        args,
+                        now,
+                    ),
+                    caller => vec![fallback_notification(args, caller)],
+                };
+                ready.into_iter().map(NotificationEvent::from).collect()
+            }
+            MessageType::MethodReturn => {
+                let (Some(caller), Some(reply_serial)) =
+                    (header.destination(), header.reply_serial())
+                else {
+                    return Vec::new();
+                };
+
+                match message.body().deserialize::<u32>() {
+                    Ok(id) => self
+                        .resolve(caller.as_str(), reply_serial.get(), id)
+                        .map(NotificationEvent::from)
+                        .into_iter()
+                        .collect(),
+                    // Replies to other calls on the daemon don't carry a u32
+                    Err(_) => Vec::new(),
+                }
+            }
+            MessageType::Error => {
+                if let (Some(caller), Some(reply_serial)) =
+                    (header.destination(), header.reply_serial())
+                {
+                    if self.discard(caller.as_str(), reply_serial.get()) {
+                        tracing::debug!("Daemon rejected Notify call from {}", caller);
+                    }
+                }
+                Vec::new()
+            }
+            // The bus itself signals the monitor (e.g. NameLost once monitoring starts)
+            MessageType::Signal
+                if header.interface().map(|i| i.as_str())
+                    != Some("org.freedesktop.Notifications") =>
+            {
+                Vec::new()
+            }
+            MessageType::Signal => match parse_signal(message) {
+                Ok(event) => vec![event],
+                Err(e) => {
+                    tracing::warn!("Failed to parse notification signal: {}", e);
+                    self.metrics.record_parse_failure();
+                    Vec::new()
+                }
+            },
+        }
+    }
+
+    /// Hold a Notify call until its reply arrives
+    ///
+    /// Without reply monitoring the call is emitted immediately. When too many
+    /// calls are pending, the oldest one is emitted with a fallback ID.
+    fn insert(
+        &mut self,
+        caller: String,
+        serial: u32,
+        args: NotifyArgs,
+        now: Instant,
+    ) -> Vec<Notification> {
+        if !self.enabled {
+            return vec![fallback_notification(args, Some(caller))];
+        }
+
+        let mut ready = Vec::new();
+        if self.pending.len() >= NOTIFICATION_BUFFER_SIZE {
+            let oldest = self
+                .pending
+                .iter()
+                .min_by_key(|(_, pending)| pending.received)
+                .map(|(key, _)| key.clone());
+            if let Some(((caller, _), pending)) =
+                oldest.and_then(|key| self.pending.remove_entry(&key))
+            {
+                tracing::warn!("Notify backlog full, showing the oldest call with a fallback ID");
+                self.metrics.record_unpaired_eviction();
+                ready.push(fallback_notification(pending.args, Some(caller)));
+            }
+        }
+
+        self.pending.insert(
+            (caller, serial),
+            PendingNotify {
+                args,
+                received: now,
+            },
+        );
+        ready
+    }
+
+    /// Complete a pending call with the ID returned by the daemon
+    fn resolve(&mut self, caller: &str, reply_serial: u32, id: u32) -> Option<Notification> {
+        let pending = self.pending.remove(&(caller.to_string(), reply_serial))?;
+        let mut notification = Notification::from_notify_args(id, pending.args);
+        notification.sender = Some(caller.to_string());
+        Some(notification)
+    }
+
+    /// Drop a pending call the daemon answered with an error
+    fn discard(&mut self, caller: &str, reply_serial: u32) -> bool {
+        self.pending
+            .remove(&(caller.to_string(), reply_serial))
+            .is_some()
+    }
+
+    /// Hold a portal notification until its backend had time to forward it
+    ///
+    /// A notification re-added under the same portal ID replaces the held one.
+    fn hold_portal(&mut self, notification: Notification, backend: Option<String>, now: Instant) {
+        self.portal.insert(
+            notification.id,
+            PendingPortal {
+                notification,
+                backend,
+                received: now,
+            },
+        );
+    }
+
+    /// Take the held portal notification a Notify call forwards, if any
+    ///
+    /// The call must come from the backend the portal addressed (when it was
+    /// addressed by unique name), name the portal app as its app name or
+    /// desktop entry, and repeat the title as its summary.
+    fn take_forwarded(&mut self, args: &NotifyArgs, caller: Option<&str>) -> Option<Notification> {
+        let (app_name, summary, hints) = (&args.0, &args.3, &args.6);
+        let desktop_entry = hints
+            .get("desktop-entry")
+            .and_then(|entry| entry.downcast_ref::<&str>().ok());
+
+        let id = self
+            .portal
+            .iter()
+            .filter(|(_, pending)| {
+                pending.backend.is_none() || pending.backend.as_deref() == caller
+            })
+            .filter(|(_, pending)| {
+                let app_id = pending.notification.app_name.as_str();
+                (app_name == app_id || desktop_entry == Some(app_id))
+                    && pending.notification.summary == *summary
+            })
+            .min_by_key(|(_, pending)| pending.received)
+            .map(|(id, _)| *id)?;
+        self.portal.remove(&id).map(|pending| pending.notification)
+    }
+
+    /// Emit overdue calls with a fallback ID, and portal notifications that
+    /// no backend forwarded in time
+    fn expire(&mut self, now: Instant) -> Vec<Notification> {
+        let overdue: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.received) >= REPLY_TIMEOUT)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        if !overdue.is_empty() {
+            tracing::debug!(
+                "{} Notify call(s) got no reply in time, using fallback IDs",
+                overdue.len()
+            );
+            self.metrics.record_reply_timeouts(overdue.len());
+        }
+
+        let unforwarded: Vec<u32> = self
+            .portal
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.received) >= PORTAL_FORWARD_TIMEOUT)
+            .map(|(id, _)| *id)
+            .collect();
+
+        overdue
+            .into_iter()
+            .filter_map(|key| self.pending.remove_entry(&key))
+            .map(|((caller, _), pending)| fallback_notification(pending.args, Some(caller)))
+            .chain(
+                unforwarded
+                    .into_iter()
+                    .filter_map(|id| self.portal.remove(&id))
+                    .map(|pending| pending.notification),
+            )
+            .collect()
+    }
+
+    /// Emit every pending call with a fallback ID (its daemon went away),
+    /// along with the held portal notifications
+    fn drain(&mut self) -> Vec<Notification> {
+        self.metrics.record_reply_timeouts(self.pending.len());
+        self.pending
+            .drain()
+            .map(|((caller, _), pending)| fallback_notification(pending.args, Some(caller)))
+            .chain(self.portal.drain().map(|(_, pending)| pending.notification))
+            .collect()
+    }
+
+    /// When the oldest pending call or portal notification times out
+    fn next_deadline(&self) -> Option<Instant> {
+        let calls = self
+            .pending
+            .values()
+            .map(|pending| pending.received + REPLY_TIMEOUT);
+        let portal = self
+            .portal
+            .values()
+            .map(|pending| pending.received + PORTAL_FORWARD_TIMEOUT);
+        calls.chain(portal).min()
+    }
 }
 
 /// Create a notification stream from an established D-Bus connection
 ///
-/// Sets up the match rule and message stream for the connection.
-/// Returns None if setup fails (will trigger reconnection attempt).
+/// Sets up the match rules and message streams for the connection. The
+/// stream ends when the notification daemon's owner changes, so the
+/// supervisor subscribes afresh. Returns an error if setup fails (will
+/// trigger a reconnection attempt).
 async fn create_notification_stream(
     connection: Connection,
-) -> Option<impl Stream<Item = Notification>> {
+) -> zbus::Result<BoxStream<'static, NotificationEvent>> {
     // Create match rule for org.freedesktop.Notifications signals
-    let match_rule = match MatchRule::builder()
+    let match_rule = MatchRule::builder()
         .msg_type(MessageType::Signal)
-        .interface("org.freedesktop.Notifications")
-    {
-        Ok(builder) => builder.build(),
-        Err(e) => {
-            tracing::error!("Failed to build match rule: {} (will retry connection)", e);
-            return None;
-        }
-    };
+        .interface("org.freedesktop.Notifications")?
+        .build();
 
     // Create message stream with configured buffer size
-    let message_stream = match MessageStream::for_match_rule(
-        match_rule,
-        &connection,
-        Some(NOTIFICATION_BUFFER_SIZE),
-    )
-    .await
-    {
-        Ok(stream) => {
-            tracing::info!("Subscribed to notification signals");
-            stream
-        }
-        Err(e) => {
-            tracing::error!(
-                "Failed to create message stream: {} (will retry connection)",
-                e
-            );
-            return None;
-        }
+    let message_stream =
+        MessageStream::for_match_rule(match_rule, &connection, Some(NOTIFICATION_BUFFER_SIZE))
+            .await?;
+    let mut owner_changes =
+        MessageStream::for_match_rule(owner_changed_rule()?, &connection, Some(1)).await?;
+    tracing::info!("Subscribed to notification signals");
+
+    let owner_changed = async move {
+        owner_changes.next().await;
+        tracing::info!(
+            "{} changed owner, subscribing again",
+            NOTIFICATIONS_BUS_NAME
+        );
     };
 
-    // Transform D-Bus messages into Notifications
-    //
-    // Note: We use filter_map with nested match instead of try_filter_map because
-    // we want to continue processing notifications even when some fail to parse.
-    // This ensures one malformed notification doesn't block the entire stream.
-    // Errors are logged but don't propagate to the caller.
-    Some(
-        message_stream
-            .filter_map(|message| async move {
-                // Handle Result from message stream
-                match message {
-                    Ok(msg) => match parse_notification_signal(msg) {
-                        Ok(notification) => {
-                            tracing::debug!(
-                                "Received notification: {} from {}",
-                                notification.summary,
-                                notification.app_name
-                            );
-                            Some(notification)
+    Ok(
+        parse_signals(measure_queue(message_stream, &LISTENER_METRICS))
+            .take_until(owner_changed)
+            .boxed(),
+    )
+}
+
+/// Pass messages through, recording how many were waiting in the queue
+///
+/// Messages are taken in batches of whatever is queued without waiting, so
+/// each batch is the queue depth at that moment. A batch that fills the
+/// stream's capacity counts as a stall: zbus stops reading the socket while
+/// the queue is full, and the messages behind it are received late.
+fn measure_queue(
+    messages: MessageStream,
+    metrics: &'static ListenerMetrics,
+) -> BoxStream<'static, zbus::Result<zbus::Message>> {
+    let capacity = messages.max_queued();
+    batch_queued(messages, capacity, metrics)
+}
+
+/// Yield `messages` in order, recording the depth of each ready batch
+fn batch_queued<S>(
+    messages: S,
+    capacity: usize,
+    metrics: &'static ListenerMetrics,
+) -> BoxStream<'static, S::Item>
+where
+    S: Stream + Send + 'static,
+    S::Item: Send,
+{
+    messages
+        .ready_chunks(capacity)
+        .inspect(move |batch| metrics.record_queue_depth(batch.len(), capacity))
+        .flat_map(futures::stream::iter)
+        .boxed()
+}
+
+/// Transform D-Bus signals into NotificationEvents
+///
+/// Note: We use filter_map with nested match instead of try_filter_map because
+/// we want to continue processing notifications even when some fail to parse.
+/// This ensures one malformed notification doesn't block the entire stream.
+/// Errors are logged and counted in LISTENER_METRICS, but don't propagate
+/// to the caller.
+fn parse_signals(
+    messages: impl Stream<Item = zbus::Result<zbus::Message>> + Send + 'static,
+) -> BoxStream<'static, NotificationEvent> {
+    messages
+        .filter_map(|message| async move {
+            // Handle Result from message stream
+            match message {
+                Ok(msg) => {
+                    LISTENER_METRICS.record_received();
+                    match parse_signal(&msg) {
+                        Ok(event) => {
+                            tracing::debug!("Received notification signal: {:?}", event);
+                            LISTENER_METRICS.record_parsed(1);
+                            Some(event)
                         }
                         Err(e) => {
                             tracing::warn!("Failed to parse notification signal: {}", e);
+                            LISTENER_METRICS.record_parse_failure();
                             None
                         }
-                    },
-                    Err(e) => {
-                        tracing::warn!("Failed to receive D-Bus message: {}", e);
-                        None
                     }
                 }
-            })
-            .boxed(),
-    )
+                Err(e) => {
+                    tracing::warn!("Failed to receive D-Bus message: {}", e);
+                    LISTENER_METRICS.record_receive_error();
+                    None
+                }
+            }
+        })
+        .boxed()
 }
 
-/// Parse a D-Bus message into a Notification
+/// Parse an org.freedesktop.Notifications signal into an event
+///
+/// Handles the signals defined by the specification:
+/// - NotificationClosed: (UINT32 id, UINT32 reason)
+/// - ActionInvoked: (UINT32 id, STRING action_key)
+/// - ActivationToken: (UINT32 id, STRING activation_token)
 ///
-/// This function extracts the Notify signal parameters:
+/// `Notify` is accepted as a signal too, for daemons that re-broadcast
+/// notifications that way; its body matches the method call. Such
+/// notifications get a fallback ID.
+///
+/// Reference: https://specifications.freedesktop.org/notification-spec/latest/protocol.html#signals
+fn parse_signal(message: &zbus::Message) -> Result<NotificationEvent, NotificationError> {
+    let header = message.header();
+    let member = header.member().ok_or(NotificationError::MissingMember)?;
+    let body = message.body();
+
+    let event = match member.as_str() {
+        "Notify" => fallback_notification(parse_notify_args(message)?, None).into(),
+        "NotificationClosed" => {
+            let (id, reason): (u32, u32) = body
+                .deserialize()
+                .map_err(NotificationError::DeserializeFailed)?;
+            NotificationEvent::Closed {
+                id,
+                reason: CloseReason::from(reason),
+            }
+        }
+        "ActionInvoked" => {
+            let (id, action_key): (u32, String) = body
+                .deserialize()
+                .map_err(NotificationError::DeserializeFailed)?;
+            NotificationEvent::ActionInvoked { id, action_key }
+        }
+        "ActivationToken" => {
+            let (id, token): (u32, String) = body
+                .deserialize()
+                .map_err(NotificationError::DeserializeFailed)?;
+            NotificationEvent::ActivationToken { id, token }
+        }
+        other => return Err(NotificationError::UnexpectedMember(other.to_string())),
+    };
+
+    Ok(event)
+}
+
+/// Parse a call from the desktop portal to its notification backend
+///
+/// `AddNotification(app_id, id, notification)` becomes a notification and
+/// `RemoveNotification(app_id, id)` a close event for it.
+fn parse_portal_call(message: &zbus::Message) -> Result<NotificationEvent, NotificationError> {
+    let header = message.header();
+    let member = header.member().ok_or(NotificationError::MissingMember)?;
+    let body = message.body();
+
+    match member.as_str() {
+        "AddNotification" => {
+            let args: AddNotificationArgs = body
+                .deserialize()
+                .map_err(NotificationError::DeserializeFailed)?;
+            // The caller is the portal, not the app: no freedesktop sender
+            Ok(notification_from_portal(args).into())
+        }
+        "RemoveNotification" => {
+            let (app_id, id): (String, String) = body
+                .deserialize()
+                .map_err(NotificationError::DeserializeFailed)?;
+            Ok(removal_from_portal(&app_id, &id))
+        }
+        other => Err(NotificationError::UnexpectedMember(other.to_string())),
+    }
+}
+
+/// Extract the Notify arguments from a Notify call or signal
+///
+/// The body carries the eight Notify parameters:
 /// - app_name: String
 /// - replaces_id: u32
 /// - app_icon: String
@@ -270,61 +1003,40 @@ async fn create_notification_stream(
 /// - expire_timeout: i32
 ///
 /// Reference: https://specifications.freedesktop.org/notification-spec/latest/ar01s09.html
-fn parse_notification_signal(message: zbus::Message) -> Result<Notification, NotificationError> {
-    // Verify this is a Notify signal
+fn parse_notify_args(message: &zbus::Message) -> Result<NotifyArgs, NotificationError> {
+    // Verify this is a Notify message
     let header = message.header();
     let member = header.member().ok_or(NotificationError::MissingMember)?;
 
     if member.as_str() != "Notify" {
-        return Err(NotificationError::UnexpectedSignal(
+        return Err(NotificationError::UnexpectedMember(
             member.as_str().to_string(),
         ));
     }
 
-    // Extract and destructure D-Bus signal parameters (8 parameters in Notify signal)
-    #[allow(clippy::type_complexity)]
-    let (
-        app_name,
-        replaces_id,
-        app_icon,
-        summary,
-        body_text,
-        actions_raw,
-        hints_raw,
-        expire_timeout,
-    ): (
-        String,
-        u32,
-        String,
-        String,
-        String,
-        Vec<String>,
-        HashMap<String, OwnedValue>,
-        i32,
-    ) = message
+    // Extract D-Bus parameters (8 parameters in Notify)
+    message
         .body()
         .deserialize()
-        .map_err(NotificationError::DeserializeFailed)?;
-
-    Ok(Notification {
-        id: generate_notification_id(&app_name, &summary),
-        app_name,
-        replaces_id,
-        app_icon,
-        summary,
-        body: body_text,
-        actions: parse_actions(&actions_raw),
-        hints: parse_hints(&hints_raw),
-        raw_hints: hints_raw,
-        expire_timeout,
-        timestamp: Local::now(),
-    })
+        .map_err(NotificationError::DeserializeFailed)
+}
+
+/// Build a notification with a locally generated ID
+///
+/// Last resort for when the daemon's reply is unavailable (signal mode,
+/// unknown daemon, no-reply calls or timeouts). The ID won't match the one
+/// the sending app received.
+fn fallback_notification(args: NotifyArgs, sender: Option<String>) -> Notification {
+    let id = generate_notification_id(&args.0, &args.3);
+    let mut notification = Notification::from_notify_args(id, args);
+    notification.sender = sender;
+    notification
 }
 
-/// Generate a notification ID
+/// Generate a fallback notification ID
 ///
-/// For now, this is a simple hash-based approach.
-/// In a real implementation, the notification manager would assign IDs.
+/// Hash of app name, summary and the current time. Only used when the
+/// daemon-assigned ID is unavailable.
 fn generate_notification_id(app_name: &str, summary: &str) -> u32 {
     use std::collections::hash_map::DefaultHasher;
 
@@ -347,8 +1059,8 @@ enum NotificationError {
     #[error("D-Bus message missing member field")]
     MissingMember,
 
-    #[error("Unexpected signal: {0}, expected 'Notify'")]
-    UnexpectedSignal(String),
+    #[error("Unexpected member: {0}, expected a notification signal or Notify")]
+    UnexpectedMember(String),
 
     #[error("Failed to deserialize notification body: {0}")]
     DeserializeFailed(zbus::Error),
@@ -358,6 +1070,537 @@ enum NotificationError {
 mod tests {
     use super::*;
 
+    fn notify_call(caller: &str, summary: &str) -> zbus::Message {
+        let args: NotifyArgs = (
+            "test-app".to_string(),
+            0,
+            String::new(),
+            summary.to_string(),
+            String::new(),
+            vec![],
+            HashMap::new(),
+            -1,
+        );
+
+        zbus::Message::method("/org/freedesktop/Notifications", "Notify")
+            .unwrap()
+            .sender(caller)
+            .unwrap()
+            .destination(NOTIFICATIONS_BUS_NAME)
+            .unwrap()
+            .interface("org.freedesktop.Notifications")
+            .unwrap()
+            .build(&args)
+            .unwrap()
+    }
+
+    fn signal<B>(member: &str, body: &B) -> zbus::Message
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        zbus::Message::signal(
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications",
+            member,
+        )
+        .unwrap()
+        .build(body)
+        .unwrap()
+    }
+
+    fn notification(event: &NotificationEvent) -> &Notification {
+        match event {
+            NotificationEvent::Notify(notification) => notification,
+            other => panic!("expected a notification, got {:?}", other),
+        }
+    }
+
+    fn notify_reply(call: &zbus::Message, id: u32) -> zbus::Message {
+        zbus::Message::method_reply(call)
+            .unwrap()
+            .build(&id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_pairing_uses_daemon_id() {
+        let mut pairing = NotifyPairing::new(true);
+        let now = Instant::now();
+
+        let call = notify_call(":1.42", "Hello");
+        assert!(pairing.handle_message(&call, now).is_empty());
+
+        let ready = pairing.handle_message(&notify_reply(&call, 7), now);
+        assert_eq!(ready.len(), 1);
+        assert_eq!(notification(&ready[0]).id, 7);
+        assert_eq!(notification(&ready[0]).summary, "Hello");
+        assert_eq!(notification(&ready[0]).sender.as_deref(), Some(":1.42"));
+        assert!(pairing.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_pairing_matches_reply_to_its_call() {
+        let mut pairing = NotifyPairing::new(true);
+        let now = Instant::now();
+
+        let first = notify_call(":1.42", "First");
+        let second = notify_call(":1.43", "Second");
+        pairing.handle_message(&first, now);
+        pairing.handle_message(&second, now);
+
+        let ready = pairing.handle_message(&notify_reply(&second, 2), now);
+        assert_eq!(notification(&ready[0]).summary, "Second");
+        assert_eq!(notification(&ready[0]).id, 2);
+
+        let ready = pairing.handle_message(&notify_reply(&first, 1), now);
+        assert_eq!(notification(&ready[0]).summary, "First");
+        assert_eq!(notification(&ready[0]).id, 1);
+    }
+
+    #[test]
+    fn test_pairing_drops_rejected_calls() {
+        let mut pairing = NotifyPairing::new(true);
+        let now = Instant::now();
+
+        let call = notify_call(":1.42", "Rejected");
+        pairing.handle_message(&call, now);
+
+        let error = zbus::Message::method_error(&call, "org.freedesktop.DBus.Error.Failed")
+            .unwrap()
+            .build(&("rejected",))
+            .unwrap();
+        assert!(pairing.handle_message(&error, now).is_empty());
+        assert!(pairing.expire(now + REPLY_TIMEOUT).is_empty());
+    }
+
+    #[test]
+    fn test_pairing_falls_back_after_timeout() {
+        let mut pairing = NotifyPairing::new(true);
+        let now = Instant::now();
+
+        pairing.handle_message(&notify_call(":1.42", "Lost reply"), now);
+        assert_eq!(pairing.next_deadline(), Some(now + REPLY_TIMEOUT));
+        assert!(pairing.expire(now).is_empty());
+
+        let expired = pairing.expire(now + REPLY_TIMEOUT);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].summary, "Lost reply");
+        assert_eq!(expired[0].sender.as_deref(), Some(":1.42"));
+        assert!(pairing.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_pairing_disabled_emits_immediately() {
+        let mut pairing = NotifyPairing::new(false);
+
+        let ready = pairing.handle_message(&notify_call(":1.42", "Unpaired"), Instant::now());
+        assert_eq!(ready.len(), 1);
+        assert_eq!(notification(&ready[0]).summary, "Unpaired");
+    }
+
+    #[test]
+    fn test_pairing_ignores_unrelated_replies() {
+        let mut pairing = NotifyPairing::new(true);
+        let now = Instant::now();
+
+        let call = notify_call(":1.42", "Pending");
+        pairing.handle_message(&call, now);
+
+        let other = notify_call(":1.99", "Unmonitored");
+        assert!(pairing
+            .handle_message(&notify_reply(&other, 5), now)
+            .is_empty());
+        assert!(pairing.next_deadline().is_some());
+    }
+
+    #[test]
+    fn test_monitored_close_notification_call() {
+        let mut pairing = NotifyPairing::new(true);
+
+        let call = zbus::Message::method("/org/freedesktop/Notifications", "CloseNotification")
+            .unwrap()
+            .sender(":1.42")
+            .unwrap()
+            .interface("org.freedesktop.Notifications")
+            .unwrap()
+            .build(&(7u32,))
+            .unwrap();
+
+        match pairing.handle_message(&call, Instant::now()).as_slice() {
+            [NotificationEvent::Closed { id, reason }] => {
+                assert_eq!(*id, 7);
+                assert_eq!(*reason, CloseReason::Closed);
+            }
+            other => panic!("unexpected events: {:?}", other),
+        }
+    }
+
+    /// A call from a stand-in portal (`:1.7`) to its notification backend (`:1.9`)
+    fn portal_call<B>(member: &str, body: &B) -> zbus::Message
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        zbus::Message::method("/org/freedesktop/portal/desktop", member)
+            .unwrap()
+            .sender(":1.7")
+            .unwrap()
+            .destination(":1.9")
+            .unwrap()
+            .interface(PORTAL_BACKEND_INTERFACE)
+            .unwrap()
+            .build(body)
+            .unwrap()
+    }
+
+    fn portal_add(title: &str) -> zbus::Message {
+        let mut fields: HashMap<String, zbus::zvariant::OwnedValue> = HashMap::new();
+        fields.insert(
+            "title".to_string(),
+            zbus::zvariant::Value::from(title).try_into().unwrap(),
+        );
+        portal_call("AddNotification", &("org.example.App", "download", fields))
+    }
+
+    /// A Notify call for the portal app, as its backend would forward it
+    fn forwarded_call(caller: &str, title: &str) -> zbus::Message {
+        let mut hints: HashMap<String, zbus::zvariant::OwnedValue> = HashMap::new();
+        hints.insert(
+            "desktop-entry".to_string(),
+            zbus::zvariant::Value::from("org.example.App")
+                .try_into()
+                .unwrap(),
+        );
+        let args: NotifyArgs = (
+            "Example".to_string(),
+            0,
+            String::new(),
+            title.to_string(),
+            String::new(),
+            vec![],
+            hints,
+            -1,
+        );
+
+        zbus::Message::method("/org/freedesktop/Notifications", "Notify")
+            .unwrap()
+            .sender(caller)
+            .unwrap()
+            .destination(NOTIFICATIONS_BUS_NAME)
+            .unwrap()
+            .interface("org.freedesktop.Notifications")
+            .unwrap()
+            .build(&args)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_monitored_portal_notifications() {
+        let mut pairing = NotifyPairing::new(true);
+        let now = Instant::now();
+
+        // Held back in case the backend forwards it, then shown as is
+        assert!(pairing
+            .handle_message(&portal_add("Sandboxed"), now)
+            .is_empty());
+        assert_eq!(pairing.next_deadline(), Some(now + PORTAL_FORWARD_TIMEOUT));
+
+        let ready = pairing.expire(now + PORTAL_FORWARD_TIMEOUT);
+        assert_eq!(ready.len(), 1);
+        let added = &ready[0];
+        assert_eq!(added.summary, "Sandboxed");
+        assert_eq!(added.app_name, "org.example.App");
+        assert_eq!(added.sender, None);
+        assert!(added.portal.is_some());
+        let id = added.id;
+
+        let remove = portal_call("RemoveNotification", &("org.example.App", "download"));
+        match pairing.handle_message(&remove, now).as_slice() {
+            [NotificationEvent::Closed { id: closed, reason }] => {
+                assert_eq!(*closed, id);
+                assert_eq!(*reason, CloseReason::Closed);
+            }
+            other => panic!("unexpected events: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_forwarded_portal_notification_is_shown_once() {
+        let mut pairing = NotifyPairing::new(true);
+        let now = Instant::now();
+
+        let mut events = pairing.handle_message(&portal_add("Sandboxed"), now);
+        let forwarded = forwarded_call(":1.9", "Sandboxed");
+        events.extend(pairing.handle_message(&forwarded, now));
+        events.extend(pairing.handle_message(&notify_reply(&forwarded, 12), now));
+        events.extend(
+            pairing
+                .expire(now + PORTAL_FORWARD_TIMEOUT)
+                .into_iter()
+                .map(NotificationEvent::from),
+        );
+
+        // Only the daemon's copy, which the backend handles actions for
+        assert_eq!(events.len(), 1);
+        assert_eq!(notification(&events[0]).id, 12);
+        assert_eq!(notification(&events[0]).sender.as_deref(), Some(":1.9"));
+        assert!(pairing.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_portal_notification_not_matched_by_other_calls() {
+        let mut pairing = NotifyPairing::new(false);
+        let now = Instant::now();
+
+        pairing.handle_message(&portal_add("Sandboxed"), now);
+
+        // Another title, or the same one from someone other than the backend
+        assert_eq!(
+            pairing
+                .handle_message(&forwarded_call(":1.9", "Other"), now)
+                .len(),
+            1
+        );
+        assert_eq!(
+            pairing
+                .handle_message(&forwarded_call(":1.42", "Sandboxed"), now)
+                .len(),
+            1
+        );
+        assert_eq!(pairing.expire(now + PORTAL_FORWARD_TIMEOUT).len(), 1);
+    }
+
+    #[test]
+    fn test_portal_notification_withdrawn_before_shown() {
+        let mut pairing = NotifyPairing::new(true);
+        let now = Instant::now();
+
+        pairing.handle_message(&portal_add("Sandboxed"), now);
+        let remove = portal_call("RemoveNotification", &("org.example.App", "download"));
+        assert!(pairing.handle_message(&remove, now).is_empty());
+        assert!(pairing.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_monitor_ignores_bus_signals() {
+        let mut pairing = NotifyPairing::new(true);
+
+        let name_lost =
+            zbus::Message::signal("/org/freedesktop/DBus", "org.freedesktop.DBus", "NameLost")
+                .unwrap()
+                .build(&(":1.42",))
+                .unwrap();
+        assert!(pairing
+            .handle_message(&name_lost, Instant::now())
+            .is_empty());
+
+        let closed = signal("NotificationClosed", &(7u32, 2u32));
+        assert_eq!(pairing.handle_message(&closed, Instant::now()).len(), 1);
+    }
+
+    fn owner_changed(name: &str, old: &str, new: &str) -> zbus::Message {
+        zbus::Message::signal(
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "NameOwnerChanged",
+        )
+        .unwrap()
+        .sender("org.freedesktop.DBus")
+        .unwrap()
+        .build(&(name, old, new))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_is_owner_change() {
+        assert!(is_owner_change(&owner_changed(
+            NOTIFICATIONS_BUS_NAME,
+            ":1.10",
+            ":1.20"
+        )));
+        assert!(is_owner_change(&owner_changed(
+            NOTIFICATIONS_BUS_NAME,
+            ":1.10",
+            ""
+        )));
+
+        // Other names, and the daemon's own signals, don't end the session
+        assert!(!is_owner_change(&owner_changed(
+            "org.example.Other",
+            "",
+            ":1.20"
+        )));
+        assert!(!is_owner_change(&signal(
+            "NotificationClosed",
+            &(7u32, 2u32)
+        )));
+    }
+
+    #[test]
+    fn test_drain_emits_pending_calls() {
+        let mut pairing = NotifyPairing::new(true);
+        let now = Instant::now();
+
+        pairing.handle_message(&notify_call(":1.42", "Unanswered"), now);
+
+        let drained = pairing.drain();
+        assert_eq!(drained.len(), 1);
+        assert_eq!(drained[0].summary, "Unanswered");
+        assert_eq!(drained[0].sender.as_deref(), Some(":1.42"));
+        assert!(pairing.next_deadline().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_queue_depth_is_measured() {
+        static METRICS: ListenerMetrics = ListenerMetrics::new();
+
+        // Everything is queued at once: batches of 2, 2 and 1
+        let queued = futures::stream::iter(1..=5);
+        let passed: Vec<_> = batch_queued(queued, 2, &METRICS).collect().await;
+        assert_eq!(passed, vec![1, 2, 3, 4, 5]);
+
+        let snapshot = METRICS.snapshot();
+        assert_eq!(snapshot.peak_queue_depth, 2);
+        assert_eq!(snapshot.queue_stalls, 2);
+    }
+
+    #[test]
+    fn test_pairing_counts_losses() {
+        static METRICS: ListenerMetrics = ListenerMetrics::new();
+        let mut pairing = NotifyPairing::with_metrics(true, &METRICS);
+        let now = Instant::now();
+
+        // One more call than the backlog holds pushes the oldest out
+        for serial in 0..=NOTIFICATION_BUFFER_SIZE {
+            pairing.handle_message(&notify_call(":1.42", &format!("Call {serial}")), now);
+        }
+        assert_eq!(METRICS.snapshot().unpaired_evictions, 1);
+
+        let malformed = signal("NotificationClosed", &("seven",));
+        pairing.handle_message(&malformed, now);
+        assert_eq!(METRICS.snapshot().parse_failures, 1);
+
+        let expired = pairing.expire(now + REPLY_TIMEOUT);
+        assert_eq!(expired.len(), NOTIFICATION_BUFFER_SIZE);
+        assert_eq!(
+            METRICS.snapshot().reply_timeouts,
+            NOTIFICATION_BUFFER_SIZE as u64
+        );
+    }
+
+    #[test]
+    fn test_supervisor_backs_off_without_limit() {
+        let mut supervisor = Supervisor::new();
+        let now = Instant::now();
+
+        // The first attempt is immediate
+        assert_eq!(supervisor.delay_before_attempt(now), None);
+
+        let mut delays = Vec::new();
+        for _ in 0..20 {
+            supervisor.attempt_failed();
+            delays.push(supervisor.delay_before_attempt(now).unwrap());
+        }
+
+        assert_eq!(delays[0], Duration::from_millis(INITIAL_RECONNECT_DELAY_MS));
+        assert_eq!(
+            delays[1],
+            Duration::from_millis(INITIAL_RECONNECT_DELAY_MS * 2)
+        );
+        assert!(delays.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(
+            delays.last().copied(),
+            Some(Duration::from_millis(MAX_RECONNECT_DELAY_MS))
+        );
+        assert_eq!(supervisor.failed_attempts, 20);
+    }
+
+    #[test]
+    fn test_supervisor_reconnects_after_healthy_session() {
+        let mut supervisor = Supervisor::new();
+        let now = Instant::now();
+
+        for _ in 0..5 {
+            supervisor.attempt_failed();
+            supervisor.delay_before_attempt(now);
+        }
+
+        // A long session resets the backoff and reconnects right away
+        supervisor.session_started(now);
+        assert_eq!(supervisor.failed_attempts, 0);
+        assert_eq!(
+            supervisor.delay_before_attempt(now + MIN_HEALTHY_SESSION),
+            None
+        );
+
+        supervisor.attempt_failed();
+        assert_eq!(
+            supervisor.delay_before_attempt(now),
+            Some(Duration::from_millis(INITIAL_RECONNECT_DELAY_MS))
+        );
+    }
+
+    #[test]
+    fn test_supervisor_backs_off_after_short_session() {
+        let mut supervisor = Supervisor::new();
+        let now = Instant::now();
+
+        supervisor.session_started(now);
+        assert_eq!(
+            supervisor.delay_before_attempt(now + Duration::from_millis(10)),
+            Some(Duration::from_millis(INITIAL_RECONNECT_DELAY_MS))
+        );
+    }
+
+    #[test]
+    fn test_parse_notification_closed_signal() {
+        let message = signal("NotificationClosed", &(7u32, 1u32));
+
+        match parse_signal(&message).expect("signal should parse") {
+            NotificationEvent::Closed { id, reason } => {
+                assert_eq!(id, 7);
+                assert_eq!(reason, CloseReason::Expired);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_action_signals() {
+        let message = signal("ActionInvoked", &(7u32, "reply"));
+        match parse_signal(&message).expect("signal should parse") {
+            NotificationEvent::ActionInvoked { id, action_key } => {
+                assert_eq!(id, 7);
+                assert_eq!(action_key, "reply");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let message = signal("ActivationToken", &(7u32, "token-123"));
+        match parse_signal(&message).expect("signal should parse") {
+            NotificationEvent::ActivationToken { id, token } => {
+                assert_eq!(id, 7);
+                assert_eq!(token, "token-123");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_signal_rejects_unknown_members() {
+        let message = signal("SomethingElse", &(7u32,));
+        assert!(matches!(
+            parse_signal(&message),
+            Err(NotificationError::UnexpectedMember(_))
+        ));
+
+        // Malformed bodies are reported, not panicked on
+        let message = signal("NotificationClosed", &("seven",));
+        assert!(matches!(
+            parse_signal(&message),
+            Err(NotificationError::DeserializeFailed(_))
+        ));
+    }
+
     #[test]
     fn test_generate_notification_id() {
         let id1 = generate_notification_id("firefox", "Download complete");
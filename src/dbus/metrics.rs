@@ -0,0 +1,189 @@
+// Listener health metrics
+//
+// Counts what happens to the D-Bus traffic the passive listener sees, so
+// "missing notification" reports can be told apart: never received, failed
+// to parse, received late behind a full message queue, shown without its
+// reply, or lost across a reconnect.
+//
+// The counters are process-wide atomics, read by the control interface
+// (`ListenerMetrics`) and the popup's diagnostics section. They only move
+// while the listener runs; daemon mode receives notifications directly.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+use zbus::zvariant::Type;
+
+/// Counters of the running notification listener
+pub static LISTENER_METRICS: ListenerMetrics = ListenerMetrics::new();
+
+/// Health counters of a notification listener
+///
+/// Updated by the listener with relaxed atomics; `snapshot` reads them.
+#[derive(Debug, Default)]
+pub struct ListenerMetrics {
+    received: AtomicU64,
+    parsed: AtomicU64,
+    parse_failures: AtomicU64,
+    receive_errors: AtomicU64,
+    unpaired_evictions: AtomicU64,
+    reply_timeouts: AtomicU64,
+    reconnects: AtomicU64,
+    queue_stalls: AtomicU64,
+    peak_queue_depth: AtomicU64,
+}
+
+impl ListenerMetrics {
+    pub const fn new() -> Self {
+        Self {
+            received: AtomicU64::new(0),
+            parsed: AtomicU64::new(0),
+            parse_failures: AtomicU64::new(0),
+            receive_errors: AtomicU64::new(0),
+            unpaired_evictions: AtomicU64::new(0),
+            reply_timeouts: AtomicU64::new(0),
+            reconnects: AtomicU64::new(0),
+            queue_stalls: AtomicU64::new(0),
+            peak_queue_depth: AtomicU64::new(0),
+        }
+    }
+
+    /// A D-Bus message reached the listener
+    pub(crate) fn record_received(&self) {
+        self.received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Events were produced from received messages
+    pub(crate) fn record_parsed(&self, events: usize) {
+        self.parsed.fetch_add(events as u64, Ordering::Relaxed);
+    }
+
+    /// A message could not be parsed and was skipped
+    pub(crate) fn record_parse_failure(&self) {
+        self.parse_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The message stream reported an error instead of a message
+    pub(crate) fn record_receive_error(&self) {
+        self.receive_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A Notify call was evicted from the full reply backlog before pairing
+    pub(crate) fn record_unpaired_eviction(&self) {
+        self.unpaired_evictions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Notify calls were shown with a fallback ID for want of a reply
+    pub(crate) fn record_reply_timeouts(&self, calls: usize) {
+        self.reply_timeouts
+            .fetch_add(calls as u64, Ordering::Relaxed);
+    }
+
+    /// The listener started a new session after losing the previous one
+    pub(crate) fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The listener found `depth` messages waiting in a stream queue
+    /// holding `capacity`
+    ///
+    /// A full queue stops zbus reading the socket, so the messages behind it
+    /// reach the listener late.
+    pub(crate) fn record_queue_depth(&self, depth: usize, capacity: usize) {
+        self.peak_queue_depth
+            .fetch_max(depth as u64, Ordering::Relaxed);
+        if depth >= capacity {
+            self.queue_stalls.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Current values of all counters
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            received: self.received.load(Ordering::Relaxed),
+            parsed: self.parsed.load(Ordering::Relaxed),
+            parse_failures: self.parse_failures.load(Ordering::Relaxed),
+            receive_errors: self.receive_errors.load(Ordering::Relaxed),
+            unpaired_evictions: self.unpaired_evictions.load(Ordering::Relaxed),
+            reply_timeouts: self.reply_timeouts.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            queue_stalls: self.queue_stalls.load(Ordering::Relaxed),
+            peak_queue_depth: self.peak_queue_depth.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Listener counters at one point in time
+///
+/// Sent by the control interface as `(ttttttttt)`, in field order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct MetricsSnapshot {
+    /// D-Bus messages the listener received
+    pub received: u64,
+    /// Notification events produced from them
+    pub parsed: u64,
+    /// Messages skipped because they could not be parsed
+    pub parse_failures: u64,
+    /// Errors the message stream reported instead of a message
+    pub receive_errors: u64,
+    /// Notify calls evicted from the full reply backlog before their reply
+    /// was paired (shown with a fallback ID)
+    pub unpaired_evictions: u64,
+    /// Notify calls whose reply never arrived (shown with a fallback ID)
+    pub reply_timeouts: u64,
+    /// Sessions started after an earlier session ended or an attempt failed
+    pub reconnects: u64,
+    /// Times the listener found its message queue full, delaying later messages
+    pub queue_stalls: u64,
+    /// Most messages found waiting in the queue at once
+    pub peak_queue_depth: u64,
+}
+
+impl MetricsSnapshot {
+    /// Messages that did not turn into a notification as sent
+    pub fn problems(&self) -> u64 {
+        self.parse_failures + self.receive_errors + self.unpaired_evictions + self.reply_timeouts
+    }
+}
+
+/// Current counters of the running notification listener
+pub fn listener_metrics() -> MetricsSnapshot {
+    LISTENER_METRICS.snapshot()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_reflects_records() {
+        let metrics = ListenerMetrics::new();
+        metrics.record_received();
+        metrics.record_received();
+        metrics.record_parsed(2);
+        metrics.record_parse_failure();
+        metrics.record_unpaired_eviction();
+        metrics.record_reply_timeouts(3);
+        metrics.record_reconnect();
+        metrics.record_queue_depth(12, 128);
+        metrics.record_queue_depth(128, 128);
+        metrics.record_queue_depth(1, 128);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.received, 2);
+        assert_eq!(snapshot.parsed, 2);
+        assert_eq!(snapshot.parse_failures, 1);
+        assert_eq!(snapshot.receive_errors, 0);
+        assert_eq!(snapshot.unpaired_evictions, 1);
+        assert_eq!(snapshot.reply_timeouts, 3);
+        assert_eq!(snapshot.reconnects, 1);
+        assert_eq!(snapshot.queue_stalls, 1);
+        assert_eq!(snapshot.peak_queue_depth, 128);
+        assert_eq!(snapshot.problems(), 5);
+    }
+
+    #[test]
+    fn test_snapshot_signature() {
+        assert_eq!(MetricsSnapshot::signature(), "(ttttttttt)");
+    }
+}
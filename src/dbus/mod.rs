@@ -5,18 +5,43 @@
 //
 // Reference: https://specifications.freedesktop.org/notification-spec/latest/
 
+pub mod control;
+pub mod gtk;
 pub mod listener;
+pub mod metrics;
+pub mod portal;
+pub mod sanitize;
 pub mod sender;
+pub mod server;
 pub mod types;
 
 // Re-export commonly used types
 pub use types::{
-    parse_actions, parse_hints, ImageData, Notification, NotificationAction, NotificationHints,
-    Urgency,
+    convert_hints, parse_actions, parse_hints, AppIdentity, HintValue, ImageData, ListenerStatus,
+    Notification, NotificationAction, NotificationEvent, NotificationHints, NotifyArgs,
+    Truncations, Urgency, DEFAULT_ACTION_KEY, INLINE_REPLY_ACTION_KEY,
 };
 
 // Re-export listener subscription function
 pub use listener::subscribe;
 
+// Re-export listener health metrics
+pub use metrics::{listener_metrics, MetricsSnapshot};
+
+// Re-export GApplication activation
+pub use gtk::activate_action;
+
+// Re-export the sanitization stage
+pub use sanitize::sanitize_notification;
+
+// Re-export portal adapter types
+pub use portal::{PortalAction, PortalOrigin};
+
+// Re-export daemon mode server types
+pub use server::NotificationServer;
+
 // Re-export sender functions
-pub use sender::{send_action_invoked, send_notification_closed, CloseReason};
+pub use sender::{
+    close_on_daemon, send_action_invoked, send_activation_token, send_notification_closed,
+    send_notification_replied, CloseReason,
+};
@@ -0,0 +1,398 @@
+// Portal notification adapter
+//
+// Sandboxed apps post notifications through the XDG desktop portal
+// (org.freedesktop.portal.Notification). The portal forwards them to its
+// backend as `AddNotification(app_id, id, notification)` and
+// `RemoveNotification(app_id, id)` calls on
+// org.freedesktop.impl.portal.Notification; this module maps those calls
+// onto `Notification`s and close events.
+//
+// Reference: https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.impl.portal.Notification.html
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use zbus::zvariant::OwnedValue;
+
+use crate::dbus::sender::CloseReason;
+use crate::dbus::types::{
+    convert_hints, HintValue, Notification, NotificationAction, NotificationEvent,
+    NotificationHints, Urgency, DEFAULT_ACTION_KEY,
+};
+
+/// Interface the portal calls on its notification backend
+pub const PORTAL_BACKEND_INTERFACE: &str = "org.freedesktop.impl.portal.Notification";
+
+/// Bit set on IDs of portal notifications, keeping them apart from daemon IDs
+///
+/// Daemons count IDs up from 1, so they never reach this range in practice.
+const PORTAL_ID_BIT: u32 = 0x8000_0000;
+
+/// Where a portal notification came from, and what its actions mean
+///
+/// Also describes notifications from org.gtk.Notifications, which posts the
+/// same dictionary.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortalOrigin {
+    /// App ID of the sandboxed app (also its desktop entry ID)
+    pub app_id: String,
+
+    /// ID the app chose for the notification
+    pub portal_id: String,
+
+    /// Portal action name and target for each action key
+    ///
+    /// The default action is stored under `DEFAULT_ACTION_KEY` and buttons
+    /// under `button-<index>`, since several buttons may share an action
+    /// name with different targets.
+    pub actions: HashMap<String, PortalAction>,
+}
+
+/// An action as the portal describes it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortalAction {
+    /// Action name, e.g. "app.reply"
+    pub name: String,
+
+    /// Parameter to activate the action with, if any
+    pub target: Option<HintValue>,
+}
+
+/// Arguments of the backend's `AddNotification` call: `(app_id, id, notification)`
+pub type AddNotificationArgs = (String, String, HashMap<String, OwnedValue>);
+
+/// Get the notification ID for a portal notification
+///
+/// Derived from the app ID and the app's own ID, so re-adding a notification
+/// replaces it and `RemoveNotification` finds it again.
+pub fn portal_notification_id(app_id: &str, portal_id: &str) -> u32 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    app_id.hash(&mut hasher);
+    portal_id.hash(&mut hasher);
+    (hasher.finish() as u32) | PORTAL_ID_BIT
+}
+
+/// Build a notification from the backend's `AddNotification` arguments
+///
+/// Maps the portal fields:
+/// - `title`, `body` / `markup-body` → summary and body
+/// - `priority` → urgency (`high` counts as normal, `urgent` as critical)
+/// - `icon` (serialized GIcon) → `app_icon` for themed names and files
+/// - `buttons` and `default-action` → actions, keeping their names and
+///   targets in `portal` (buttons are keyed by their index)
+///
+/// The notification replaces any earlier one with the same portal ID.
+pub fn notification_from_portal(args: AddNotificationArgs) -> Notification {
+    let (app_id, portal_id, fields) = args;
+    let fields = convert_hints(&fields);
+    let string = |key: &str| fields.get(key).and_then(HintValue::as_str);
+
+    let id = portal_notification_id(&app_id, &portal_id);
+    let mut actions = Vec::new();
+    let mut portal_actions = HashMap::new();
+
+    if let Some(name) = string("default-action") {
+        actions.push(NotificationAction::new(DEFAULT_ACTION_KEY, ""));
+        portal_actions.insert(
+            DEFAULT_ACTION_KEY.to_string(),
+            PortalAction {
+                name: name.to_string(),
+                target: fields.get("default-action-target").cloned(),
+            },
+        );
+    }
+
+    if let Some(HintValue::Array(buttons)) = fields.get("buttons") {
+        for (index, button) in buttons.iter().enumerate() {
+            let (Some(label), Some(name)) = (
+                dict_get(button, "label").and_then(HintValue::as_str),
+                dict_get(button, "action").and_then(HintValue::as_str),
+            ) else {
+                tracing::warn!("Skipping portal button without label or action");
+                continue;
+            };
+
+            let key = format!("button-{index}");
+            actions.push(NotificationAction::new(&key, label));
+            portal_actions.insert(
+                key,
+                PortalAction {
+                    name: name.to_string(),
+                    target: dict_get(button, "target").cloned(),
+                },
+            );
+        }
+    }
+
+    // Plain bodies must not be read as markup
+    let body = match string("markup-body") {
+        Some(markup) => markup.to_string(),
+        None => escape_markup(string("body").unwrap_or_default()),
+    };
+
+    Notification {
+        id,
+        app_name: app_id.clone(),
+        replaces_id: id,
+        app_icon: fields.get("icon").and_then(icon_name).unwrap_or_default(),
+        summary: string("title").unwrap_or_default().to_string(),
+        body,
+        actions,
+        hints: NotificationHints {
+            urgency: string("priority").map(urgency).unwrap_or_default(),
+            desktop_entry: Some(app_id.clone()).filter(|app_id| !app_id.is_empty()),
+            ..Default::default()
+        },
+        raw_hints: fields,
+        expire_timeout: -1,
+        timestamp: Local::now(),
+        close_reason: None,
+        app_identity: None,
+        sender: None,
+        portal: Some(PortalOrigin {
+            app_id,
+            portal_id,
+            actions: portal_actions,
+        }),
+        truncated: Default::default(),
+    }
+}
+
+/// Build the close event for the backend's `RemoveNotification(app_id, id)` call
+pub fn removal_from_portal(app_id: &str, portal_id: &str) -> NotificationEvent {
+    NotificationEvent::Closed {
+        id: portal_notification_id(app_id, portal_id),
+        reason: CloseReason::Closed,
+    }
+}
+
+/// Map a portal priority to an urgency
+fn urgency(priority: &str) -> Urgency {
+    match priority {
+        "low" => Urgency::Low,
+        "urgent" => Urgency::Critical,
+        _ => Urgency::Normal,
+    }
+}
+
+/// Get an icon name or file URI from a serialized GIcon
+///
+/// Handles `("themed", as)` (first name), `("file", s)` and bare strings.
+/// Byte and file descriptor icons are not supported.
+fn icon_name(icon: &HintValue) -> Option<String> {
+    match icon {
+        HintValue::String(name) => Some(name.clone()),
+        HintValue::Struct(fields) => match fields.as_slice() {
+            [HintValue::String(kind), HintValue::Array(names)] if kind == "themed" => {
+                names.iter().find_map(HintValue::as_str).map(str::to_string)
+            }
+            [HintValue::String(kind), HintValue::String(uri)] if kind == "file" => {
+                Some(uri.clone())
+            }
+            [HintValue::String(kind), _] => {
+                tracing::debug!("Unsupported portal icon type '{}'", kind);
+                None
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Look up a string key in an `a{sv}` dictionary
+fn dict_get<'a>(dict: &'a HintValue, key: &str) -> Option<&'a HintValue> {
+    match dict {
+        HintValue::Dict(entries) => entries
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+/// Escape text so the body markup parser shows it verbatim
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::{Array, Dict, Signature, StructureBuilder, Value};
+
+    fn owned(value: Value<'_>) -> OwnedValue {
+        OwnedValue::try_from(value).unwrap()
+    }
+
+    fn button(label: &str, action: &str, target: Option<&str>) -> Value<'static> {
+        let mut dict = Dict::new(
+            Signature::from_static_str_unchecked("s"),
+            Signature::from_static_str_unchecked("v"),
+        );
+        dict.add("label", Value::from(label.to_string())).unwrap();
+        dict.add("action", Value::from(action.to_string())).unwrap();
+        if let Some(target) = target {
+            dict.add("target", Value::from(target.to_string())).unwrap();
+        }
+        Value::Dict(dict)
+    }
+
+    fn add_args(fields: Vec<(&str, Value<'static>)>) -> AddNotificationArgs {
+        (
+            "org.example.Chat".to_string(),
+            "new-message".to_string(),
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), owned(value)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_portal_fields() {
+        let icon = StructureBuilder::new()
+            .add_field("themed")
+            .add_field(vec!["chat-symbolic", "chat"])
+            .build();
+
+        let notification = notification_from_portal(add_args(vec![
+            ("title", Value::from("Alice")),
+            ("body", Value::from("Is 1 < 2?")),
+            ("priority", Value::from("urgent")),
+            ("icon", Value::from(icon)),
+        ]));
+
+        assert_eq!(
+            notification.id,
+            portal_notification_id("org.example.Chat", "new-message")
+        );
+        assert_eq!(notification.replaces_id, notification.id);
+        assert_eq!(notification.summary, "Alice");
+        assert_eq!(notification.body, "Is 1 &lt; 2?");
+        assert_eq!(notification.urgency(), Urgency::Critical);
+        assert_eq!(notification.app_icon, "chat-symbolic");
+        assert_eq!(notification.desktop_entry(), Some("org.example.Chat"));
+        assert!(notification.actions.is_empty());
+    }
+
+    #[test]
+    fn test_portal_markup_body_and_priority() {
+        let notification = notification_from_portal(add_args(vec![
+            ("body", Value::from("plain")),
+            ("markup-body", Value::from("<b>bold</b>")),
+            ("priority", Value::from("low")),
+        ]));
+        assert_eq!(notification.body, "<b>bold</b>");
+        assert_eq!(notification.urgency(), Urgency::Low);
+
+        let notification =
+            notification_from_portal(add_args(vec![("priority", Value::from("high"))]));
+        assert_eq!(notification.urgency(), Urgency::Normal);
+    }
+
+    #[test]
+    fn test_portal_actions_keep_targets() {
+        let mut buttons = Array::new(Signature::from_static_str_unchecked("a{sv}"));
+        buttons
+            .append(button("Reply", "app.reply", Some("thread-7")))
+            .unwrap();
+        buttons.append(button("Mute", "app.mute", None)).unwrap();
+
+        let notification = notification_from_portal(add_args(vec![
+            ("default-action", Value::from("app.open")),
+            ("default-action-target", Value::from(7u32)),
+            ("buttons", Value::Array(buttons)),
+        ]));
+
+        assert_eq!(
+            notification.default_action().map(|a| a.key.as_str()),
+            Some(DEFAULT_ACTION_KEY)
+        );
+        let buttons: Vec<_> = notification
+            .button_actions()
+            .iter()
+            .map(|a| (a.key.as_str(), a.label.as_str()))
+            .collect();
+        assert_eq!(buttons, vec![("button-0", "Reply"), ("button-1", "Mute")]);
+
+        let portal = notification.portal.unwrap();
+        assert_eq!(
+            portal.actions[DEFAULT_ACTION_KEY],
+            PortalAction {
+                name: "app.open".to_string(),
+                target: Some(HintValue::UInt32(7)),
+            }
+        );
+        assert_eq!(
+            portal.actions["button-0"],
+            PortalAction {
+                name: "app.reply".to_string(),
+                target: Some(HintValue::String("thread-7".to_string())),
+            }
+        );
+        assert_eq!(portal.actions["button-1"].name, "app.mute");
+        assert_eq!(portal.actions["button-1"].target, None);
+    }
+
+    #[test]
+    fn test_portal_buttons_sharing_an_action() {
+        let mut buttons = Array::new(Signature::from_static_str_unchecked("a{sv}"));
+        buttons
+            .append(button("5 minutes", "app.snooze", Some("5")))
+            .unwrap();
+        buttons
+            .append(button("10 minutes", "app.snooze", Some("10")))
+            .unwrap();
+
+        let notification =
+            notification_from_portal(add_args(vec![("buttons", Value::Array(buttons))]));
+
+        let keys: Vec<_> = notification
+            .button_actions()
+            .iter()
+            .map(|a| a.key.clone())
+            .collect();
+        assert_eq!(keys, vec!["button-0", "button-1"]);
+
+        // Each button keeps its own target
+        let portal = notification.portal.unwrap();
+        let targets: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                (
+                    portal.actions[key].name.as_str(),
+                    &portal.actions[key].target,
+                )
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                ("app.snooze", &Some(HintValue::String("5".to_string()))),
+                ("app.snooze", &Some(HintValue::String("10".to_string()))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_portal_removal_matches_id() {
+        let notification = notification_from_portal(add_args(vec![]));
+        match removal_from_portal("org.example.Chat", "new-message") {
+            NotificationEvent::Closed { id, reason } => {
+                assert_eq!(id, notification.id);
+                assert_eq!(reason, CloseReason::Closed);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // Same portal ID from another app is a different notification
+        assert_ne!(
+            portal_notification_id("org.example.Other", "new-message"),
+            notification.id
+        );
+    }
+}
@@ -0,0 +1,522 @@
+// Sanitization of untrusted notification payloads
+//
+// Any process on the session bus can send a notification, with summaries,
+// bodies, action lists and images of any size. This stage runs on every
+// notification before it reaches the NotificationManager, so what is kept
+// in memory and written to the history file stays bounded:
+// - control and bidi-override characters are stripped from displayed text
+// - whitespace is normalized (single-line fields collapse to one line)
+// - text, action lists, names and vendor hints are capped
+// - attachments that would run when opened are refused
+// - images are bounded in byte size and downscaled to icon scale
+//
+// Whatever gets cut is recorded in `Notification::truncated`.
+
+use crate::dbus::types::{HintValue, Notification, NotificationAction};
+use crate::ui::url_parser::validate_attachment_url;
+
+/// Longest summary kept, in characters
+pub const MAX_SUMMARY_CHARS: usize = 256;
+
+/// Longest body kept, in characters (including markup)
+pub const MAX_BODY_CHARS: usize = 4096;
+
+/// Most action buttons kept
+///
+/// The `default` and `inline-reply` actions have no button and don't count.
+pub const MAX_ACTIONS: usize = 8;
+
+/// Longest action label kept, in characters
+pub const MAX_ACTION_LABEL_CHARS: usize = 64;
+
+/// Longest action key accepted, in bytes
+///
+/// Keys are sent back to the app as-is, so an overlong one is dropped with
+/// its action rather than shortened.
+pub const MAX_ACTION_KEY_LEN: usize = 256;
+
+/// Longest app name, display name or origin kept, in characters
+pub const MAX_NAME_CHARS: usize = 128;
+
+/// Longest icon name, path or URL accepted, in bytes
+pub const MAX_PATH_LEN: usize = 4096;
+
+/// Most attached files or links (`x-kde-urls`) kept
+pub const MAX_ATTACHMENTS: usize = 16;
+
+/// Largest image-data pixel buffer accepted, in bytes
+///
+/// Larger images are dropped without being decoded.
+pub const MAX_IMAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Largest image width or height kept, in pixels
+///
+/// Cards show images at icon size; larger images are downscaled to this.
+pub const MAX_IMAGE_SIZE: i32 = 256;
+
+/// Largest vendor hint kept, in (approximate) bytes
+pub const MAX_HINT_BYTES: usize = 4096;
+
+/// Sanitize a notification in place before it is shown and stored
+///
+/// Records in `notification.truncated` every part that was shortened or
+/// dropped. Stripping characters and normalizing whitespace alone doesn't
+/// count as truncation.
+pub fn sanitize_notification(notification: &mut Notification) {
+    let truncated = &mut notification.truncated;
+
+    truncated.summary |= clean_line(&mut notification.summary, MAX_SUMMARY_CHARS);
+    truncated.body |= clean_body(&mut notification.body, MAX_BODY_CHARS);
+    truncated.actions |= sanitize_actions(&mut notification.actions);
+
+    let hints = &mut notification.hints;
+    let mut hints_truncated = clean_line(&mut notification.app_name, MAX_NAME_CHARS);
+    for name in [&mut hints.display_app_name, &mut hints.origin_name]
+        .into_iter()
+        .flatten()
+    {
+        hints_truncated |= clean_line(name, MAX_NAME_CHARS);
+    }
+
+    for path in [
+        Some(&mut notification.app_icon),
+        hints.image_path.as_mut(),
+        hints.sound_file.as_mut(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if path.len() > MAX_PATH_LEN {
+            path.clear();
+            hints_truncated = true;
+        }
+    }
+
+    let attachments = hints.urls.len();
+    hints
+        .urls
+        .retain(|url| url.len() <= MAX_PATH_LEN && !url.chars().any(is_stripped));
+    hints.urls.truncate(MAX_ATTACHMENTS);
+    hints_truncated |= hints.urls.len() < attachments;
+    // Refused rather than shortened, so not recorded as truncation
+    hints.urls = hints
+        .urls
+        .drain(..)
+        .filter_map(|url| validate_attachment_url(&url))
+        .collect();
+
+    let raw_hints = notification.raw_hints.len();
+    notification
+        .raw_hints
+        .retain(|key, value| key.len() <= MAX_NAME_CHARS && hint_size(value) <= MAX_HINT_BYTES);
+    for value in notification.raw_hints.values_mut() {
+        if let HintValue::String(text) = value {
+            strip_characters(text);
+        }
+    }
+    hints_truncated |= notification.raw_hints.len() < raw_hints;
+    truncated.hints |= hints_truncated;
+
+    if let Some(image) = hints.image_data.take() {
+        if image.data.len() > MAX_IMAGE_BYTES {
+            tracing::debug!(
+                "Dropping {} byte image from {}",
+                image.data.len(),
+                notification.app_name
+            );
+            truncated.image = true;
+        } else if image.width > MAX_IMAGE_SIZE || image.height > MAX_IMAGE_SIZE {
+            hints.image_data = image.downscaled(MAX_IMAGE_SIZE);
+            truncated.image = true;
+        } else {
+            hints.image_data = Some(image);
+        }
+    }
+
+    if truncated.any() {
+        tracing::debug!(
+            "Notification {} from {} shortened: {}",
+            notification.id,
+            notification.app_name,
+            truncated.parts().join(", ")
+        );
+    }
+}
+
+/// Cap the action list and clean its labels
+///
+/// Keeps the `default` and `inline-reply` actions, plus up to MAX_ACTIONS
+/// buttons. Returns whether anything was dropped or shortened.
+fn sanitize_actions(actions: &mut Vec<NotificationAction>) -> bool {
+    let count = actions.len();
+    let mut buttons = 0;
+    actions.retain(|action| {
+        if action.key.len() > MAX_ACTION_KEY_LEN {
+            return false;
+        }
+        if action.is_default() || action.is_inline_reply() {
+            return true;
+        }
+        buttons += 1;
+        buttons <= MAX_ACTIONS
+    });
+
+    let mut truncated = actions.len() < count;
+    for action in actions.iter_mut() {
+        truncated |= clean_line(&mut action.label, MAX_ACTION_LABEL_CHARS);
+    }
+    truncated
+}
+
+/// Whether a character is removed from displayed text
+///
+/// Control characters other than newline and tab, and the bidi embedding,
+/// override and isolate controls that can make text render out of order.
+/// The body markup decoder drops them too, when written as entities.
+pub(crate) fn is_stripped(c: char) -> bool {
+    (c.is_control() && c != '\n' && c != '\t')
+        || matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Remove stripped characters, turning carriage returns into newlines
+fn strip_characters(text: &mut String) {
+    if text.chars().any(|c| c == '\r' || is_stripped(c)) {
+        *text = text
+            .replace("\r\n", "\n")
+            .replace('\r', "\n")
+            .chars()
+            .filter(|&c| !is_stripped(c))
+            .collect();
+    }
+}
+
+/// Remove numeric character references to stripped characters
+///
+/// Bodies are markup, so `&#x202E;` renders as the character itself once
+/// decoded. Other references are left for the markup parser.
+fn strip_character_references(text: &mut String) {
+    if !text.contains("&#") {
+        return;
+    }
+
+    let mut kept = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find("&#") {
+        kept.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let stripped = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .filter(|&end| numeric_reference(&rest[2..end]).is_some_and(is_stripped));
+        match stripped {
+            Some(end) => rest = &rest[end + 1..],
+            None => {
+                kept.push_str("&#");
+                rest = &rest[2..];
+            }
+        }
+    }
+    kept.push_str(rest);
+    *text = kept;
+}
+
+/// Decode the digits of a `&#NNN;` or `&#xHHH;` reference
+fn numeric_reference(digits: &str) -> Option<char> {
+    let code = match digits.strip_prefix(['x', 'X']) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    char::from_u32(code)
+}
+
+/// Clean a single-line field and cap it at `max_chars`
+///
+/// Every run of whitespace, line breaks included, becomes one space.
+/// Returns whether the text was shortened.
+fn clean_line(text: &mut String, max_chars: usize) -> bool {
+    strip_characters(text);
+    let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    *text = normalized;
+    truncate_chars(text, max_chars)
+}
+
+/// Clean a body and cap it at `max_chars`
+///
+/// Whitespace runs within a line become one space, and blank lines collapse
+/// into one. Stripped characters are removed whether written directly or as
+/// numeric entities. The cut avoids ending inside a markup tag or entity.
+/// Returns whether the body was shortened.
+fn clean_body(body: &mut String, max_chars: usize) -> bool {
+    strip_characters(body);
+    strip_character_references(body);
+
+    let mut lines: Vec<String> = Vec::new();
+    for line in body.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        let previous_blank = lines.last().is_none_or(|last| last.is_empty());
+        if !line.is_empty() || !previous_blank {
+            lines.push(line);
+        }
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    *body = lines.join("\n");
+
+    if !truncate_chars(body, max_chars) {
+        return false;
+    }
+
+    // Don't leave half a tag or entity before the ellipsis
+    let kept = &body[..body.len() - '…'.len_utf8()];
+    let open_tag = kept.rfind('<').filter(|&i| !kept[i..].contains('>'));
+    let open_entity = kept
+        .rfind('&')
+        .filter(|&i| !kept[i..].contains(';') && kept.len() - i <= 10);
+    if let Some(cut) = open_tag.into_iter().chain(open_entity).min() {
+        body.replace_range(cut..body.len() - '…'.len_utf8(), "");
+    }
+    true
+}
+
+/// Cap text at `max_chars` characters, ending it with an ellipsis if cut
+fn truncate_chars(text: &mut String, max_chars: usize) -> bool {
+    let Some((cut, _)) = text.char_indices().nth(max_chars) else {
+        return false;
+    };
+    // Make room for the ellipsis within the limit
+    let cut = text[..cut]
+        .char_indices()
+        .next_back()
+        .map_or(0, |(last, _)| last);
+    text.truncate(cut);
+    text.push('…');
+    true
+}
+
+/// Approximate serialized size of a hint value, in bytes
+fn hint_size(value: &HintValue) -> usize {
+    match value {
+        HintValue::String(text) => text.len(),
+        HintValue::Bytes(bytes) => bytes.len(),
+        HintValue::Array(values) | HintValue::Struct(values) => values.iter().map(hint_size).sum(),
+        HintValue::Dict(entries) => entries
+            .iter()
+            .map(|(key, value)| hint_size(key) + hint_size(value))
+            .sum(),
+        _ => 8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::types::ImageData;
+    use std::collections::HashMap;
+
+    fn notification(summary: &str, body: &str) -> Notification {
+        Notification::from_notify_args(
+            1,
+            (
+                "test-app".to_string(),
+                0,
+                String::new(),
+                summary.to_string(),
+                body.to_string(),
+                vec![],
+                HashMap::new(),
+                -1,
+            ),
+        )
+    }
+
+    fn image(width: i32, height: i32) -> ImageData {
+        ImageData {
+            width,
+            height,
+            rowstride: width * 4,
+            has_alpha: true,
+            bits_per_sample: 8,
+            channels: 4,
+            data: vec![0x80; (width * height * 4) as usize],
+        }
+    }
+
+    #[test]
+    fn test_short_notifications_are_untouched() {
+        let mut notif = notification("Build finished", "All <b>12</b> tests passed");
+        sanitize_notification(&mut notif);
+
+        assert_eq!(notif.summary, "Build finished");
+        assert_eq!(notif.body, "All <b>12</b> tests passed");
+        assert!(!notif.truncated.any());
+    }
+
+    #[test]
+    fn test_strips_control_and_bidi_characters() {
+        let mut notif = notification(
+            "Invoice\u{202E}fdp.exe\u{0007}",
+            "Line\u{0000} one\r\nLine \u{2066}two\u{2069}",
+        );
+        sanitize_notification(&mut notif);
+
+        assert_eq!(notif.summary, "Invoicefdp.exe");
+        assert_eq!(notif.body, "Line one\nLine two");
+        // Stripping alone isn't a truncation
+        assert!(!notif.truncated.any());
+    }
+
+    #[test]
+    fn test_strips_entity_encoded_characters() {
+        let mut notif = notification(
+            "Invoice",
+            "Invoice&#x202E;fdp.exe &#8238;&#7;&#X2066;done &#x263A; &amp; &#60;",
+        );
+        sanitize_notification(&mut notif);
+
+        assert_eq!(notif.body, "Invoicefdp.exe done &#x263A; &amp; &#60;");
+        assert!(!notif.truncated.any());
+    }
+
+    #[test]
+    fn test_normalizes_whitespace() {
+        let mut notif = notification(
+            "  Multi\n line \t summary ",
+            "\n\nFirst   line\t\n\n\n\nSecond line  \n\n",
+        );
+        sanitize_notification(&mut notif);
+
+        assert_eq!(notif.summary, "Multi line summary");
+        assert_eq!(notif.body, "First line\n\nSecond line");
+    }
+
+    #[test]
+    fn test_caps_summary_and_body() {
+        let mut notif = notification(&"s".repeat(1000), &"b".repeat(10_000));
+        sanitize_notification(&mut notif);
+
+        assert_eq!(notif.summary.chars().count(), MAX_SUMMARY_CHARS);
+        assert!(notif.summary.ends_with('…'));
+        assert_eq!(notif.body.chars().count(), MAX_BODY_CHARS);
+        assert!(notif.truncated.summary);
+        assert!(notif.truncated.body);
+        assert_eq!(notif.truncated.parts(), vec!["summary", "body"]);
+    }
+
+    #[test]
+    fn test_body_cut_avoids_partial_markup() {
+        let mut body = format!("{}<a href=\"https://example.com\">link</a>", "x".repeat(20));
+        assert!(clean_body(&mut body, 30));
+        assert_eq!(body, format!("{}…", "x".repeat(20)));
+
+        let mut body = format!("{}&amp; more", "x".repeat(20));
+        assert!(clean_body(&mut body, 23));
+        assert_eq!(body, format!("{}…", "x".repeat(20)));
+    }
+
+    #[test]
+    fn test_truncates_multibyte_text_on_char_boundaries() {
+        let mut text = "ñ".repeat(10);
+        assert!(truncate_chars(&mut text, 5));
+        assert_eq!(text, "ññññ…");
+        assert!(!truncate_chars(&mut text, 5));
+    }
+
+    #[test]
+    fn test_caps_actions() {
+        let mut notif = notification("Actions", "");
+        notif.actions = (0..20)
+            .map(|i| NotificationAction::new(format!("action-{i}"), "x".repeat(100)))
+            .collect();
+        notif
+            .actions
+            .push(NotificationAction::new("default", "Open"));
+        notif
+            .actions
+            .push(NotificationAction::new("k".repeat(1000), "Overlong key"));
+        sanitize_notification(&mut notif);
+
+        assert_eq!(notif.button_actions().len(), MAX_ACTIONS);
+        assert!(notif.default_action().is_some());
+        assert!(notif
+            .actions
+            .iter()
+            .all(|action| action.label.chars().count() <= MAX_ACTION_LABEL_CHARS));
+        assert!(notif.truncated.actions);
+    }
+
+    #[test]
+    fn test_bounds_images() {
+        let mut notif = notification("Image", "");
+        notif.hints.image_data = Some(image(1024, 512));
+        sanitize_notification(&mut notif);
+
+        let scaled = notif.hints.image_data.as_ref().expect("image is kept");
+        assert_eq!(
+            (scaled.width, scaled.height),
+            (MAX_IMAGE_SIZE, MAX_IMAGE_SIZE / 2)
+        );
+        assert!(scaled.is_valid());
+        assert!(notif.truncated.image);
+
+        let mut notif = notification("Image", "");
+        let mut blob = image(64, 64);
+        blob.data.resize(MAX_IMAGE_BYTES + 1, 0);
+        notif.hints.image_data = Some(blob);
+        sanitize_notification(&mut notif);
+
+        assert!(notif.hints.image_data.is_none());
+        assert!(notif.truncated.image);
+
+        let mut notif = notification("Image", "");
+        notif.hints.image_data = Some(image(48, 48));
+        sanitize_notification(&mut notif);
+        assert_eq!(notif.hints.image_data.as_ref().map(|i| i.width), Some(48));
+        assert!(!notif.truncated.image);
+    }
+
+    #[test]
+    fn test_bounds_names_and_hints() {
+        let mut notif = notification("Hints", "");
+        notif.app_name = format!("App\u{202E}{}", "n".repeat(500));
+        notif.hints.urls = (0..40)
+            .map(|i| format!("https://example.com/{i}"))
+            .collect();
+        notif.raw_hints.insert(
+            "x-vendor-blob".to_string(),
+            HintValue::Bytes(vec![0; MAX_HINT_BYTES + 1]),
+        );
+        notif.raw_hints.insert(
+            "x-kde-reply-placeholder-text".to_string(),
+            HintValue::String("Reply\u{202E}".to_string()),
+        );
+        sanitize_notification(&mut notif);
+
+        assert_eq!(notif.app_name.chars().count(), MAX_NAME_CHARS);
+        assert!(notif.app_name.starts_with("Appnnn"));
+        assert_eq!(notif.attachments().len(), MAX_ATTACHMENTS);
+        assert!(notif.raw_hint("x-vendor-blob").is_none());
+        assert_eq!(notif.reply_placeholder(), Some("Reply"));
+        assert!(notif.truncated.hints);
+        assert!(!notif.truncated.summary);
+    }
+
+    #[test]
+    fn test_refuses_runnable_attachments() {
+        let mut notif = notification("Attachments", "");
+        notif.hints.urls = vec![
+            "file:///tmp/report.pdf".to_string(),
+            "file:///usr/share/applications/evil.desktop".to_string(),
+            "javascript:alert(1)".to_string(),
+            "https://example.com/report".to_string(),
+        ];
+        sanitize_notification(&mut notif);
+
+        assert_eq!(
+            notif.attachments(),
+            ["file:///tmp/report.pdf", "https://example.com/report"]
+        );
+        assert!(!notif.truncated.hints);
+    }
+}
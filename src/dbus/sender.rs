@@ -1,37 +1,99 @@
 // D-Bus signal sender
 //
 // Handles sending signals back to notification senders, particularly ActionInvoked.
+// Signals are addressed to the sender's unique bus name when it is known, and
+// all of them go out over one shared session bus connection.
 
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
 use zbus::Connection;
 
+use crate::dbus::server::NOTIFICATIONS_BUS_NAME;
+
+/// Object path the notification signals are emitted from
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Interface the notification signals belong to
+const NOTIFICATIONS_INTERFACE: &str = "org.freedesktop.Notifications";
+
+/// Connection every signal is emitted on, created on first use
+static SIGNAL_CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// Emit signals on the given connection from now on
+///
+/// Daemon mode passes the connection that owns org.freedesktop.Notifications,
+/// as clients match notification signals on the name owner.
+pub fn set_signal_connection(connection: Connection) {
+    *SIGNAL_CONNECTION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(connection);
+}
+
+/// Stop emitting signals on a connection set with `set_signal_connection()`
+///
+/// The next signal connects to the session bus again. Does nothing if
+/// another connection has been set since.
+pub fn release_signal_connection(connection: &Connection) {
+    let mut current = SIGNAL_CONNECTION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if current.as_ref().map(Connection::unique_name) == Some(connection.unique_name()) {
+        *current = None;
+    }
+}
+
+/// Get the shared signal connection, connecting to the session bus if needed
+///
+/// Also used for the few method calls the applet makes to apps.
+pub(crate) async fn signal_connection() -> zbus::Result<Connection> {
+    let existing = SIGNAL_CONNECTION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    if let Some(connection) = existing {
+        return Ok(connection);
+    }
+
+    let connection = Connection::session().await?;
+    // Another signal may have connected in the meantime; keep the first one
+    Ok(SIGNAL_CONNECTION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get_or_insert(connection)
+        .clone())
+}
+
 /// Send an ActionInvoked signal to notify the sender that an action was clicked
 ///
 /// According to the freedesktop.org notification spec:
 /// - Signal: org.freedesktop.Notifications.ActionInvoked
 /// - Parameters: (UINT32 id, STRING action_key)
 ///
+/// `destination` is the sender's unique bus name; the signal is broadcast
+/// when it is unknown.
+///
 /// Returns:
 /// - Ok(()) if signal sent successfully
 /// - Err if D-Bus communication failed
 pub async fn send_action_invoked(
     notification_id: u32,
     action_key: &str,
+    destination: Option<&str>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!(
-        "Sending ActionInvoked signal for notification {}: action={}",
+        "Sending ActionInvoked signal for notification {}: action={} to {}",
         notification_id,
-        action_key
+        action_key,
+        destination.unwrap_or("all")
     );
 
-    // Get session bus connection
-    let connection = Connection::session().await?;
-
-    // Send ActionInvoked signal
-    connection
+    signal_connection()
+        .await?
         .emit_signal(
-            None::<()>, // destination (None = broadcast)
-            "/org/freedesktop/Notifications",
-            "org.freedesktop.Notifications",
+            destination,
+            NOTIFICATIONS_PATH,
+            NOTIFICATIONS_INTERFACE,
             "ActionInvoked",
             &(notification_id, action_key),
         )
@@ -41,6 +103,75 @@ pub async fn send_action_invoked(
     Ok(())
 }
 
+/// Send an ActivationToken signal ahead of ActionInvoked
+///
+/// According to the freedesktop.org notification spec (1.2):
+/// - Signal: org.freedesktop.Notifications.ActivationToken
+/// - Parameters: (UINT32 id, STRING activation_token)
+///
+/// The token lets the app raise its window under Wayland focus rules, so it
+/// must arrive before the ActionInvoked signal for the same action.
+/// `destination` is handled as in `send_action_invoked()`.
+pub async fn send_activation_token(
+    notification_id: u32,
+    token: &str,
+    destination: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    tracing::debug!(
+        "Sending ActivationToken signal for notification {} to {}",
+        notification_id,
+        destination.unwrap_or("all")
+    );
+
+    signal_connection()
+        .await?
+        .emit_signal(
+            destination,
+            NOTIFICATIONS_PATH,
+            NOTIFICATIONS_INTERFACE,
+            "ActivationToken",
+            &(notification_id, token),
+        )
+        .await?;
+
+    tracing::trace!("ActivationToken signal sent successfully");
+    Ok(())
+}
+
+/// Send a NotificationReplied signal with text typed into the reply field
+///
+/// Not part of the freedesktop.org spec; follows the inline reply extension
+/// used by KDE and GNOME:
+/// - Signal: org.freedesktop.Notifications.NotificationReplied
+/// - Parameters: (UINT32 id, STRING text)
+///
+/// `destination` is handled as in `send_action_invoked()`.
+pub async fn send_notification_replied(
+    notification_id: u32,
+    text: &str,
+    destination: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    tracing::info!(
+        "Sending NotificationReplied signal for notification {} to {}",
+        notification_id,
+        destination.unwrap_or("all")
+    );
+
+    signal_connection()
+        .await?
+        .emit_signal(
+            destination,
+            NOTIFICATIONS_PATH,
+            NOTIFICATIONS_INTERFACE,
+            "NotificationReplied",
+            &(notification_id, text),
+        )
+        .await?;
+
+    tracing::debug!("NotificationReplied signal sent successfully");
+    Ok(())
+}
+
 /// Send a NotificationClosed signal to notify the sender that a notification was closed
 ///
 /// According to the freedesktop.org notification spec:
@@ -53,28 +184,30 @@ pub async fn send_action_invoked(
 /// - 3: Closed by CloseNotification call
 /// - 4: Undefined/reserved
 ///
+/// `destination` is the sender's unique bus name; the signal is broadcast
+/// when it is unknown.
+///
 /// Returns:
 /// - Ok(()) if signal sent successfully
 /// - Err if D-Bus communication failed
 pub async fn send_notification_closed(
     notification_id: u32,
     reason: CloseReason,
+    destination: Option<&str>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     tracing::debug!(
-        "Sending NotificationClosed signal for notification {}: reason={:?}",
+        "Sending NotificationClosed signal for notification {}: reason={:?} to {}",
         notification_id,
-        reason
+        reason,
+        destination.unwrap_or("all")
     );
 
-    // Get session bus connection
-    let connection = Connection::session().await?;
-
-    // Send NotificationClosed signal
-    connection
+    signal_connection()
+        .await?
         .emit_signal(
-            None::<()>,
-            "/org/freedesktop/Notifications",
-            "org.freedesktop.Notifications",
+            destination,
+            NOTIFICATIONS_PATH,
+            NOTIFICATIONS_INTERFACE,
             "NotificationClosed",
             &(notification_id, reason as u32),
         )
@@ -84,10 +217,39 @@ pub async fn send_notification_closed(
     Ok(())
 }
 
+/// Ask the notification daemon to close one of its notifications
+///
+/// Used in listener mode, where another daemon owns
+/// org.freedesktop.Notifications and the notification IDs. The daemon
+/// emits NotificationClosed to the sending app itself.
+///
+/// Returns:
+/// - Ok(()) if the daemon closed the notification
+/// - Err if D-Bus communication failed or the daemon refused
+pub async fn close_on_daemon(notification_id: u32) -> zbus::Result<()> {
+    tracing::debug!(
+        "Asking the daemon to close notification {}",
+        notification_id
+    );
+
+    signal_connection()
+        .await?
+        .call_method(
+            Some(NOTIFICATIONS_BUS_NAME),
+            NOTIFICATIONS_PATH,
+            Some(NOTIFICATIONS_INTERFACE),
+            "CloseNotification",
+            &(notification_id,),
+        )
+        .await?;
+
+    Ok(())
+}
+
 /// Reason a notification was closed
 ///
 /// Maps to the reason parameter in NotificationClosed signal.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u32)]
 pub enum CloseReason {
     /// Notification expired (timeout)
@@ -100,6 +262,20 @@ pub enum CloseReason {
     Undefined = 4,
 }
 
+impl From<u32> for CloseReason {
+    /// Decode the reason parameter of a NotificationClosed signal
+    ///
+    /// Unknown values map to `Undefined`, as the spec reserves them.
+    fn from(value: u32) -> Self {
+        match value {
+            1 => CloseReason::Expired,
+            2 => CloseReason::Dismissed,
+            3 => CloseReason::Closed,
+            _ => CloseReason::Undefined,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,4 +287,19 @@ mod tests {
         assert_eq!(CloseReason::Closed as u32, 3);
         assert_eq!(CloseReason::Undefined as u32, 4);
     }
+
+    #[test]
+    fn test_close_reason_from_u32() {
+        for reason in [
+            CloseReason::Expired,
+            CloseReason::Dismissed,
+            CloseReason::Closed,
+            CloseReason::Undefined,
+        ] {
+            assert_eq!(CloseReason::from(reason as u32), reason);
+        }
+
+        assert_eq!(CloseReason::from(0), CloseReason::Undefined);
+        assert_eq!(CloseReason::from(42), CloseReason::Undefined);
+    }
 }
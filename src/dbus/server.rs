@@ -0,0 +1,457 @@
+// D-Bus notification server ("daemon mode")
+//
+// This module implements the org.freedesktop.Notifications interface so the
+// applet can own the notification bus name itself, instead of passively
+// listening to another daemon. Enabled through `AppletConfig::daemon_mode`.
+//
+// Architecture: The zbus interface object only allocates IDs and forwards
+// requests over a channel. The NotificationManager stays single-threaded and
+// owned by the iced application; the subscription stream hands it the
+// forwarded events as Messages.
+//
+// Reference: https://specifications.freedesktop.org/notification-spec/latest/protocol.html
+
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use cosmic::iced;
+use futures::channel::mpsc;
+use futures::stream::{Stream, StreamExt};
+use zbus::message::Header;
+use zbus::object_server::SignalContext;
+use zbus::{interface, Connection};
+
+use crate::dbus::sender::{self, CloseReason};
+use crate::dbus::types::{Notification, NotificationEvent, NotifyArgs};
+use crate::manager::IdAllocator;
+
+/// Well-known bus name claimed in daemon mode
+pub const NOTIFICATIONS_BUS_NAME: &str = "org.freedesktop.Notifications";
+
+/// Object path of the notification interface
+pub const NOTIFICATIONS_OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Capabilities advertised through `GetCapabilities`
+///
+/// Only lists features the applet actually implements.
+/// Reference: https://specifications.freedesktop.org/notification-spec/latest/protocol.html#command-get-capabilities
+pub const CAPABILITIES: &[&str] = &[
+    "actions",
+    "body",
+    "body-hyperlinks",
+    "body-markup",
+    "icon-static",
+    "inline-reply",
+    "persistence",
+];
+
+/// Server name reported by `GetServerInformation`
+const SERVER_NAME: &str = "COSMIC Notification Applet";
+
+/// Vendor reported by `GetServerInformation`
+const SERVER_VENDOR: &str = "cosmic-applet-notifications";
+
+/// Notification specification version implemented by the server
+const SPEC_VERSION: &str = "1.2";
+
+/// Buffer size for requests forwarded from the interface to the application
+///
+/// Matches the listener's notification buffer so both modes absorb the same
+/// burst size before applying backpressure to callers.
+const SERVER_EVENT_BUFFER_SIZE: usize = 128;
+
+/// Delay before retrying when the server connection cannot be set up
+const SERVER_RETRY_DELAY_SECS: u64 = 5;
+
+/// The org.freedesktop.Notifications interface object
+///
+/// Allocates IDs from the manager's shared counter and forwards every request
+/// to the application over a bounded channel, as `NotificationEvent`s.
+pub struct NotificationServer {
+    ids: IdAllocator,
+    events: mpsc::Sender<NotificationEvent>,
+}
+
+impl NotificationServer {
+    /// Create a server that allocates IDs from `ids` and forwards to `events`
+    pub fn new(ids: IdAllocator, events: mpsc::Sender<NotificationEvent>) -> Self {
+        Self { ids, events }
+    }
+
+    /// Forward an event to the application
+    async fn forward(&mut self, event: NotificationEvent) -> zbus::fdo::Result<()> {
+        use futures::SinkExt;
+
+        self.events
+            .send(event)
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Applet is not accepting events: {e}")))
+    }
+}
+
+#[interface(name = "org.freedesktop.Notifications")]
+impl NotificationServer {
+    /// Display a notification and return its ID
+    ///
+    /// A `replaces_id` naming an active notification keeps its ID, as required
+    /// by the specification. Any other `replaces_id` (zero, unknown or already
+    /// closed) gets a fresh ID, so an ID is never handed to two notifications.
+    /// The caller's unique name is recorded so signals about the notification
+    /// are sent to it.
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &mut self,
+        #[zbus(header)] header: Header<'_>,
+        app_name: String,
+        replaces_id: u32,
+        app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: std::collections::HashMap<String, zbus::zvariant::OwnedValue>,
+        expire_timeout: i32,
+    ) -> zbus::fdo::Result<u32> {
+        let replaces_id = if replaces_id != 0 && self.ids.is_in_use(replaces_id) {
+            replaces_id
+        } else {
+            0
+        };
+        let id = if replaces_id != 0 {
+            replaces_id
+        } else {
+            self.ids.next_id()
+        };
+
+        let args: NotifyArgs = (
+            app_name,
+            replaces_id,
+            app_icon,
+            summary,
+            body,
+            actions,
+            hints,
+            expire_timeout,
+        );
+        let mut notification = Notification::from_notify_args(id, args);
+        notification.sender = header.sender().map(|sender| sender.to_string());
+
+        tracing::debug!(
+            "Notify from {}: {} (id {})",
+            notification.app_name,
+            notification.summary,
+            id
+        );
+
+        if let Err(e) = self.forward(notification.into()).await {
+            if replaces_id == 0 {
+                self.ids.release(id);
+            }
+            return Err(e);
+        }
+        Ok(id)
+    }
+
+    /// Close a notification
+    ///
+    /// The application closes it with reason 3 and sends `NotificationClosed`
+    /// to the app that sent it. Fails with `InvalidArgs` if no active
+    /// notification has this ID.
+    async fn close_notification(&mut self, id: u32) -> zbus::fdo::Result<()> {
+        tracing::debug!("CloseNotification for id {}", id);
+
+        if !self.ids.is_in_use(id) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "No active notification with ID {id}"
+            )));
+        }
+
+        self.forward(NotificationEvent::Closed {
+            id,
+            reason: CloseReason::Closed,
+        })
+        .await
+    }
+
+    /// List optional capabilities implemented by this server
+    async fn get_capabilities(&self) -> Vec<String> {
+        CAPABILITIES.iter().map(|c| c.to_string()).collect()
+    }
+
+    /// Return (name, vendor, version, spec_version)
+    #[zbus(out_args("name", "vendor", "version", "spec_version"))]
+    async fn get_server_information(&self) -> (String, String, String, String) {
+        (
+            SERVER_NAME.to_string(),
+            SERVER_VENDOR.to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+            SPEC_VERSION.to_string(),
+        )
+    }
+
+    /// Emitted when a notification is closed
+    #[zbus(signal)]
+    async fn notification_closed(
+        ctxt: &SignalContext<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+
+    /// Emitted when the user invokes a notification action
+    #[zbus(signal)]
+    async fn action_invoked(
+        ctxt: &SignalContext<'_>,
+        id: u32,
+        action_key: &str,
+    ) -> zbus::Result<()>;
+
+    /// Emitted when the user sends a reply typed into the notification
+    #[zbus(signal)]
+    async fn notification_replied(
+        ctxt: &SignalContext<'_>,
+        id: u32,
+        text: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// Subscription ID for the notification server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ServerSubscription;
+
+impl Hash for ServerSubscription {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+}
+
+/// Create a subscription that serves org.freedesktop.Notifications
+///
+/// Claims the notification bus name and yields every `Notify` and
+/// `CloseNotification` request as a `NotificationEvent`. IDs are allocated from
+/// `ids`, which should come from `NotificationManager::id_allocator()`.
+///
+/// If the name is owned by another daemon, the error is logged and the
+/// claim is retried periodically.
+pub fn subscribe<Message>(ids: IdAllocator) -> iced::Subscription<Message>
+where
+    Message: 'static + Send + Clone + From<NotificationEvent>,
+{
+    iced::Subscription::run_with_id(ServerSubscription, server_stream(ids).map(Message::from))
+}
+
+/// Serve the interface and stream forwarded events
+///
+/// The connection is kept alive inside the stream state, so the bus name is
+/// released when iced drops the subscription.
+fn server_stream(ids: IdAllocator) -> impl Stream<Item = NotificationEvent> {
+    futures::stream::unfold(ids, |ids| async move {
+        loop {
+            let (sender, receiver) = mpsc::channel(SERVER_EVENT_BUFFER_SIZE);
+            match serve(NotificationServer::new(ids.clone(), sender)).await {
+                Ok(connection) => {
+                    // Keep the connection alive for as long as events are consumed
+                    let events = futures::stream::unfold(
+                        (SignalConnection::new(connection), receiver),
+                        |(connection, mut receiver)| async move {
+                            let event = receiver.next().await?;
+                            Some((event, (connection, receiver)))
+                        },
+                    );
+                    return Some((events, ids));
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to claim {}: {} (retrying in {}s)",
+                        NOTIFICATIONS_BUS_NAME,
+                        e,
+                        SERVER_RETRY_DELAY_SECS
+                    );
+                    tokio::time::sleep(Duration::from_secs(SERVER_RETRY_DELAY_SECS)).await;
+                }
+            }
+        }
+    })
+    .flatten()
+}
+
+/// Registers the daemon connection for outgoing signals while it is alive
+///
+/// Signals about notifications must come from the owner of the notification
+/// bus name. The registration is dropped with the server's stream, so the
+/// name is released when iced drops the subscription.
+struct SignalConnection(Connection);
+
+impl SignalConnection {
+    fn new(connection: Connection) -> Self {
+        sender::set_signal_connection(connection.clone());
+        Self(connection)
+    }
+}
+
+impl Drop for SignalConnection {
+    fn drop(&mut self) {
+        sender::release_signal_connection(&self.0);
+    }
+}
+
+/// Export the interface on the session bus and claim the well-known name
+async fn serve(server: NotificationServer) -> zbus::Result<Connection> {
+    let connection = zbus::connection::Builder::session()?
+        .serve_at(NOTIFICATIONS_OBJECT_PATH, server)?
+        .name(NOTIFICATIONS_BUS_NAME)?
+        .build()
+        .await?;
+
+    tracing::info!(
+        "Serving {} on the session bus (daemon mode)",
+        NOTIFICATIONS_BUS_NAME
+    );
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn test_server() -> (NotificationServer, mpsc::Receiver<NotificationEvent>) {
+        let (sender, receiver) = mpsc::channel(SERVER_EVENT_BUFFER_SIZE);
+        (
+            NotificationServer::new(IdAllocator::new(), sender),
+            receiver,
+        )
+    }
+
+    /// A Notify call from `:1.42`, for its header
+    fn notify_call() -> zbus::Message {
+        zbus::Message::method(NOTIFICATIONS_OBJECT_PATH, "Notify")
+            .unwrap()
+            .sender(":1.42")
+            .unwrap()
+            .build(&())
+            .unwrap()
+    }
+
+    async fn notify(server: &mut NotificationServer, replaces_id: u32, summary: &str) -> u32 {
+        let call = notify_call();
+        server
+            .notify(
+                call.header(),
+                "test".to_string(),
+                replaces_id,
+                String::new(),
+                summary.to_string(),
+                String::new(),
+                vec![],
+                HashMap::new(),
+                -1,
+            )
+            .await
+            .expect("notify should succeed")
+    }
+
+    #[tokio::test]
+    async fn test_notify_returns_allocated_ids() {
+        let (mut server, mut receiver) = test_server();
+
+        let id1 = notify(&mut server, 0, "First").await;
+        let id2 = notify(&mut server, 0, "Second").await;
+        assert_eq!(id1, 1);
+        assert_eq!(id2, 2);
+
+        match receiver.next().await {
+            Some(NotificationEvent::Notify(notification)) => {
+                assert_eq!(notification.id, id1);
+                assert_eq!(notification.summary, "First");
+                assert_eq!(notification.sender.as_deref(), Some(":1.42"));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notify_replacement_keeps_id() {
+        let (mut server, _receiver) = test_server();
+
+        let id = notify(&mut server, 0, "Original").await;
+        let replaced = notify(&mut server, id, "Replacement").await;
+        assert_eq!(replaced, id);
+    }
+
+    #[tokio::test]
+    async fn test_notify_unknown_replacement_gets_fresh_id() {
+        let (mut server, mut receiver) = test_server();
+
+        // Never allocated: a fresh ID, and nothing to replace
+        let id = notify(&mut server, 77, "Stale").await;
+        assert_eq!(id, 1);
+        match receiver.next().await {
+            Some(NotificationEvent::Notify(notification)) => {
+                assert_eq!(notification.id, 1);
+                assert_eq!(notification.replaces_id, 0);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // Closed since: not reused either
+        server.ids.release(id);
+        assert_eq!(notify(&mut server, id, "Closed").await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_close_notification() {
+        let (mut server, mut receiver) = test_server();
+
+        assert!(matches!(
+            server.close_notification(5).await,
+            Err(zbus::fdo::Error::InvalidArgs(_))
+        ));
+
+        let id = notify(&mut server, 0, "Closable").await;
+        server.close_notification(id).await.unwrap();
+        receiver.next().await;
+        assert!(matches!(
+            receiver.next().await,
+            Some(NotificationEvent::Closed {
+                reason: CloseReason::Closed,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_notify_fails_when_applet_is_gone() {
+        let (mut server, receiver) = test_server();
+        drop(receiver);
+
+        let call = notify_call();
+        let result = server
+            .notify(
+                call.header(),
+                "test".to_string(),
+                0,
+                String::new(),
+                "Orphan".to_string(),
+                String::new(),
+                vec![],
+                HashMap::new(),
+                -1,
+            )
+            .await;
+        assert!(result.is_err());
+        assert!(!server.ids.is_in_use(1));
+    }
+
+    #[tokio::test]
+    async fn test_capabilities_and_server_information() {
+        let (server, _receiver) = test_server();
+
+        let capabilities = server.get_capabilities().await;
+        assert!(capabilities.contains(&"actions".to_string()));
+        assert!(capabilities.contains(&"body".to_string()));
+
+        let (name, _vendor, version, spec_version) = server.get_server_information().await;
+        assert_eq!(name, SERVER_NAME);
+        assert_eq!(version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(spec_version, "1.2");
+    }
+}
@@ -6,20 +6,16 @@
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use zbus::zvariant::{OwnedValue, Value};
+use zbus::zvariant::{Array, Dict, OwnedValue, StructureBuilder, Value};
+
+use crate::dbus::portal::PortalOrigin;
+use crate::dbus::sender::CloseReason;
 
 /// A notification received from D-Bus
 ///
 /// Implements the freedesktop.org Desktop Notifications Specification v1.2
 /// All fields are required to implement Clone for iced Message compatibility
-///
-/// # Clone Behavior Warning
-///
-/// **IMPORTANT**: Cloning a `Notification` will **lose all `raw_hints` data**.
-/// This is because `zbus::zvariant::OwnedValue` does not implement `Clone`.
-/// Only the parsed `hints` field will be preserved. If you need to preserve
-/// raw D-Bus hints (e.g., for debugging or forwarding), avoid cloning.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Notification {
     /// Unique notification ID (assigned by us)
     pub id: u32,
@@ -45,13 +41,13 @@ pub struct Notification {
     /// Parsed notification hints
     pub hints: NotificationHints,
 
-    /// Raw D-Bus hints for unrecognized keys (skipped during serialization)
+    /// All D-Bus hints as sent, converted to `HintValue`
     ///
-    /// **WARNING**: This field is **NOT cloned** when cloning a `Notification`.
-    /// The `OwnedValue` type does not implement `Clone`, so cloning will result
-    /// in an empty HashMap. This means non-standard D-Bus hints are lost on clone.
-    #[serde(skip, default)]
-    pub raw_hints: HashMap<String, OwnedValue>,
+    /// Keeps vendor hints (e.g. `x-kde-*`, `value`) that aren't parsed into
+    /// `hints`, so they survive cloning and are persisted with history.
+    /// Image data hints are left out; they are decoded into `hints.image_data`.
+    #[serde(default)]
+    pub raw_hints: HashMap<String, HintValue>,
 
     /// Expiration timeout in milliseconds
     /// -1: never expire, 0: use server default, >0: specific timeout
@@ -59,55 +55,73 @@ pub struct Notification {
 
     /// Timestamp when notification was received
     pub timestamp: DateTime<Local>,
+
+    /// Why the notification was closed (None while it is still active)
+    #[serde(default)]
+    pub close_reason: Option<CloseReason>,
+
+    /// Application resolved from the `desktop-entry` hint
+    #[serde(default)]
+    pub app_identity: Option<AppIdentity>,
+
+    /// Unique bus name of the sending client (e.g. ":1.42"), if known
+    ///
+    /// ActionInvoked and NotificationClosed are sent to this name.
+    #[serde(default)]
+    pub sender: Option<String>,
+
+    /// Portal origin, for notifications posted through the desktop portal
+    /// or org.gtk.Notifications
+    #[serde(default)]
+    pub portal: Option<PortalOrigin>,
+
+    /// Parts cut down by the sanitization stage (see `sanitize`)
+    #[serde(default)]
+    pub truncated: Truncations,
 }
 
-/// Manual Clone implementation with data loss caveat
+/// Arguments of an `org.freedesktop.Notifications.Notify` call, in wire order
 ///
-/// # Data Loss Warning
-///
-/// **IMPORTANT**: This Clone implementation intentionally **discards `raw_hints`** data.
-/// The `zbus::zvariant::OwnedValue` type does not implement `Clone`, making it
-/// impossible to clone raw D-Bus hints. This means:
-///
-/// - Standard hints (parsed into `hints` field) are preserved ✓
-/// - Non-standard D-Bus hints (in `raw_hints`) are lost ✗
-///
-/// This limitation affects:
-/// - Debugging: Lost hints make it harder to diagnose notification issues
-/// - Forwarding: Cannot perfectly replicate non-standard notifications
-/// - Custom apps: Application-specific hints may be discarded
-///
-/// # Current Usage
-///
-/// This applet currently clones notifications in the following scenarios:
-/// - ~~get_active_notifications() → UI rendering~~ (FIXED: now uses references)
-/// - History storage (when dismissing/evicting notifications)
-///
-/// For most use cases, this limitation is acceptable because:
-/// - Standard hints are properly parsed and preserved
-/// - Non-standard hints are rare in practice
-/// - History doesn't need debugging metadata
-impl Clone for Notification {
-    fn clone(&self) -> Self {
+/// `(app_name, replaces_id, app_icon, summary, body, actions, hints, expire_timeout)`
+pub type NotifyArgs = (
+    String,
+    u32,
+    String,
+    String,
+    String,
+    Vec<String>,
+    HashMap<String, OwnedValue>,
+    i32,
+);
+
+impl Notification {
+    /// Build a notification from the arguments of a `Notify` call
+    ///
+    /// Parses actions and hints, and timestamps the notification with the
+    /// current local time.
+    pub fn from_notify_args(id: u32, args: NotifyArgs) -> Self {
+        let (app_name, replaces_id, app_icon, summary, body, actions, hints, expire_timeout) = args;
+
         Self {
-            id: self.id,
-            app_name: self.app_name.clone(),
-            replaces_id: self.replaces_id,
-            app_icon: self.app_icon.clone(),
-            summary: self.summary.clone(),
-            body: self.body.clone(),
-            actions: self.actions.clone(),
-            hints: self.hints.clone(),
-            // CRITICAL: raw_hints is intentionally not cloned (see doc comment above)
-            // OwnedValue doesn't implement Clone, so we create an empty HashMap
-            raw_hints: HashMap::new(),
-            expire_timeout: self.expire_timeout,
-            timestamp: self.timestamp,
+            id,
+            app_name,
+            replaces_id,
+            app_icon,
+            summary,
+            body,
+            actions: parse_actions(&actions),
+            hints: parse_hints(&hints),
+            raw_hints: convert_hints(&hints),
+            expire_timeout,
+            timestamp: Local::now(),
+            close_reason: None,
+            app_identity: None,
+            sender: None,
+            portal: None,
+            truncated: Truncations::default(),
         }
     }
-}
 
-impl Notification {
     /// Get the urgency level of this notification
     pub fn urgency(&self) -> Urgency {
         self.hints.urgency
@@ -118,6 +132,11 @@ impl Notification {
         self.hints.transient
     }
 
+    /// Get the progress percentage (0-100), if this is a progress notification
+    pub fn progress(&self) -> Option<u8> {
+        self.hints.value
+    }
+
     /// Check if this notification is resident (should not be automatically removed)
     pub fn is_resident(&self) -> bool {
         self.hints.resident
@@ -128,6 +147,35 @@ impl Notification {
         self.hints.category.as_deref()
     }
 
+    /// Get the name to show for the sending application
+    ///
+    /// Prefers the sender's `x-kde-display-appname`, then the name from the
+    /// desktop entry, over the raw `app_name`.
+    pub fn display_name(&self) -> &str {
+        if let Some(name) = self.hints.display_app_name.as_deref() {
+            return name;
+        }
+        match &self.app_identity {
+            Some(identity) => &identity.name,
+            None => &self.app_name,
+        }
+    }
+
+    /// Get where the notification comes from (`x-kde-origin-name`)
+    pub fn origin_name(&self) -> Option<&str> {
+        self.hints.origin_name.as_deref()
+    }
+
+    /// Get the attached files or links (`x-kde-urls`)
+    pub fn attachments(&self) -> &[String] {
+        &self.hints.urls
+    }
+
+    /// Get the synchronous tag (`x-canonical-private-synchronous`)
+    pub fn synchronous_tag(&self) -> Option<&str> {
+        self.hints.synchronous.as_deref()
+    }
+
     /// Get the desktop entry name for this notification
     pub fn desktop_entry(&self) -> Option<&str> {
         self.hints.desktop_entry.as_deref()
@@ -137,6 +185,101 @@ impl Notification {
     pub fn has_actions(&self) -> bool {
         !self.actions.is_empty()
     }
+
+    /// Check if this notification offers the action with this key
+    pub fn has_action(&self, key: &str) -> bool {
+        self.actions.iter().any(|action| action.key == key)
+    }
+
+    /// Get the default action, invoked by clicking the notification
+    pub fn default_action(&self) -> Option<&NotificationAction> {
+        self.actions.iter().find(|action| action.is_default())
+    }
+
+    /// Get the actions shown as buttons
+    ///
+    /// Leaves out the default action and the inline reply action, which
+    /// are rendered as the card itself and a reply field.
+    pub fn button_actions(&self) -> Vec<&NotificationAction> {
+        self.actions
+            .iter()
+            .filter(|action| !action.is_default() && !action.is_inline_reply())
+            .collect()
+    }
+
+    /// Check if the sender accepts a typed reply
+    ///
+    /// Set by an `inline-reply` action or KDE's `x-kde-reply-placeholder-text` hint.
+    pub fn accepts_reply(&self) -> bool {
+        self.actions.iter().any(NotificationAction::is_inline_reply)
+            || self.reply_placeholder().is_some()
+    }
+
+    /// Bus name a typed reply is sent to, if a reply can be sent at all
+    ///
+    /// None unless the notification accepts replies and came from a known
+    /// freedesktop sender: NotificationReplied is never broadcast, and
+    /// portal and org.gtk.Notifications apps have no way to receive it.
+    pub fn reply_destination(&self) -> Option<&str> {
+        if !self.accepts_reply() || self.portal.is_some() {
+            return None;
+        }
+        self.sender.as_deref()
+    }
+
+    /// Get the placeholder text for the reply field (`x-kde-reply-placeholder-text`)
+    pub fn reply_placeholder(&self) -> Option<&str> {
+        self.raw_hint(REPLY_PLACEHOLDER_HINT)
+            .and_then(HintValue::as_str)
+    }
+
+    /// Get the label for the reply send button (`x-kde-reply-submit-button-text`)
+    pub fn reply_submit_label(&self) -> Option<&str> {
+        self.raw_hint(REPLY_SUBMIT_LABEL_HINT)
+            .and_then(HintValue::as_str)
+    }
+
+    /// Get a hint by key, including hints not parsed into `hints`
+    pub fn raw_hint(&self, key: &str) -> Option<&HintValue> {
+        self.raw_hints.get(key)
+    }
+}
+
+/// Notification traffic observed on the session bus or served in daemon mode
+///
+/// Produced by both the listener and the daemon mode server, so the
+/// application handles either source the same way.
+#[derive(Debug, Clone)]
+pub enum NotificationEvent {
+    /// A notification was posted (`Notify`)
+    Notify(Box<Notification>),
+
+    /// A notification was closed (`NotificationClosed` or `CloseNotification`)
+    Closed { id: u32, reason: CloseReason },
+
+    /// An action was invoked on a notification (`ActionInvoked`)
+    ActionInvoked { id: u32, action_key: String },
+
+    /// An activation token was issued ahead of `ActionInvoked` (`ActivationToken`)
+    ActivationToken { id: u32, token: String },
+
+    /// The listener's connection to the session bus changed
+    Status(ListenerStatus),
+}
+
+/// Connection state of the passive notification listener
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerStatus {
+    /// Receiving notification traffic from the session bus
+    Connected,
+    /// Not connected to the session bus; reconnecting in the background
+    Disconnected,
+}
+
+impl From<Notification> for NotificationEvent {
+    fn from(notification: Notification) -> Self {
+        NotificationEvent::Notify(Box::new(notification))
+    }
 }
 
 /// Notification urgency level
@@ -172,6 +315,70 @@ impl Urgency {
     }
 }
 
+/// Parts of a notification cut down because they exceeded a size limit
+///
+/// Set by `sanitize::sanitize_notification`, so the card can say that what
+/// it shows is not everything the sender sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Truncations {
+    /// Summary shortened
+    pub summary: bool,
+    /// Body shortened
+    pub body: bool,
+    /// Actions dropped, or their labels shortened
+    pub actions: bool,
+    /// Image dropped or downscaled
+    pub image: bool,
+    /// Names, attachments or vendor hints shortened or dropped
+    pub hints: bool,
+}
+
+impl Truncations {
+    /// Check if anything was cut down
+    pub fn any(&self) -> bool {
+        self.summary || self.body || self.actions || self.image || self.hints
+    }
+
+    /// Names of the parts that were cut down, for display
+    pub fn parts(&self) -> Vec<&'static str> {
+        [
+            (self.summary, "summary"),
+            (self.body, "body"),
+            (self.actions, "actions"),
+            (self.image, "image"),
+            (self.hints, "details"),
+        ]
+        .into_iter()
+        .filter_map(|(truncated, part)| truncated.then_some(part))
+        .collect()
+    }
+}
+
+/// Application identity read from a desktop entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppIdentity {
+    /// Desktop entry ID (e.g. "firefox")
+    pub desktop_entry: String,
+
+    /// Localized application name
+    pub name: String,
+
+    /// Icon name or path
+    pub icon: Option<String>,
+}
+
+/// Key of the action invoked by clicking the notification itself
+pub const DEFAULT_ACTION_KEY: &str = "default";
+
+/// Key of the action asking for a typed reply (answered with NotificationReplied)
+pub const INLINE_REPLY_ACTION_KEY: &str = "inline-reply";
+
+/// KDE hint with placeholder text for the reply field
+const REPLY_PLACEHOLDER_HINT: &str = "x-kde-reply-placeholder-text";
+
+/// KDE hint with the label of the reply send button
+const REPLY_SUBMIT_LABEL_HINT: &str = "x-kde-reply-submit-button-text";
+
 /// A notification action (button)
 ///
 /// Actions are displayed as buttons in the notification.
@@ -192,6 +399,16 @@ impl NotificationAction {
             label: label.into(),
         }
     }
+
+    /// Check if this is the default action (invoked by clicking the notification)
+    pub fn is_default(&self) -> bool {
+        self.key == DEFAULT_ACTION_KEY
+    }
+
+    /// Check if this action asks for a typed reply
+    pub fn is_inline_reply(&self) -> bool {
+        self.key == INLINE_REPLY_ACTION_KEY
+    }
 }
 
 /// Parsed notification hints
@@ -238,11 +455,44 @@ pub struct NotificationHints {
 
     /// Image path (icon as file path)
     pub image_path: Option<String>,
+
+    /// Progress percentage from the `value` hint (0-100)
+    #[serde(default)]
+    pub value: Option<u8>,
+
+    /// Attached files or links (`x-kde-urls`)
+    #[serde(default)]
+    pub urls: Vec<String>,
+
+    /// Where the notification comes from, e.g. a website or paired device
+    /// (`x-kde-origin-name`)
+    #[serde(default)]
+    pub origin_name: Option<String>,
+
+    /// App name to show instead of `app_name` (`x-kde-display-appname`)
+    #[serde(default)]
+    pub display_app_name: Option<String>,
+
+    /// Tag of a synchronous notification (`x-canonical-private-synchronous`)
+    ///
+    /// A new notification with the same tag from the same app replaces the
+    /// previous one, as volume and brightness popups expect.
+    #[serde(default)]
+    pub synchronous: Option<String>,
 }
 
+/// Largest image-data width or height accepted, in pixels
+///
+/// Notification images are shown at icon size; anything larger than this is
+/// almost certainly malformed and would only waste memory.
+const MAX_IMAGE_DIMENSION: i32 = 4096;
+
 /// Raw image data for notification icons
 ///
-/// ARGB32 format image data
+/// Decoded from the `(iiibiiay)` image-data hint: `height` rows of
+/// `rowstride` bytes, each pixel `channels` samples of `bits_per_sample` bits
+/// (RGB, or RGBA when `has_alpha` is set).
+/// Reference: https://specifications.freedesktop.org/notification-spec/latest/icons-and-images.html
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageData {
     pub width: i32,
@@ -254,6 +504,272 @@ pub struct ImageData {
     pub data: Vec<u8>,
 }
 
+impl ImageData {
+    /// Check that the geometry is supported and matches the pixel buffer
+    ///
+    /// Only 8-bit RGB/RGBA is accepted. The last row may omit its padding,
+    /// so the buffer must hold `rowstride * (height - 1)` bytes plus one
+    /// unpadded row.
+    pub fn is_valid(&self) -> bool {
+        if self.width <= 0
+            || self.height <= 0
+            || self.width > MAX_IMAGE_DIMENSION
+            || self.height > MAX_IMAGE_DIMENSION
+            || self.bits_per_sample != 8
+            || self.channels != if self.has_alpha { 4 } else { 3 }
+        {
+            return false;
+        }
+
+        let row_len = self.width as usize * self.channels as usize;
+        if self.rowstride < 0 || (self.rowstride as usize) < row_len {
+            return false;
+        }
+
+        let required = self.rowstride as usize * (self.height as usize - 1) + row_len;
+        self.data.len() >= required
+    }
+
+    /// Convert to tightly packed RGBA8 pixels
+    ///
+    /// Drops row padding and adds an opaque alpha channel to RGB images.
+    /// Returns None if the image is invalid.
+    pub fn to_rgba(&self) -> Option<Vec<u8>> {
+        if !self.is_valid() {
+            return None;
+        }
+
+        let channels = self.channels as usize;
+        let row_len = self.width as usize * channels;
+        let mut rgba = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+
+        for row in self
+            .data
+            .chunks(self.rowstride as usize)
+            .take(self.height as usize)
+        {
+            for pixel in row[..row_len].chunks_exact(channels) {
+                rgba.extend_from_slice(&pixel[..3]);
+                rgba.push(if self.has_alpha { pixel[3] } else { u8::MAX });
+            }
+        }
+
+        Some(rgba)
+    }
+
+    /// Scale down to fit within `max_size` pixels on each side
+    ///
+    /// Samples the nearest pixel and returns packed RGBA, keeping the aspect
+    /// ratio. Returns None if the image is invalid.
+    pub fn downscaled(&self, max_size: i32) -> Option<ImageData> {
+        let rgba = self.to_rgba()?;
+        let scale = f64::from(max_size) / f64::from(self.width.max(self.height));
+        let width = ((f64::from(self.width) * scale).round() as i32).clamp(1, max_size);
+        let height = ((f64::from(self.height) * scale).round() as i32).clamp(1, max_size);
+
+        let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height as usize {
+            let source_y = y * self.height as usize / height as usize;
+            for x in 0..width as usize {
+                let source_x = x * self.width as usize / width as usize;
+                let offset = (source_y * self.width as usize + source_x) * 4;
+                data.extend_from_slice(&rgba[offset..offset + 4]);
+            }
+        }
+
+        Some(ImageData {
+            width,
+            height,
+            rowstride: width * 4,
+            has_alpha: true,
+            bits_per_sample: 8,
+            channels: 4,
+            data,
+        })
+    }
+}
+
+/// A D-Bus hint value that can be cloned and serialized
+///
+/// Mirrors the D-Bus basic and container types. Variants are unwrapped,
+/// object paths and signatures become strings, and `ay` arrays become `Bytes`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HintValue {
+    Bool(bool),
+    Byte(u8),
+    Int16(i16),
+    UInt16(u16),
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Double(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<HintValue>),
+    Struct(Vec<HintValue>),
+    Dict(Vec<(HintValue, HintValue)>),
+}
+
+impl HintValue {
+    /// Convert a D-Bus value
+    ///
+    /// Returns None for values that can't be kept, such as file descriptors.
+    pub fn from_value(value: &Value<'_>) -> Option<Self> {
+        let hint = match value {
+            Value::Bool(v) => HintValue::Bool(*v),
+            Value::U8(v) => HintValue::Byte(*v),
+            Value::I16(v) => HintValue::Int16(*v),
+            Value::U16(v) => HintValue::UInt16(*v),
+            Value::I32(v) => HintValue::Int32(*v),
+            Value::U32(v) => HintValue::UInt32(*v),
+            Value::I64(v) => HintValue::Int64(*v),
+            Value::U64(v) => HintValue::UInt64(*v),
+            Value::F64(v) => HintValue::Double(*v),
+            Value::Str(v) => HintValue::String(v.to_string()),
+            Value::ObjectPath(v) => HintValue::String(v.to_string()),
+            Value::Signature(v) => HintValue::String(v.to_string()),
+            Value::Value(inner) => return HintValue::from_value(inner),
+            Value::Array(array) if array.element_signature() == "y" => HintValue::Bytes(
+                array
+                    .inner()
+                    .iter()
+                    .filter_map(|v| match v {
+                        Value::U8(byte) => Some(*byte),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            Value::Array(array) => HintValue::Array(
+                array
+                    .inner()
+                    .iter()
+                    .filter_map(HintValue::from_value)
+                    .collect(),
+            ),
+            Value::Structure(structure) => HintValue::Struct(
+                structure
+                    .fields()
+                    .iter()
+                    .filter_map(HintValue::from_value)
+                    .collect(),
+            ),
+            Value::Dict(dict) => HintValue::Dict(
+                dict.iter()
+                    .filter_map(|(k, v)| {
+                        Some((HintValue::from_value(k)?, HintValue::from_value(v)?))
+                    })
+                    .collect(),
+            ),
+            _ => return None,
+        };
+
+        Some(hint)
+    }
+
+    /// Convert back to a D-Bus value
+    ///
+    /// Arrays and dictionaries take their signature from the first element,
+    /// so empty ones can't be converted and return None.
+    pub fn to_value(&self) -> Option<Value<'static>> {
+        let value = match self {
+            HintValue::Bool(v) => Value::from(*v),
+            HintValue::Byte(v) => Value::from(*v),
+            HintValue::Int16(v) => Value::from(*v),
+            HintValue::UInt16(v) => Value::from(*v),
+            HintValue::Int32(v) => Value::from(*v),
+            HintValue::UInt32(v) => Value::from(*v),
+            HintValue::Int64(v) => Value::from(*v),
+            HintValue::UInt64(v) => Value::from(*v),
+            HintValue::Double(v) => Value::from(*v),
+            HintValue::String(v) => Value::from(v.clone()),
+            HintValue::Bytes(v) => Value::from(v.clone()),
+            HintValue::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(HintValue::to_value)
+                    .collect::<Option<Vec<_>>>()?;
+                let mut array = Array::new(items.first()?.value_signature().to_owned());
+                for item in items {
+                    array.append(item).ok()?;
+                }
+                Value::Array(array)
+            }
+            HintValue::Struct(fields) => {
+                let mut builder = StructureBuilder::new();
+                for field in fields {
+                    builder = builder.append_field(field.to_value()?);
+                }
+                Value::Structure(builder.build())
+            }
+            HintValue::Dict(entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(k, v)| Some((k.to_value()?, v.to_value()?)))
+                    .collect::<Option<Vec<_>>>()?;
+                let (key, value) = entries.first()?;
+                let mut dict = Dict::new(
+                    key.value_signature().to_owned(),
+                    value.value_signature().to_owned(),
+                );
+                for (key, value) in entries {
+                    dict.append(key, value).ok()?;
+                }
+                Value::Dict(dict)
+            }
+        };
+
+        Some(value)
+    }
+
+    /// Get the value as a string slice
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            HintValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Get the value as a boolean
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            HintValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Get any integer value widened to i64
+    ///
+    /// Senders disagree on integer widths (e.g. `value` is sent as both
+    /// `i` and `u`), so integer hints are best read through this.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            HintValue::Byte(v) => Some(i64::from(*v)),
+            HintValue::Int16(v) => Some(i64::from(*v)),
+            HintValue::UInt16(v) => Some(i64::from(*v)),
+            HintValue::Int32(v) => Some(i64::from(*v)),
+            HintValue::UInt32(v) => Some(i64::from(*v)),
+            HintValue::Int64(v) => Some(*v),
+            HintValue::UInt64(v) => i64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Hint keys decoded into `NotificationHints::image_data`
+const IMAGE_DATA_KEYS: [&str; 3] = ["image-data", "image_data", "icon_data"];
+
+/// Convert D-Bus hints into cloneable `HintValue`s
+///
+/// Image data hints are skipped, since they are large and already decoded.
+pub fn convert_hints(hints: &HashMap<String, OwnedValue>) -> HashMap<String, HintValue> {
+    hints
+        .iter()
+        .filter(|(key, _)| !IMAGE_DATA_KEYS.contains(&key.as_str()))
+        .filter_map(|(key, value)| Some((key.clone(), HintValue::from_value(value)?)))
+        .collect()
+}
+
 /// Parse notification hints from D-Bus HashMap
 ///
 /// Extracts standard hints and falls back to sensible defaults
@@ -273,6 +789,11 @@ pub fn parse_hints(hints: &HashMap<String, OwnedValue>) -> NotificationHints {
         action_icons: parse_bool(hints, "action-icons"),
         image_data: parse_image_data(hints),
         image_path: parse_string(hints, "image-path").or_else(|| parse_string(hints, "image_path")),
+        value: parse_progress(hints),
+        urls: parse_string_list(hints, "x-kde-urls"),
+        origin_name: parse_string(hints, "x-kde-origin-name"),
+        display_app_name: parse_string(hints, "x-kde-display-appname"),
+        synchronous: parse_string(hints, "x-canonical-private-synchronous"),
     }
 }
 
@@ -293,6 +814,18 @@ fn parse_string(hints: &HashMap<String, OwnedValue>, key: &str) -> Option<String
     })
 }
 
+/// Parse a string array value from hints, empty if missing
+fn parse_string_list(hints: &HashMap<String, OwnedValue>, key: &str) -> Vec<String> {
+    match hints.get(key).and_then(|v| HintValue::from_value(v)) {
+        Some(HintValue::Array(items)) => items
+            .iter()
+            .filter_map(HintValue::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
 /// Parse boolean value from hints
 fn parse_bool(hints: &HashMap<String, OwnedValue>, key: &str) -> bool {
     hints
@@ -306,29 +839,56 @@ fn parse_i32(hints: &HashMap<String, OwnedValue>, key: &str) -> Option<i32> {
     hints.get(key).and_then(|v| v.downcast_ref::<i32>().ok())
 }
 
+/// Parse the `value` progress hint, clamped to 0-100
+///
+/// The spec types it as `i`, but senders also use `u` and other widths.
+fn parse_progress(hints: &HashMap<String, OwnedValue>) -> Option<u8> {
+    hints
+        .get("value")
+        .and_then(|v| HintValue::from_value(v))
+        .and_then(|v| v.as_i64())
+        .map(|v| v.clamp(0, 100) as u8)
+}
+
 /// Parse image data from hints
 ///
-/// Image data can be in multiple formats. We try to parse the structure
-/// (width, height, rowstride, has_alpha, bits_per_sample, channels, data)
+/// Decodes the structure (width, height, rowstride, has_alpha,
+/// bits_per_sample, channels, data). `image-data` is preferred over the
+/// deprecated `image_data` and `icon_data` keys. Malformed images are
+/// skipped so the next key (or image-path / app_icon) can be used instead.
 fn parse_image_data(hints: &HashMap<String, OwnedValue>) -> Option<ImageData> {
-    // Try multiple possible keys for image data
-    let keys = ["image-data", "image_data", "icon_data"];
-
-    for key in &keys {
-        if let Some(value) = hints.get(*key) {
-            // Image data is a structure: (iiibiiay)
-            // Try to extract it - this is complex and may need adjustment
-            // For now, we'll skip the implementation and return None
-            // TODO: Implement proper image data parsing
-            tracing::debug!(
-                "Image data found but parsing not yet implemented for key: {}",
-                key
-            );
-            let _ = value; // Use value to avoid warning
+    IMAGE_DATA_KEYS.iter().find_map(|key| {
+        let image = decode_image_data(hints.get(*key)?);
+        if image.is_none() {
+            tracing::warn!("Ignoring malformed {} hint", key);
         }
-    }
+        image
+    })
+}
 
-    None
+/// Decode one `(iiibiiay)` image value, validating its geometry
+fn decode_image_data(value: &OwnedValue) -> Option<ImageData> {
+    let (width, height, rowstride, has_alpha, bits_per_sample, channels, data): (
+        i32,
+        i32,
+        i32,
+        bool,
+        i32,
+        i32,
+        Vec<u8>,
+    ) = value.try_clone().ok()?.try_into().ok()?;
+
+    let image = ImageData {
+        width,
+        height,
+        rowstride,
+        has_alpha,
+        bits_per_sample,
+        channels,
+        data,
+    };
+
+    image.is_valid().then_some(image)
 }
 
 /// Parse notification actions from D-Bus array
@@ -456,20 +1016,336 @@ mod tests {
     }
 
     #[test]
-    fn test_notification_has_actions() {
-        let mut notif = Notification {
-            id: 1,
-            app_name: "test".to_string(),
-            replaces_id: 0,
-            app_icon: "".to_string(),
-            summary: "Test".to_string(),
-            body: "Body".to_string(),
-            actions: vec![],
-            hints: NotificationHints::default(),
-            raw_hints: HashMap::new(),
-            expire_timeout: 0,
-            timestamp: Local::now(),
+    fn test_parse_hints_progress_value() {
+        let mut hints = HashMap::new();
+        hints.insert("value".to_string(), OwnedValue::from(42i32));
+        assert_eq!(parse_hints(&hints).value, Some(42));
+
+        hints.insert("value".to_string(), OwnedValue::from(150u32));
+        assert_eq!(parse_hints(&hints).value, Some(100));
+
+        hints.insert("value".to_string(), OwnedValue::from(-5i32));
+        assert_eq!(parse_hints(&hints).value, Some(0));
+
+        hints.insert("value".to_string(), OwnedValue::from(true));
+        assert_eq!(parse_hints(&hints).value, None);
+    }
+
+    #[test]
+    fn test_parse_vendor_hints() {
+        let string = |s: &str| OwnedValue::try_from(Value::from(s)).unwrap();
+        let hints = HashMap::from([
+            (
+                "x-kde-urls".to_string(),
+                OwnedValue::try_from(Value::from(vec![
+                    "file:///tmp/a.png",
+                    "https://example.com",
+                ]))
+                .unwrap(),
+            ),
+            ("x-kde-origin-name".to_string(), string("Phone")),
+            ("x-kde-display-appname".to_string(), string("Messages")),
+            (
+                "x-canonical-private-synchronous".to_string(),
+                string("volume"),
+            ),
+        ]);
+
+        let notification = Notification::from_notify_args(
+            1,
+            (
+                "kdeconnectd".to_string(),
+                0,
+                String::new(),
+                String::new(),
+                String::new(),
+                vec![],
+                hints,
+                -1,
+            ),
+        );
+        assert_eq!(
+            notification.attachments(),
+            ["file:///tmp/a.png", "https://example.com"]
+        );
+        assert_eq!(notification.origin_name(), Some("Phone"));
+        assert_eq!(notification.display_name(), "Messages");
+        assert_eq!(notification.synchronous_tag(), Some("volume"));
+
+        let plain = parse_hints(&HashMap::new());
+        assert!(plain.urls.is_empty());
+        assert_eq!(plain.synchronous, None);
+    }
+
+    fn image_value(
+        width: i32,
+        height: i32,
+        rowstride: i32,
+        has_alpha: bool,
+        channels: i32,
+        data: Vec<u8>,
+    ) -> OwnedValue {
+        let structure = zbus::zvariant::StructureBuilder::new()
+            .add_field(width)
+            .add_field(height)
+            .add_field(rowstride)
+            .add_field(has_alpha)
+            .add_field(8i32)
+            .add_field(channels)
+            .add_field(data)
+            .build();
+        OwnedValue::try_from(Value::from(structure)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_image_data_rgba() {
+        let mut hints = HashMap::new();
+        hints.insert(
+            "image-data".to_string(),
+            image_value(2, 1, 8, true, 4, vec![1, 2, 3, 4, 5, 6, 7, 8]),
+        );
+
+        let image = parse_hints(&hints).image_data.expect("image should decode");
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.to_rgba(), Some(vec![1, 2, 3, 4, 5, 6, 7, 8]));
+    }
+
+    #[test]
+    fn test_image_data_rgb_with_padding() {
+        // 1x2 RGB image, rows padded to 4 bytes, last row unpadded
+        let image = ImageData {
+            width: 1,
+            height: 2,
+            rowstride: 4,
+            has_alpha: false,
+            bits_per_sample: 8,
+            channels: 3,
+            data: vec![10, 20, 30, 0, 40, 50, 60],
+        };
+
+        assert!(image.is_valid());
+        assert_eq!(
+            image.to_rgba(),
+            Some(vec![10, 20, 30, 255, 40, 50, 60, 255])
+        );
+    }
+
+    #[test]
+    fn test_image_data_validation() {
+        let valid = ImageData {
+            width: 2,
+            height: 2,
+            rowstride: 8,
+            has_alpha: true,
+            bits_per_sample: 8,
+            channels: 4,
+            data: vec![0; 16],
+        };
+        assert!(valid.is_valid());
+
+        // Buffer too short for the declared geometry
+        let short = ImageData {
+            data: vec![0; 11],
+            ..valid.clone()
+        };
+        assert!(!short.is_valid());
+        assert_eq!(short.to_rgba(), None);
+
+        // Rowstride smaller than a row
+        let narrow = ImageData {
+            rowstride: 4,
+            ..valid.clone()
+        };
+        assert!(!narrow.is_valid());
+
+        // Channel count must match has_alpha
+        let mismatched = ImageData {
+            has_alpha: false,
+            ..valid.clone()
         };
+        assert!(!mismatched.is_valid());
+
+        // Only 8 bits per sample is supported
+        let deep = ImageData {
+            bits_per_sample: 16,
+            ..valid.clone()
+        };
+        assert!(!deep.is_valid());
+
+        let empty = ImageData { width: 0, ..valid };
+        assert!(!empty.is_valid());
+    }
+
+    #[test]
+    fn test_parse_image_data_falls_back_to_deprecated_keys() {
+        let mut hints = HashMap::new();
+        // Malformed preferred key: rowstride too small
+        hints.insert(
+            "image-data".to_string(),
+            image_value(2, 1, 1, true, 4, vec![0; 8]),
+        );
+        hints.insert(
+            "icon_data".to_string(),
+            image_value(1, 1, 3, false, 3, vec![1, 2, 3]),
+        );
+
+        let image = parse_hints(&hints)
+            .image_data
+            .expect("deprecated key should decode");
+        assert_eq!(image.channels, 3);
+
+        // Wrong type entirely
+        let mut hints = HashMap::new();
+        hints.insert("image-data".to_string(), OwnedValue::from(5u32));
+        assert!(parse_hints(&hints).image_data.is_none());
+    }
+
+    #[test]
+    fn test_convert_hints() {
+        let mut hints = HashMap::new();
+        hints.insert("value".to_string(), OwnedValue::from(42i32));
+        hints.insert("x-kde-urls".to_string(), {
+            let urls = vec!["file:///tmp/a.png"];
+            OwnedValue::try_from(Value::from(urls)).unwrap()
+        });
+        hints.insert(
+            "x-canonical-private-synchronous".to_string(),
+            OwnedValue::try_from(Value::from("volume")).unwrap(),
+        );
+        hints.insert(
+            "image-data".to_string(),
+            image_value(1, 1, 3, false, 3, vec![1, 2, 3]),
+        );
+
+        let raw = convert_hints(&hints);
+        assert_eq!(raw.get("value"), Some(&HintValue::Int32(42)));
+        assert_eq!(raw["value"].as_i64(), Some(42));
+        assert_eq!(
+            raw.get("x-kde-urls"),
+            Some(&HintValue::Array(vec![HintValue::String(
+                "file:///tmp/a.png".to_string()
+            )]))
+        );
+        assert_eq!(
+            raw["x-canonical-private-synchronous"].as_str(),
+            Some("volume")
+        );
+        // Already decoded into hints.image_data
+        assert!(!raw.contains_key("image-data"));
+    }
+
+    #[test]
+    fn test_hint_value_bytes_and_variants() {
+        let bytes = Value::from(vec![1u8, 2, 3]);
+        assert_eq!(
+            HintValue::from_value(&bytes),
+            Some(HintValue::Bytes(vec![1, 2, 3]))
+        );
+
+        let nested = Value::Value(Box::new(Value::from(true)));
+        assert_eq!(HintValue::from_value(&nested), Some(HintValue::Bool(true)));
+    }
+
+    #[test]
+    fn test_hint_value_round_trip() {
+        let hints = [
+            HintValue::UInt32(7),
+            HintValue::String("thread-7".to_string()),
+            HintValue::Bytes(vec![1, 2]),
+            HintValue::Array(vec![HintValue::Int32(1), HintValue::Int32(2)]),
+            HintValue::Struct(vec![
+                HintValue::String("a".to_string()),
+                HintValue::Bool(false),
+            ]),
+            HintValue::Dict(vec![(
+                HintValue::String("key".to_string()),
+                HintValue::Double(0.5),
+            )]),
+        ];
+        for hint in hints {
+            let value = hint.to_value().expect("hint should convert");
+            assert_eq!(HintValue::from_value(&value), Some(hint));
+        }
+
+        // Empty containers have no element signature to use
+        assert_eq!(HintValue::Array(vec![]).to_value(), None);
+    }
+
+    #[test]
+    fn test_raw_hints_survive_clone_and_serialization() {
+        let mut hints = HashMap::new();
+        hints.insert("x-vendor-flag".to_string(), OwnedValue::from(7u32));
+
+        let notif = Notification::from_notify_args(
+            1,
+            (
+                "app".to_string(),
+                0,
+                String::new(),
+                "Summary".to_string(),
+                String::new(),
+                vec![],
+                hints,
+                -1,
+            ),
+        );
+
+        let cloned = notif.clone();
+        assert_eq!(
+            cloned.raw_hint("x-vendor-flag"),
+            Some(&HintValue::UInt32(7))
+        );
+
+        let serialized = ron::to_string(&cloned).unwrap();
+        let restored: Notification = ron::from_str(&serialized).unwrap();
+        assert_eq!(
+            restored.raw_hint("x-vendor-flag"),
+            Some(&HintValue::UInt32(7))
+        );
+    }
+
+    #[test]
+    fn test_notification_from_notify_args() {
+        let mut hints = HashMap::new();
+        hints.insert("urgency".to_string(), OwnedValue::from(2u8));
+
+        let notif = Notification::from_notify_args(
+            7,
+            (
+                "firefox".to_string(),
+                3,
+                "firefox".to_string(),
+                "Download complete".to_string(),
+                "file.zip".to_string(),
+                vec!["default".to_string(), "Open".to_string()],
+                hints,
+                5000,
+            ),
+        );
+
+        assert_eq!(notif.id, 7);
+        assert_eq!(notif.replaces_id, 3);
+        assert_eq!(notif.summary, "Download complete");
+        assert_eq!(notif.actions.len(), 1);
+        assert_eq!(notif.urgency(), Urgency::Critical);
+        assert_eq!(notif.expire_timeout, 5000);
+    }
+
+    #[test]
+    fn test_notification_has_actions() {
+        let mut notif = Notification::from_notify_args(
+            1,
+            (
+                "test".to_string(),
+                0,
+                String::new(),
+                "Test".to_string(),
+                "Body".to_string(),
+                vec![],
+                HashMap::new(),
+                0,
+            ),
+        );
 
         assert!(!notif.has_actions());
 
@@ -478,4 +1354,122 @@ mod tests {
             .push(NotificationAction::new("default", "Open"));
         assert!(notif.has_actions());
     }
+
+    #[test]
+    fn test_default_action_not_a_button() {
+        let notif = Notification::from_notify_args(
+            1,
+            (
+                "app".to_string(),
+                0,
+                String::new(),
+                "Summary".to_string(),
+                String::new(),
+                vec![
+                    "reply".to_string(),
+                    "Reply".to_string(),
+                    "default".to_string(),
+                    "Open".to_string(),
+                    "mute".to_string(),
+                    "Mute".to_string(),
+                ],
+                HashMap::new(),
+                -1,
+            ),
+        );
+
+        assert_eq!(
+            notif.default_action().map(|a| a.label.as_str()),
+            Some("Open")
+        );
+        let buttons: Vec<_> = notif
+            .button_actions()
+            .iter()
+            .map(|a| a.key.as_str())
+            .collect();
+        assert_eq!(buttons, vec!["reply", "mute"]);
+    }
+
+    #[test]
+    fn test_inline_reply() {
+        let mut notif = Notification::from_notify_args(
+            1,
+            (
+                "chat".to_string(),
+                0,
+                String::new(),
+                "Alice".to_string(),
+                "Lunch?".to_string(),
+                vec![
+                    "inline-reply".to_string(),
+                    "Reply".to_string(),
+                    "mark-read".to_string(),
+                    "Mark as read".to_string(),
+                ],
+                HashMap::new(),
+                -1,
+            ),
+        );
+
+        // The reply action gets a text field instead of a button
+        assert!(notif.accepts_reply());
+        let buttons: Vec<_> = notif
+            .button_actions()
+            .iter()
+            .map(|a| a.key.as_str())
+            .collect();
+        assert_eq!(buttons, vec!["mark-read"]);
+        assert_eq!(notif.reply_placeholder(), None);
+
+        // KDE asks for replies through a hint alone
+        notif.actions.clear();
+        assert!(!notif.accepts_reply());
+        notif.raw_hints.insert(
+            "x-kde-reply-placeholder-text".to_string(),
+            HintValue::String("Reply to Alice".to_string()),
+        );
+        notif.raw_hints.insert(
+            "x-kde-reply-submit-button-text".to_string(),
+            HintValue::String("Send".to_string()),
+        );
+        assert!(notif.accepts_reply());
+        assert_eq!(notif.reply_placeholder(), Some("Reply to Alice"));
+        assert_eq!(notif.reply_submit_label(), Some("Send"));
+    }
+
+    #[test]
+    fn test_reply_destination() {
+        let mut notif = Notification::from_notify_args(
+            1,
+            (
+                "Chat".to_string(),
+                0,
+                String::new(),
+                "Alice".to_string(),
+                "Lunch?".to_string(),
+                vec!["inline-reply".to_string(), "Reply".to_string()],
+                HashMap::new(),
+                -1,
+            ),
+        );
+
+        // Replies are never broadcast
+        assert_eq!(notif.reply_destination(), None);
+
+        notif.sender = Some(":1.42".to_string());
+        assert_eq!(notif.reply_destination(), Some(":1.42"));
+
+        // Portal apps can't receive NotificationReplied
+        notif.portal = Some(PortalOrigin {
+            app_id: "org.example.Chat".to_string(),
+            portal_id: "lunch".to_string(),
+            actions: HashMap::new(),
+        });
+        assert_eq!(notif.reply_destination(), None);
+
+        // Nor can notifications that never asked for a reply
+        notif.portal = None;
+        notif.actions.clear();
+        assert_eq!(notif.reply_destination(), None);
+    }
 }
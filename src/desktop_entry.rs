@@ -0,0 +1,370 @@
+// Desktop entry resolution
+//
+// Resolves `desktop-entry` hints to the application's localized name and
+// icon by reading `<desktop-entry>.desktop` from the XDG data directories.
+// Reference: https://specifications.freedesktop.org/desktop-entry-spec/latest/
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::dbus::AppIdentity;
+
+/// Most desktop entry IDs kept in the resolver's cache
+///
+/// IDs come from senders, so the cache is emptied when it fills up rather
+/// than growing with every made-up ID.
+const MAX_CACHED_ENTRIES: usize = 256;
+
+/// How long a missing desktop entry is remembered
+///
+/// Apps installed while the applet runs are found once this passes.
+const MISS_TTL: Duration = Duration::from_secs(60);
+
+/// A cached resolution
+#[derive(Debug, Clone)]
+enum CachedEntry {
+    Found(AppIdentity),
+    Missing { since: Instant },
+}
+
+/// Get the XDG data directories, in priority order
+///
+/// `$XDG_DATA_HOME` first, then each of `$XDG_DATA_DIRS`
+/// (defaulting to /usr/local/share:/usr/share).
+pub fn xdg_data_dirs() -> Vec<PathBuf> {
+    let mut data_dirs: Vec<PathBuf> = dirs::data_dir().into_iter().collect();
+
+    let system_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    data_dirs.extend(system_dirs.split(':').map(PathBuf::from));
+
+    data_dirs
+}
+
+/// Resolves desktop entry IDs to application identities
+///
+/// Results are cached, up to MAX_CACHED_ENTRIES IDs; misses expire after
+/// MISS_TTL. Resolving reads desktop files, so do it off the UI thread.
+#[derive(Debug, Clone)]
+pub struct DesktopEntryResolver {
+    /// `applications` directories, in priority order
+    search_dirs: Vec<PathBuf>,
+
+    /// Locale keys to try for localized values, most specific first
+    locales: Vec<String>,
+
+    /// Desktop entry ID -> resolved identity
+    cache: HashMap<String, CachedEntry>,
+}
+
+impl Default for DesktopEntryResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DesktopEntryResolver {
+    /// Create a resolver for the XDG data directories and current locale
+    ///
+    /// The locale is read from LC_ALL, LC_MESSAGES or LANG, in that order.
+    pub fn new() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty());
+
+        Self::with_search_dirs(
+            xdg_data_dirs()
+                .into_iter()
+                .map(|dir| dir.join("applications"))
+                .collect(),
+            locale.as_deref(),
+        )
+    }
+
+    /// Create a resolver for the given `applications` directories and locale
+    pub fn with_search_dirs(search_dirs: Vec<PathBuf>, locale: Option<&str>) -> Self {
+        Self {
+            search_dirs,
+            locales: locale.map(locale_keys).unwrap_or_default(),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Resolve a desktop entry ID (e.g. "firefox" or "org.gnome.Nautilus")
+    ///
+    /// A trailing `.desktop` is ignored. Returns None if no entry is found,
+    /// it has no name, or the ID could point outside the search directories
+    /// (see `is_valid_id`).
+    pub fn resolve(&mut self, desktop_entry: &str) -> Option<AppIdentity> {
+        self.resolve_at(desktop_entry, Instant::now())
+    }
+
+    /// Resolve a desktop entry ID, expiring cached misses as of `now`
+    fn resolve_at(&mut self, desktop_entry: &str, now: Instant) -> Option<AppIdentity> {
+        let id = desktop_entry
+            .strip_suffix(".desktop")
+            .unwrap_or(desktop_entry);
+
+        if !is_valid_id(id) {
+            tracing::debug!("Ignoring invalid desktop entry ID '{}'", id);
+            return None;
+        }
+
+        match self.cache.get(id) {
+            Some(CachedEntry::Found(identity)) => return Some(identity.clone()),
+            Some(CachedEntry::Missing { since }) if now.duration_since(*since) < MISS_TTL => {
+                return None;
+            }
+            _ => {}
+        }
+
+        let identity = self.load(id);
+        if self.cache.len() >= MAX_CACHED_ENTRIES && !self.cache.contains_key(id) {
+            self.cache.clear();
+        }
+        let cached = match &identity {
+            Some(identity) => CachedEntry::Found(identity.clone()),
+            None => {
+                tracing::debug!("No desktop entry found for '{}'", id);
+                CachedEntry::Missing { since: now }
+            }
+        };
+        self.cache.insert(id.to_string(), cached);
+        identity
+    }
+
+    /// Find and parse the desktop file for an ID
+    fn load(&self, id: &str) -> Option<AppIdentity> {
+        // IDs of entries in subdirectories use '-' for '/' (kde-foo -> kde/foo)
+        let mut candidates = vec![format!("{}.desktop", id)];
+        let nested = id.replace('-', "/");
+        if nested.split('/').all(is_valid_segment) {
+            candidates.push(format!("{}.desktop", nested));
+        }
+
+        let contents = self.search_dirs.iter().find_map(|dir| {
+            candidates
+                .iter()
+                .find_map(|file| std::fs::read_to_string(dir.join(file)).ok())
+        })?;
+
+        self.parse(id, &contents)
+    }
+
+    /// Read the localized Name and Icon from the [Desktop Entry] group
+    fn parse(&self, id: &str, contents: &str) -> Option<AppIdentity> {
+        let mut values: HashMap<&str, &str> = HashMap::new();
+        let mut in_entry = false;
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+                continue;
+            }
+            if !in_entry || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                values.entry(key.trim()).or_insert(value.trim());
+            }
+        }
+
+        let localized = |key: &str| {
+            self.locales
+                .iter()
+                .find_map(|locale| values.get(format!("{}[{}]", key, locale).as_str()))
+                .or_else(|| values.get(key))
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+        };
+
+        Some(AppIdentity {
+            desktop_entry: id.to_string(),
+            name: localized("Name")?,
+            icon: localized("Icon"),
+        })
+    }
+}
+
+/// Check that a desktop entry ID names a file inside the search directories
+///
+/// The ID comes from the sender: an absolute path would replace the search
+/// directory when joined, and `..` would walk out of it, letting any client
+/// show its own name and icon under a trusted-looking entry.
+fn is_valid_id(id: &str) -> bool {
+    !id.contains(['/', '\\', '\0']) && is_valid_segment(id)
+}
+
+/// Check one path segment of a desktop file name
+fn is_valid_segment(segment: &str) -> bool {
+    !segment.is_empty() && !segment.starts_with('.')
+}
+
+/// Locale keys to match `Key[locale]` entries, most specific first
+///
+/// For `de_DE.UTF-8@euro`: `de_DE@euro`, `de_DE`, `de@euro`, `de`.
+fn locale_keys(locale: &str) -> Vec<String> {
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    // The encoding is not part of the key
+    let locale = locale.split('.').next().unwrap_or(locale);
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
+
+    if lang.is_empty() || lang == "C" || lang == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut keys = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        keys.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        keys.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        keys.push(format!("{}@{}", lang, modifier));
+    }
+    keys.push(lang.to_string());
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const FIREFOX: &str = "[Desktop Entry]\n\
+        Name=Firefox\n\
+        Name[de]=Firefox Browser\n\
+        Icon=firefox\n\
+        \n\
+        [Desktop Action new-window]\n\
+        Name=New Window\n";
+
+    fn resolver(dir: &tempfile::TempDir, locale: Option<&str>) -> DesktopEntryResolver {
+        DesktopEntryResolver::with_search_dirs(vec![dir.path().to_path_buf()], locale)
+    }
+
+    #[test]
+    fn test_resolve_name_and_icon() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("firefox.desktop"), FIREFOX).unwrap();
+
+        let identity = resolver(&dir, None).resolve("firefox").unwrap();
+        assert_eq!(identity.name, "Firefox");
+        assert_eq!(identity.icon.as_deref(), Some("firefox"));
+        assert_eq!(identity.desktop_entry, "firefox");
+    }
+
+    #[test]
+    fn test_resolve_localized_name() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("firefox.desktop"), FIREFOX).unwrap();
+
+        let mut resolver = resolver(&dir, Some("de_AT.UTF-8"));
+        assert_eq!(
+            resolver.resolve("firefox.desktop").unwrap().name,
+            "Firefox Browser"
+        );
+    }
+
+    #[test]
+    fn test_resolve_subdirectory_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("kde")).unwrap();
+        fs::write(
+            dir.path().join("kde/konsole.desktop"),
+            "[Desktop Entry]\nName=Konsole\n",
+        )
+        .unwrap();
+
+        let identity = resolver(&dir, None).resolve("kde-konsole").unwrap();
+        assert_eq!(identity.name, "Konsole");
+        assert_eq!(identity.icon, None);
+    }
+
+    #[test]
+    fn test_resolve_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut resolver = resolver(&dir, None);
+        let now = Instant::now();
+
+        // Misses are cached for a while
+        assert_eq!(resolver.resolve_at("firefox", now), None);
+        fs::write(dir.path().join("firefox.desktop"), FIREFOX).unwrap();
+        assert_eq!(resolver.resolve_at("firefox", now), None);
+
+        // An app installed since is found once the miss expires
+        let identity = resolver.resolve_at("firefox", now + MISS_TTL);
+        assert_eq!(
+            identity.map(|identity| identity.name).as_deref(),
+            Some("Firefox")
+        );
+
+        // Hits stay cached
+        fs::remove_file(dir.path().join("firefox.desktop")).unwrap();
+        assert!(resolver.resolve_at("firefox", now + MISS_TTL * 2).is_some());
+    }
+
+    #[test]
+    fn test_resolve_rejects_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = dir.path().join("applications");
+        fs::create_dir_all(&inner).unwrap();
+        fs::write(dir.path().join("x.desktop"), FIREFOX).unwrap();
+        fs::write(dir.path().join(".hidden.desktop"), FIREFOX).unwrap();
+
+        let mut resolver = DesktopEntryResolver::with_search_dirs(vec![inner], None);
+        let absolute = dir.path().join("x");
+        assert_eq!(resolver.resolve(absolute.to_str().unwrap()), None);
+        assert_eq!(resolver.resolve("/tmp/x"), None);
+        assert_eq!(resolver.resolve("../x"), None);
+        // '-' maps to '/', so this would become ../x too
+        assert_eq!(resolver.resolve("..-x"), None);
+        assert_eq!(resolver.resolve("a\0b"), None);
+        assert!(resolver.cache.is_empty());
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut resolver = resolver(&dir, None);
+
+        for i in 0..MAX_CACHED_ENTRIES * 2 {
+            resolver.resolve(&format!("made-up-{i}"));
+        }
+        assert!(resolver.cache.len() <= MAX_CACHED_ENTRIES);
+    }
+
+    #[test]
+    fn test_resolve_without_name() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("broken.desktop"),
+            "[Desktop Entry]\nIcon=x\n",
+        )
+        .unwrap();
+
+        assert_eq!(resolver(&dir, None).resolve("broken"), None);
+    }
+
+    #[test]
+    fn test_locale_keys() {
+        assert_eq!(
+            locale_keys("de_DE.UTF-8@euro"),
+            vec!["de_DE@euro", "de_DE", "de@euro", "de"]
+        );
+        assert_eq!(locale_keys("fr"), vec!["fr"]);
+        assert!(locale_keys("C.UTF-8").is_empty());
+    }
+}
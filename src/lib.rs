@@ -4,9 +4,12 @@
 // including D-Bus communication, notification management, and UI components.
 
 pub mod accessibility;
+pub mod activation;
 pub mod config;
 pub mod dbus;
+pub mod desktop_entry;
 pub mod manager;
+pub mod sound;
 pub mod ui;
 
 // Re-export commonly used types
@@ -3,7 +3,7 @@
 
 use cosmic::iced::Task;
 use cosmic::{Application, Element};
-use cosmic_applet_notifications::{config, dbus, manager, ui};
+use cosmic_applet_notifications::{activation, config, dbus, desktop_entry, manager, sound, ui};
 
 /// Main application state
 pub struct NotificationApplet {
@@ -33,6 +33,17 @@ pub struct NotificationApplet {
     /// Animation states for notifications (notification_id -> animation)
     notification_animations: std::collections::HashMap<u32, ui::animation::NotificationAnimation>,
 
+    /// Resolved notification icons, built once per notification (notification_id -> handle)
+    notification_icons: std::collections::HashMap<u32, cosmic::widget::icon::Handle>,
+
+    /// Resolves icon names, paths and URIs for notification icons, shared
+    /// with the blocking tasks that do the lookups
+    icon_resolver: std::sync::Arc<std::sync::Mutex<ui::icons::IconResolver>>,
+
+    /// Resolves `desktop-entry` hints to app names and icons, shared with
+    /// the blocking tasks that read the desktop files
+    desktop_entries: std::sync::Arc<std::sync::Mutex<desktop_entry::DesktopEntryResolver>>,
+
     /// Popup animation state
     popup_animation: Option<ui::animation::PopupAnimation>,
 
@@ -41,6 +52,27 @@ pub struct NotificationApplet {
 
     /// Whether reduced motion is preferred (accessibility)
     prefers_reduced_motion: bool,
+
+    /// Notification sound playback
+    sound: sound::SoundPlayer,
+
+    /// Sound theme set on the desktop, used unless the config names one
+    desktop_sound_theme: Option<String>,
+
+    /// Source of activation tokens handed to apps when an action is invoked
+    activation: Box<dyn activation::ActivationTokenProvider>,
+
+    /// Reply text being typed, for notifications accepting inline replies
+    reply_drafts: std::collections::HashMap<u32, String>,
+
+    /// State last reported through the control interface's signals
+    control_snapshot: dbus::control::ControlSnapshot,
+
+    /// Whether the passive listener is connected to the session bus
+    listener_status: dbus::ListenerStatus,
+
+    /// Whether the popup's diagnostics section is expanded
+    show_diagnostics: bool,
 }
 
 /// Messages that drive the application
@@ -58,6 +90,41 @@ pub enum Message {
     /// Dismiss a notification by ID
     DismissNotification(u32),
 
+    /// A notification was closed outside the applet (CloseNotification call,
+    /// or NotificationClosed from another daemon)
+    NotificationWithdrawn { id: u32, reason: dbus::CloseReason },
+
+    /// An action was invoked outside the applet (e.g. in another daemon's popup)
+    ExternalActionInvoked { id: u32, action_key: String },
+
+    /// An activation token was issued for an action invoked outside the applet
+    ExternalActivationToken { id: u32, token: String },
+
+    /// A notification's icon was resolved (`timestamp` identifies the update)
+    IconResolved {
+        id: u32,
+        timestamp: chrono::DateTime<chrono::Local>,
+        handle: Option<cosmic::widget::icon::Handle>,
+    },
+
+    /// A notification's desktop entry was resolved off the UI thread
+    ///
+    /// Like `IconResolved`, the timestamp identifies the notification's version.
+    IdentityResolved {
+        id: u32,
+        timestamp: chrono::DateTime<chrono::Local>,
+        identity: Option<dbus::AppIdentity>,
+    },
+
+    /// The notification listener connected to or lost the session bus
+    ListenerStatusChanged(dbus::ListenerStatus),
+
+    /// Expand or collapse the diagnostics section of the popup
+    ToggleDiagnostics,
+
+    /// Dismiss all active notifications
+    ClearAllNotifications,
+
     /// Update configuration
     UpdateConfig(config::AppletConfig),
 
@@ -70,9 +137,21 @@ pub enum Message {
         action_key: String,
     },
 
+    /// Reply text edited for a notification
+    ReplyInput { notification_id: u32, text: String },
+
+    /// Send the typed reply for a notification
+    SendReply(u32),
+
     /// Toggle Do Not Disturb mode
     ToggleDND,
 
+    /// Clear the notification history
+    ClearHistory,
+
+    /// Request made through the control D-Bus interface
+    Control(dbus::control::ControlRequest),
+
     /// Set minimum urgency level (0=Low, 1=Normal, 2=Critical)
     SetUrgencyLevel(u8),
 
@@ -138,17 +217,41 @@ pub enum Message {
     /// Update prefers-reduced-motion accessibility setting
     UpdatePrefersReducedMotion(bool),
 
+    /// The desktop's sound theme setting was read (None if unavailable)
+    DesktopSoundThemeDetected(Option<String>),
+
     /// Tick for periodic updates
     Tick,
 
-    /// Keyboard event
-    KeyboardEvent(cosmic::iced::keyboard::Event),
+    /// Keyboard event, and whether a widget (e.g. a reply field) already handled it
+    KeyboardEvent(cosmic::iced::keyboard::Event, cosmic::iced::event::Status),
 }
 
-// Implement From<Notification> for Message to work with subscription
-impl From<dbus::Notification> for Message {
-    fn from(notification: dbus::Notification) -> Self {
-        Message::NotificationReceived(Box::new(notification))
+// Implement From<NotificationEvent> for Message to work with both the listener
+// and the daemon mode subscription
+impl From<dbus::NotificationEvent> for Message {
+    fn from(event: dbus::NotificationEvent) -> Self {
+        match event {
+            dbus::NotificationEvent::Notify(notification) => {
+                Message::NotificationReceived(notification)
+            }
+            dbus::NotificationEvent::Closed { id, reason } => {
+                Message::NotificationWithdrawn { id, reason }
+            }
+            dbus::NotificationEvent::ActionInvoked { id, action_key } => {
+                Message::ExternalActionInvoked { id, action_key }
+            }
+            dbus::NotificationEvent::ActivationToken { id, token } => {
+                Message::ExternalActivationToken { id, token }
+            }
+            dbus::NotificationEvent::Status(status) => Message::ListenerStatusChanged(status),
+        }
+    }
+}
+
+impl From<dbus::control::ControlRequest> for Message {
+    fn from(request: dbus::control::ControlRequest) -> Self {
+        Message::Control(request)
     }
 }
 
@@ -176,6 +279,115 @@ impl NotificationApplet {
         }
     }
 
+    /// Report every closure the manager recorded
+    ///
+    /// Call this after any manager operation that can remove notifications
+    /// (dismiss, expire, clear, replace, evict). See `report_closure()`.
+    fn report_closed_notifications(&mut self) {
+        let daemon_mode = self.config.daemon_mode;
+        for closed in self.take_closed_notifications() {
+            tokio::spawn(report_closure(closed, daemon_mode));
+        }
+    }
+
+    /// Take the manager's recorded closures and drop their per-card state
+    ///
+    /// The caller must pass each returned closure to `report_closure()`.
+    fn take_closed_notifications(&mut self) -> Vec<manager::ClosedNotification> {
+        let closed = self.manager.take_closed_notifications();
+        for closure in &closed {
+            // Evicted cards never finish a dismissal
+            self.forget_card(closure.id);
+        }
+        closed
+    }
+
+    /// Play sounds from the configured theme, else the desktop's
+    fn apply_sound_theme(&mut self) {
+        let theme = self
+            .config
+            .sound_theme
+            .as_deref()
+            .or(self.desktop_sound_theme.as_deref())
+            .unwrap_or(sound::theme::FALLBACK_THEME);
+        self.sound.set_theme(theme);
+    }
+
+    /// Drop the per-card state of a notification that is no longer shown
+    fn forget_card(&mut self, id: u32) {
+        self.notification_animations.remove(&id);
+        self.notification_icons.remove(&id);
+        self.progress_indicators.remove(&id);
+        self.reply_drafts.remove(&id);
+    }
+
+    /// Resolve a notification's desktop entry on a blocking thread
+    ///
+    /// Reads desktop files, too slow for `update()`. The identity arrives in
+    /// `Message::IdentityResolved`.
+    fn resolve_identity(&self, notification: &dbus::Notification) -> Task<cosmic::Action<Message>> {
+        let Some(desktop_entry) = notification.desktop_entry().map(str::to_string) else {
+            return Task::none();
+        };
+        let resolver = std::sync::Arc::clone(&self.desktop_entries);
+        let id = notification.id;
+        let timestamp = notification.timestamp;
+
+        Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    resolver
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .resolve(&desktop_entry)
+                })
+                .await
+                .ok()
+                .flatten()
+            },
+            move |identity| {
+                cosmic::Action::App(Message::IdentityResolved {
+                    id,
+                    timestamp,
+                    identity,
+                })
+            },
+        )
+    }
+
+    /// Resolve a notification's icon on a blocking thread
+    ///
+    /// Themed lookups read icon theme directories, too slow for `update()`.
+    /// The handle arrives in `Message::IconResolved`.
+    fn resolve_icon(&self, notification: dbus::Notification) -> Task<cosmic::Action<Message>> {
+        let resolver = std::sync::Arc::clone(&self.icon_resolver);
+        let id = notification.id;
+        let timestamp = notification.timestamp;
+
+        Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    let mut resolver = resolver
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    // Icons of the previous theme are forgotten when it changes
+                    resolver.set_theme(cosmic::icon_theme::default());
+                    ui::widgets::notification_card::notification_icon(&notification, &mut resolver)
+                })
+                .await
+                .ok()
+                .flatten()
+            },
+            move |handle| {
+                cosmic::Action::App(Message::IconResolved {
+                    id,
+                    timestamp,
+                    handle,
+                })
+            },
+        )
+    }
+
     /// Validate and fix selection indices after notifications change
     /// Call this after removing notifications to keep selection in bounds
     fn validate_selection(&mut self) {
@@ -206,72 +418,97 @@ impl NotificationApplet {
             }
         }
     }
-}
 
-impl Application for NotificationApplet {
-    type Executor = cosmic::executor::Default;
-    type Flags = ();
-    type Message = Message;
-
-    const APP_ID: &'static str = "com.system76.CosmicAppletNotifications";
-
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
-    }
-
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
+    /// Emit the control interface's signals for changes since the last call
+    ///
+    /// Runs after every message, so scripts hear about changes from any source.
+    fn publish_control_state(&mut self) {
+        let snapshot = dbus::control::ControlSnapshot::of(&self.manager);
+        let lists_changed = snapshot.lists_changed(&self.control_snapshot);
+        let dnd_changed = snapshot.dnd_changed(&self.control_snapshot);
+        self.control_snapshot = snapshot;
+
+        if lists_changed {
+            tokio::spawn(async {
+                if let Err(e) = dbus::control::emit_changed().await {
+                    tracing::error!("Failed to emit Changed: {}", e);
+                }
+            });
+        }
+        if let Some(enabled) = dnd_changed {
+            tokio::spawn(async move {
+                if let Err(e) = dbus::control::emit_dnd_changed(enabled).await {
+                    tracing::error!("Failed to emit DndChanged: {}", e);
+                }
+            });
+        }
     }
 
-    fn init(
-        core: cosmic::app::Core,
-        _flags: Self::Flags,
-    ) -> (Self, Task<cosmic::Action<Self::Message>>) {
-        // Load configuration
-        let config_helper = config::ConfigHelper::new();
-        let config = config_helper.load();
-
-        tracing::info!("Configuration loaded from {:?}", config_helper.path());
-        tracing::debug!("Config: {:?}", config);
-
-        // Initialize manager with history from disk and config settings
-        let mut manager = if config.history_enabled {
-            manager::NotificationManager::with_history(
-                config.max_history_items,
-                config.history_retention_days,
-            )
-        } else {
-            manager::NotificationManager::new()
-        };
-        manager.set_do_not_disturb(config.do_not_disturb);
-        manager.set_min_urgency_level(config.min_urgency_level);
-        manager.load_app_filters(config.app_filters.clone());
-
-        let app = NotificationApplet {
-            core,
-            manager,
-            config_helper,
-            config,
-            popup_id: None,
-            selected_notification_index: None,
-            selected_action_index: None,
-            notification_animations: std::collections::HashMap::new(),
-            popup_animation: None,
-            progress_indicators: std::collections::HashMap::new(),
-            prefers_reduced_motion: false, // Will be detected asynchronously
-        };
-
-        // Detect prefers-reduced-motion accessibility setting on startup
-        let detect_task = Task::future(async {
-            let prefers_reduced =
-                cosmic_applet_notifications::accessibility::detect_prefers_reduced_motion().await;
-            cosmic::Action::App(Message::UpdatePrefersReducedMotion(prefers_reduced))
-        });
-
-        (app, detect_task)
+    /// Handle a control interface request with the matching message
+    fn handle_control_request(
+        &mut self,
+        request: dbus::control::ControlRequest,
+    ) -> Task<cosmic::Action<Message>> {
+        use dbus::control::{ControlRequest, NotificationSummary};
+
+        match request {
+            ControlRequest::ToggleDnd => self.update(Message::ToggleDND),
+            ControlRequest::SetDnd(enabled) => {
+                if enabled == self.config.do_not_disturb {
+                    Task::none()
+                } else {
+                    self.update(Message::ToggleDND)
+                }
+            }
+            ControlRequest::GetDnd(reply) => {
+                reply.send(self.config.do_not_disturb);
+                Task::none()
+            }
+            ControlRequest::ListActive(reply) => {
+                let active = self.manager.get_active_notifications();
+                reply.send(active.iter().map(NotificationSummary::from).collect());
+                Task::none()
+            }
+            ControlRequest::ListHistory(reply) => {
+                let history = self.manager.history();
+                reply.send(history.iter().map(NotificationSummary::from).collect());
+                Task::none()
+            }
+            ControlRequest::Dismiss { id, reply } => {
+                let found = self.manager.get_notification(id).is_some();
+                reply.send(found);
+                if found {
+                    self.update(Message::DismissNotification(id))
+                } else {
+                    Task::none()
+                }
+            }
+            ControlRequest::ClearAll => self.update(Message::ClearAllNotifications),
+            ControlRequest::ClearHistory => self.update(Message::ClearHistory),
+            ControlRequest::InvokeAction {
+                id,
+                action_key,
+                reply,
+            } => {
+                let found = self
+                    .manager
+                    .get_notification(id)
+                    .is_some_and(|notification| notification.has_action(&action_key));
+                reply.send(found);
+                if found {
+                    self.update(Message::InvokeAction {
+                        notification_id: id,
+                        action_key,
+                    })
+                } else {
+                    Task::none()
+                }
+            }
+        }
     }
 
-    fn update(&mut self, message: Self::Message) -> Task<cosmic::Action<Self::Message>> {
+    /// Handle a message; `update()` publishes the resulting state afterwards
+    fn handle_message(&mut self, message: Message) -> Task<cosmic::Action<Message>> {
         use cosmic::iced::window;
 
         match message {
@@ -334,11 +571,43 @@ impl Application for NotificationApplet {
 
             Message::NotificationReceived(notification) => {
                 // Dereference the Box to get the notification
-                let notification = *notification;
+                let mut notification = *notification;
+
+                // Bound and clean the untrusted payload before it is shown or stored
+                dbus::sanitize_notification(&mut notification);
 
                 // Add notification to manager
                 let action = self.manager.add_notification(notification.clone());
 
+                // Replaced or evicted notifications are reported as closed
+                self.report_closed_notifications();
+
+                let visible = matches!(
+                    action,
+                    manager::NotificationAction::Displayed | manager::NotificationAction::Updated
+                );
+
+                // Resolve the icon once, the card reuses the handle. The
+                // desktop entry can name the icon, so it is resolved first
+                // (also for notifications that went straight to history).
+                let shown = self
+                    .manager
+                    .get_notification(notification.id)
+                    .filter(|_| visible)
+                    .cloned();
+                let icon_task = match shown {
+                    _ if notification.app_identity.is_none()
+                        && notification.desktop_entry().is_some() =>
+                    {
+                        self.resolve_identity(&notification)
+                    }
+                    Some(shown) => self.resolve_icon(shown),
+                    None => {
+                        self.notification_icons.remove(&notification.id);
+                        Task::none()
+                    }
+                };
+
                 tracing::info!(
                     "Received notification from {}: {} (action: {:?})",
                     notification.app_name,
@@ -346,12 +615,25 @@ impl Application for NotificationApplet {
                     action
                 );
 
+                if !visible {
+                    return Task::none();
+                }
+
+                // Updates of a visible notification don't play its sound again
+                if action == manager::NotificationAction::Displayed {
+                    self.sound
+                        .play_for(&notification, self.manager.is_do_not_disturb());
+                }
+
                 // Start appear animation if enabled (respect accessibility preferences)
-                if self.config.animations.enabled
+                // In-place updates keep the card where it is, without animating
+                if action == manager::NotificationAction::Displayed
+                    && self.config.animations.enabled
                     && self.config.animations.notification_appear
                     && !self.prefers_reduced_motion
                 {
-                    return self.update(Message::StartAppearAnimation(notification.id));
+                    let appear = self.update(Message::StartAppearAnimation(notification.id));
+                    return Task::batch([icon_task, appear]);
                 }
 
                 // Create progress indicator for timed notifications
@@ -362,6 +644,56 @@ impl Application for NotificationApplet {
                     );
                     self.progress_indicators.insert(notification.id, indicator);
                 }
+
+                return icon_task;
+            }
+
+            Message::IconResolved {
+                id,
+                timestamp,
+                handle,
+            } => {
+                // Drop results for notifications that closed or changed since
+                let current = self
+                    .manager
+                    .get_notification(id)
+                    .is_some_and(|notification| notification.timestamp == timestamp);
+                if current {
+                    match handle {
+                        Some(handle) => {
+                            self.notification_icons.insert(id, handle);
+                        }
+                        None => {
+                            self.notification_icons.remove(&id);
+                        }
+                    }
+                }
+            }
+
+            Message::IdentityResolved {
+                id,
+                timestamp,
+                identity,
+            } => {
+                let shown = match identity {
+                    Some(identity) => {
+                        let shown = self.manager.set_app_identity(id, timestamp, identity);
+                        // Hidden by an app filter on its resolved name
+                        if !shown && self.manager.get_notification(id).is_none() {
+                            self.forget_card(id);
+                            self.validate_selection();
+                        }
+                        shown
+                    }
+                    None => self
+                        .manager
+                        .get_notification(id)
+                        .is_some_and(|notification| notification.timestamp == timestamp),
+                };
+
+                if let Some(notification) = self.manager.get_notification(id).filter(|_| shown) {
+                    return self.resolve_icon(notification.clone());
+                }
             }
 
             Message::DismissNotification(id) => {
@@ -377,6 +709,56 @@ impl Application for NotificationApplet {
                 }
             }
 
+            Message::NotificationWithdrawn { id, reason } => {
+                if self.config.daemon_mode {
+                    // CloseNotification on our own server, tell the sending app
+                    if self.manager.close_notification(id, reason) {
+                        tracing::debug!("Notification {} closed by its sender", id);
+                    }
+                    self.report_closed_notifications();
+                } else if self.manager.withdraw_notification(id, reason) {
+                    // Whoever closed it already emitted NotificationClosed, just drop the card
+                    tracing::debug!("Notification {} closed externally ({:?})", id, reason);
+                }
+
+                self.notification_animations.remove(&id);
+                self.notification_icons.remove(&id);
+                self.progress_indicators.remove(&id);
+                self.reply_drafts.remove(&id);
+                self.validate_selection();
+            }
+
+            Message::ExternalActionInvoked { id, action_key } => {
+                // The daemon that showed the action closes the notification if needed
+                tracing::debug!("Action '{}' invoked externally on {}", action_key, id);
+            }
+
+            Message::ExternalActivationToken { id, token } => {
+                tracing::trace!("Activation token for notification {}: {}", id, token);
+            }
+
+            Message::ListenerStatusChanged(status) => {
+                if status != self.listener_status {
+                    tracing::info!("Notification listener is now {:?}", status);
+                    self.listener_status = status;
+                }
+            }
+
+            Message::ToggleDiagnostics => {
+                self.show_diagnostics = !self.show_diagnostics;
+            }
+
+            Message::ClearAllNotifications => {
+                tracing::debug!(
+                    "Clearing {} active notifications",
+                    self.manager.active_count()
+                );
+
+                self.manager.clear_all();
+                self.report_closed_notifications();
+                self.clear_selection();
+            }
+
             Message::UpdateConfig(new_config) => {
                 // Validate and save config
                 let mut config = new_config;
@@ -389,6 +771,7 @@ impl Application for NotificationApplet {
                 self.manager.set_do_not_disturb(config.do_not_disturb);
                 self.manager.set_min_urgency_level(config.min_urgency_level);
                 self.manager.load_app_filters(config.app_filters.clone());
+                self.sound.set_enabled(config.play_sound);
 
                 // Save config
                 if let Err(e) = self.config_helper.save(&config) {
@@ -398,9 +781,17 @@ impl Application for NotificationApplet {
                 }
 
                 self.config = config;
+                self.apply_sound_theme();
             }
 
             Message::OpenUrl(url) => {
+                // Check again right before opening: the file may have changed
+                // since the notification arrived
+                let Some(url) = ui::url_parser::validate_attachment_url(&url) else {
+                    tracing::warn!("Refusing to open {}", url);
+                    return Task::none();
+                };
+
                 // Open URL using system handler (xdg-open)
                 if let Err(e) = ui::url_parser::open_url(&url) {
                     tracing::error!("Failed to open URL {}: {}", url, e);
@@ -413,25 +804,115 @@ impl Application for NotificationApplet {
                 notification_id,
                 action_key,
             } => {
-                // Send ActionInvoked signal to D-Bus
-                let action_key_clone = action_key.clone();
+                // Look up the sender first, non-resident notifications close
+                // once an action is invoked
+                let Some((sender, portal)) = self
+                    .manager
+                    .get_notification(notification_id)
+                    .filter(|notification| notification.has_action(&action_key))
+                    .map(|notification| (notification.sender.clone(), notification.portal.clone()))
+                else {
+                    tracing::warn!(
+                        "Ignoring action '{}' for notification {}: no such action",
+                        action_key,
+                        notification_id
+                    );
+                    return Task::none();
+                };
+                let outcome = self.manager.invoke_action(notification_id);
+                let closed = self.take_closed_notifications();
+                self.validate_selection();
+
+                // Already gone, so there is nothing to tell the sending app
+                if outcome == manager::ActionOutcome::NotFound {
+                    return Task::none();
+                }
+
+                tracing::info!(
+                    "Action '{}' invoked for notification {} ({:?})",
+                    action_key,
+                    notification_id,
+                    outcome
+                );
+
+                // Signals are sent once the token for the popup surface arrives
+                let invoked = activation::InvokedAction {
+                    notification_id,
+                    action_key,
+                    sender,
+                    portal,
+                    closed,
+                };
+                return activation::request_and_answer(
+                    self.activation.as_ref(),
+                    self.popup_id,
+                    invoked,
+                    BusSignals {
+                        daemon_mode: self.config.daemon_mode,
+                    },
+                )
+                .discard();
+            }
+
+            Message::ReplyInput {
+                notification_id,
+                text,
+            } => {
+                self.reply_drafts.insert(notification_id, text);
+            }
+
+            Message::SendReply(notification_id) => {
+                let Some(text) = self
+                    .reply_drafts
+                    .remove(&notification_id)
+                    .filter(|text| !text.trim().is_empty())
+                else {
+                    return Task::none();
+                };
+
+                // Only the app that asked for a reply may receive it
+                let Some(notification) = self.manager.get_notification(notification_id) else {
+                    return Task::none();
+                };
+                let Some(sender) = notification.reply_destination().map(str::to_string) else {
+                    tracing::warn!(
+                        "Not sending reply for notification {}: it can't receive one",
+                        notification_id
+                    );
+                    return Task::none();
+                };
+
+                // A reply answers the notification like an action does
+                let outcome = self.manager.invoke_action(notification_id);
+                let closed = self.take_closed_notifications();
+                self.validate_selection();
+
+                if outcome == manager::ActionOutcome::NotFound {
+                    return Task::none();
+                }
+
+                tracing::info!(
+                    "Reply sent for notification {} ({:?})",
+                    notification_id,
+                    outcome
+                );
+
+                // Send NotificationReplied before NotificationClosed
+                let daemon_mode = self.config.daemon_mode;
                 tokio::spawn(async move {
                     if let Err(e) =
-                        dbus::send_action_invoked(notification_id, &action_key_clone).await
+                        dbus::send_notification_replied(notification_id, &text, Some(&sender)).await
                     {
                         tracing::error!(
-                            "Failed to send ActionInvoked for notification {}: {}",
+                            "Failed to send NotificationReplied for notification {}: {}",
                             notification_id,
                             e
                         );
                     }
+                    for closure in closed {
+                        report_closure(closure, daemon_mode).await;
+                    }
                 });
-
-                tracing::info!(
-                    "Action '{}' invoked for notification {}",
-                    action_key,
-                    notification_id
-                );
             }
 
             Message::ToggleDND => {
@@ -454,6 +935,21 @@ impl Application for NotificationApplet {
                 }
             }
 
+            Message::ClearHistory => {
+                tracing::debug!("Clearing {} history entries", self.manager.history().len());
+
+                self.manager.clear_history();
+                if self.config.history_enabled {
+                    if let Err(e) = self.manager.save_history() {
+                        tracing::error!("Failed to save notification history: {}", e);
+                    }
+                }
+            }
+
+            Message::Control(request) => {
+                return self.handle_control_request(request);
+            }
+
             Message::SetUrgencyLevel(level) => {
                 // Set minimum urgency level
                 self.config.min_urgency_level = level.min(2); // Clamp to 0-2
@@ -626,12 +1122,24 @@ impl Application for NotificationApplet {
             }
 
             Message::ActivateSelected => {
-                // Activate the selected notification (open URL or invoke first action)
+                // Activate the selected notification (default action, URL or first action)
                 if let Some(idx) = self.selected_notification_index {
                     let active_notifications = self.manager.get_active_notifications();
                     if let Some(notification) = active_notifications.get(idx) {
-                        // First try to open a URL if present in body
-                        if let Some(url) = ui::url_parser::extract_first_url(&notification.body) {
+                        // Same as clicking the notification
+                        if let Some(action) = notification.default_action() {
+                            tracing::info!(
+                                "Activating selected notification {}: invoking default action",
+                                notification.id
+                            );
+                            return self.update(Message::InvokeAction {
+                                notification_id: notification.id,
+                                action_key: action.key.clone(),
+                            });
+                        }
+
+                        // Then try to open a URL if present in body
+                        if let Some(url) = ui::markup::first_link(&notification.body) {
                             tracing::info!(
                                 "Activating selected notification {}: opening URL {}",
                                 notification.id,
@@ -641,8 +1149,8 @@ impl Application for NotificationApplet {
                         }
 
                         // If no URL, try to invoke first action
-                        if !notification.actions.is_empty() {
-                            let action_key = notification.actions[0].key.clone();
+                        if let Some(action) = notification.button_actions().first() {
+                            let action_key = action.key.clone();
                             tracing::info!(
                                 "Activating selected notification {}: invoking action {}",
                                 notification.id,
@@ -689,7 +1197,7 @@ impl Application for NotificationApplet {
                 if let Some(notif_idx) = self.selected_notification_index {
                     let active_notifications = self.manager.get_active_notifications();
                     if let Some(notification) = active_notifications.get(notif_idx) {
-                        let action_count = notification.actions.len();
+                        let action_count = notification.button_actions().len();
 
                         if action_count == 0 {
                             // No actions to cycle
@@ -721,8 +1229,8 @@ impl Application for NotificationApplet {
                         // Convert 1-based action number to 0-based index
                         let action_idx = (action_number - 1) as usize;
 
-                        if action_idx < notification.actions.len() {
-                            let action_key = notification.actions[action_idx].key.clone();
+                        if let Some(action) = notification.button_actions().get(action_idx) {
+                            let action_key = action.key.clone();
                             tracing::info!(
                                 "Quick action {}: invoking action {} for notification {}",
                                 action_number,
@@ -745,9 +1253,14 @@ impl Application for NotificationApplet {
                 }
             }
 
-            Message::KeyboardEvent(event) => {
+            Message::KeyboardEvent(event, status) => {
                 use cosmic::iced::keyboard::{Event as KeyEvent, Key};
 
+                // Keys typed into a reply field are text, not shortcuts
+                if status == cosmic::iced::event::Status::Captured {
+                    return Task::none();
+                }
+
                 if let KeyEvent::KeyPressed { key, modifiers, .. } = event {
                     match key {
                         // Escape key closes popup
@@ -829,9 +1342,11 @@ impl Application for NotificationApplet {
                 let expired_ids = self.manager.get_expired_notifications();
 
                 for id in expired_ids {
-                    self.manager.remove_notification(id);
+                    self.manager
+                        .close_notification(id, dbus::CloseReason::Expired);
                     tracing::debug!("Removed expired notification {}", id);
                 }
+                self.report_closed_notifications();
 
                 // Validate selection after removing notifications
                 self.validate_selection();
@@ -931,9 +1446,10 @@ impl Application for NotificationApplet {
             }
 
             Message::CompleteNotificationDismissal(notification_id) => {
-                // Remove the notification from manager
+                // Remove the notification from manager (reported as dismissed)
                 if self.manager.remove_notification(notification_id) {
                     tracing::debug!("Completed dismissal of notification {}", notification_id);
+                    self.report_closed_notifications();
                 } else {
                     tracing::warn!(
                         "Failed to complete dismissal of notification {} (not found)",
@@ -958,10 +1474,120 @@ impl Application for NotificationApplet {
                     tracing::debug!("Accessibility: prefers-reduced-motion disabled - animations will respect config");
                 }
             }
+
+            Message::DesktopSoundThemeDetected(theme) => {
+                self.desktop_sound_theme = theme;
+                self.apply_sound_theme();
+            }
         }
 
         Task::none()
     }
+}
+
+impl Application for NotificationApplet {
+    type Executor = cosmic::executor::Default;
+    type Flags = ();
+    type Message = Message;
+
+    const APP_ID: &'static str = "com.system76.CosmicAppletNotifications";
+
+    fn core(&self) -> &cosmic::app::Core {
+        &self.core
+    }
+
+    fn core_mut(&mut self) -> &mut cosmic::app::Core {
+        &mut self.core
+    }
+
+    fn init(
+        core: cosmic::app::Core,
+        _flags: Self::Flags,
+    ) -> (Self, Task<cosmic::Action<Self::Message>>) {
+        // Load configuration
+        let config_helper = config::ConfigHelper::new();
+        let config = config_helper.load();
+
+        tracing::info!("Configuration loaded from {:?}", config_helper.path());
+        tracing::debug!("Config: {:?}", config);
+
+        // Initialize manager with history from disk and config settings
+        let mut manager = if config.history_enabled {
+            manager::NotificationManager::with_history(
+                config.max_history_items,
+                config.history_retention_days,
+            )
+        } else {
+            manager::NotificationManager::new()
+        };
+        manager.set_do_not_disturb(config.do_not_disturb);
+        manager.set_min_urgency_level(config.min_urgency_level);
+        manager.load_app_filters(config.app_filters.clone());
+
+        let icon_resolver =
+            std::sync::Arc::new(std::sync::Mutex::new(ui::icons::IconResolver::new(
+                cosmic::icon_theme::default(),
+                ui::theme::ComponentSize::NOTIFICATION_ICON,
+                core.scale_factor().ceil() as u16,
+            )));
+
+        let desktop_entries = std::sync::Arc::new(std::sync::Mutex::new(
+            desktop_entry::DesktopEntryResolver::new(),
+        ));
+
+        let mut sound = sound::SoundPlayer::default();
+        sound.set_enabled(config.play_sound);
+        if let Some(theme) = config.sound_theme.as_deref() {
+            sound.set_theme(theme);
+        }
+
+        let control_snapshot = dbus::control::ControlSnapshot::of(&manager);
+
+        let app = NotificationApplet {
+            core,
+            manager,
+            config_helper,
+            config,
+            popup_id: None,
+            selected_notification_index: None,
+            selected_action_index: None,
+            notification_animations: std::collections::HashMap::new(),
+            notification_icons: std::collections::HashMap::new(),
+            icon_resolver,
+            desktop_entries,
+            popup_animation: None,
+            progress_indicators: std::collections::HashMap::new(),
+            prefers_reduced_motion: false, // Will be detected asynchronously
+            sound,
+            desktop_sound_theme: None,
+            activation: Box::new(activation::XdgActivation::new(Self::APP_ID)),
+            reply_drafts: std::collections::HashMap::new(),
+            control_snapshot,
+            listener_status: dbus::ListenerStatus::Connected,
+            show_diagnostics: false,
+        };
+
+        // Detect prefers-reduced-motion accessibility setting on startup
+        let detect_task = Task::future(async {
+            let prefers_reduced =
+                cosmic_applet_notifications::accessibility::detect_prefers_reduced_motion().await;
+            cosmic::Action::App(Message::UpdatePrefersReducedMotion(prefers_reduced))
+        });
+
+        // Read the desktop's sound theme, in case the config doesn't set one
+        let sound_theme_task = Task::future(async {
+            let theme = sound::theme::desktop_sound_theme().await;
+            cosmic::Action::App(Message::DesktopSoundThemeDetected(theme))
+        });
+
+        (app, Task::batch([detect_task, sound_theme_task]))
+    }
+
+    fn update(&mut self, message: Self::Message) -> Task<cosmic::Action<Self::Message>> {
+        let task = self.handle_message(message);
+        self.publish_control_state();
+        task
+    }
 
     fn view(&self) -> Element<'_, Self::Message> {
         // Panel icon with notification count badge
@@ -984,14 +1610,22 @@ impl Application for NotificationApplet {
             let notification_list = ui::widgets::notification_list(
                 notifications,
                 &self.notification_animations,
+                &self.notification_icons,
                 self.selected_notification_index,
                 self.selected_action_index,
                 Message::DismissNotification,
+                Message::ClearAllNotifications,
                 Message::OpenUrl,
                 |notification_id, action_key| Message::InvokeAction {
                     notification_id,
                     action_key,
                 },
+                &self.reply_drafts,
+                |notification_id, text| Message::ReplyInput {
+                    notification_id,
+                    text,
+                },
+                Message::SendReply,
             );
 
             // Create filter settings view
@@ -1014,13 +1648,31 @@ impl Application for NotificationApplet {
                 Message::PreviewPosition,
             );
 
+            // Listener health, for debugging missing notifications
+            let diagnostics = ui::widgets::diagnostics(
+                dbus::listener_metrics(),
+                (!self.config.daemon_mode).then_some(self.listener_status),
+                self.show_diagnostics,
+                Message::ToggleDiagnostics,
+            );
+
+            // Reconnection happens in the background; say why nothing arrives meanwhile
+            let mut content = column();
+            if !self.config.daemon_mode
+                && self.listener_status == dbus::ListenerStatus::Disconnected
+            {
+                content = content.push(ui::widgets::disconnected_banner());
+            }
+
             // Combine notification list and settings
-            let content = column()
+            let content = content
                 .push(notification_list)
                 .push(divider::horizontal::default())
                 .push(filter_settings)
                 .push(divider::horizontal::default())
                 .push(position_settings)
+                .push(divider::horizontal::default())
+                .push(diagnostics)
                 .spacing(0.0);
 
             self.core.applet.popup_container(content).into()
@@ -1038,21 +1690,36 @@ impl Application for NotificationApplet {
         use cosmic::iced::time;
         use std::time::Duration;
 
+        // Daemon mode owns org.freedesktop.Notifications, otherwise listen passively
+        let notifications = if self.config.daemon_mode {
+            dbus::server::subscribe(self.manager.id_allocator())
+        } else {
+            dbus::subscribe()
+        };
+
         let mut subscriptions = vec![
-            // D-Bus notification listener
-            dbus::subscribe(),
+            // D-Bus notifications
+            notifications,
             // Periodic tick every 60 seconds to check for expired notifications
             time::every(Duration::from_secs(60)).map(|_| Message::Tick),
             // Keyboard events for shortcuts
-            cosmic::iced::event::listen_with(|event, _status, _window| {
+            cosmic::iced::event::listen_with(|event, status, _window| {
                 if let cosmic::iced::Event::Keyboard(keyboard_event) = event {
-                    Some(Message::KeyboardEvent(keyboard_event))
+                    Some(Message::KeyboardEvent(keyboard_event, status))
                 } else {
                     None
                 }
             }),
         ];
 
+        // Control interface for scripts
+        subscriptions.push(dbus::control::subscribe());
+
+        // GApplication notifications, when configured to take them
+        if self.config.gtk_notifications {
+            subscriptions.push(dbus::gtk::subscribe());
+        }
+
         // Add animation frame subscription if animations are enabled and there are active animations
         // (respect accessibility preferences)
         if self.config.animations.enabled
@@ -1070,6 +1737,75 @@ impl Application for NotificationApplet {
     }
 }
 
+/// Answers invoked actions on the session bus, logging failures
+struct BusSignals {
+    /// Whether closures are reported as the daemon (see `report_closure()`)
+    daemon_mode: bool,
+}
+
+impl activation::ActionSignals for BusSignals {
+    async fn activation_token(&self, id: u32, token: &str, destination: Option<&str>) {
+        if let Err(e) = dbus::send_activation_token(id, token, destination).await {
+            tracing::error!(
+                "Failed to send ActivationToken for notification {}: {}",
+                id,
+                e
+            );
+        }
+    }
+
+    async fn action_invoked(&self, id: u32, action_key: &str, destination: Option<&str>) {
+        if let Err(e) = dbus::send_action_invoked(id, action_key, destination).await {
+            tracing::error!(
+                "Failed to send ActionInvoked for notification {}: {}",
+                id,
+                e
+            );
+        }
+    }
+
+    async fn activate(&self, origin: &dbus::PortalOrigin, action_key: &str, token: Option<&str>) {
+        if let Err(e) = dbus::activate_action(origin, action_key, token).await {
+            tracing::error!("Failed to activate {}: {}", origin.app_id, e);
+        }
+    }
+
+    async fn notification_closed(&self, closed: &manager::ClosedNotification) {
+        report_closure(closed.clone(), self.daemon_mode).await;
+    }
+}
+
+/// Report a recorded closure to the sending app, logging failures
+///
+/// Daemon mode sends NotificationClosed, listener mode asks the owning
+/// daemon to close dismissed notifications (see `ClosedNotification::report()`).
+async fn report_closure(closed: manager::ClosedNotification, daemon_mode: bool) {
+    match closed.report(daemon_mode) {
+        manager::ClosureReport::Signal => {
+            if let Err(e) =
+                dbus::send_notification_closed(closed.id, closed.reason, closed.sender.as_deref())
+                    .await
+            {
+                tracing::error!(
+                    "Failed to send NotificationClosed for notification {}: {}",
+                    closed.id,
+                    e
+                );
+            }
+        }
+        manager::ClosureReport::CloseOnDaemon => {
+            if let Err(e) = dbus::close_on_daemon(closed.id).await {
+                tracing::warn!(
+                    "Failed to close notification {} on the daemon: {}",
+                    closed.id,
+                    e
+                );
+            }
+        }
+        manager::ClosureReport::None => {}
+    }
+}
+
 fn main() -> cosmic::iced::Result {
     // Initialize logging
     tracing_subscriber::fmt()
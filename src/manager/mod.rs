@@ -6,11 +6,13 @@
 
 pub mod storage;
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 
 use chrono::{DateTime, Duration, Local};
 
-use crate::dbus::{Notification, Urgency};
+use crate::dbus::{AppIdentity, CloseReason, Notification, Urgency};
 
 /// Maximum number of notifications to keep in history.
 ///
@@ -44,8 +46,8 @@ pub struct NotificationManager {
     /// Historical notifications (circular buffer)
     notification_history: VecDeque<Notification>,
 
-    /// Next notification ID counter
-    next_id: u32,
+    /// Notification ID counter (shared with the D-Bus server in daemon mode)
+    ids: IdAllocator,
 
     /// Do Not Disturb mode
     do_not_disturb: bool,
@@ -55,6 +57,9 @@ pub struct NotificationManager {
 
     /// Minimum urgency level to display (0=Low, 1=Normal, 2=Critical)
     min_urgency_level: u8,
+
+    /// Closures not yet reported to the sending apps
+    closed_notifications: Vec<ClosedNotification>,
 }
 
 impl Default for NotificationManager {
@@ -69,10 +74,11 @@ impl NotificationManager {
         Self {
             active_notifications: VecDeque::new(),
             notification_history: VecDeque::with_capacity(MAX_HISTORY_SIZE),
-            next_id: 1,
+            ids: IdAllocator::new(),
             do_not_disturb: false,
             app_filters: HashMap::new(),
             min_urgency_level: 0, // Show all notifications by default
+            closed_notifications: Vec::new(),
         }
     }
 
@@ -98,10 +104,11 @@ impl NotificationManager {
         Self {
             active_notifications: VecDeque::new(),
             notification_history: history,
-            next_id: 1,
+            ids: IdAllocator::new(),
             do_not_disturb: false,
             app_filters: HashMap::new(),
             min_urgency_level: 0, // Show all notifications by default
+            closed_notifications: Vec::new(),
         }
     }
 
@@ -109,11 +116,17 @@ impl NotificationManager {
     ///
     /// Handles:
     /// - ID assignment
-    /// - Notification replacement (replaces_id)
+    /// - Notification replacement (replaces_id, or a synchronous tag)
     /// - Filtering (DND, app filters, urgency)
     /// - History management
     /// - Active notification limits
     ///
+    /// A replacement for a visible notification takes its place in the active
+    /// list (`NotificationAction::Updated`), so progress updates don't reorder
+    /// cards. Replaced notifications that keep their ID are not reported as
+    /// closed; evicted ones, and replaced IDs, are reported with
+    /// `CloseReason::Undefined`.
+    ///
     /// # History Behavior
     ///
     /// Notifications are added to history ONLY when:
@@ -121,6 +134,8 @@ impl NotificationManager {
     /// 2. Dismissed by user → added on removal
     /// 3. Evicted due to MAX_ACTIVE_NOTIFICATIONS limit → added on eviction
     ///
+    /// Versions superseded by an in-place update are not added to history.
+    ///
     /// **Rationale**: Active notifications should remain in the active list until
     /// explicitly dismissed or evicted. This prevents duplicate entries in history
     /// and ensures history only contains notifications that are no longer visible.
@@ -128,29 +143,74 @@ impl NotificationManager {
     pub fn add_notification(&mut self, mut notification: Notification) -> NotificationAction {
         // Assign unique ID if not already assigned
         if notification.id == 0 {
-            notification.id = self.next_id;
-            self.next_id = self.next_id.wrapping_add(1);
+            notification.id = self.ids.next_id();
+        }
+
+        // Synchronous notifications (e.g. volume popups) replace the previous
+        // one with the same tag instead of stacking up
+        if notification.replaces_id == 0 {
+            notification.replaces_id = self.synchronous_predecessor(&notification).unwrap_or(0);
+        }
+
+        // Update a visible notification in place, keeping its position
+        if notification.replaces_id != 0 && self.should_display(&notification) {
+            if let Some(pos) = self
+                .active_notifications
+                .iter()
+                .position(|n| n.id == notification.replaces_id)
+            {
+                if notification.replaces_id != notification.id {
+                    // The sender can no longer refer to the old ID
+                    let closed = ClosedNotification::new(
+                        &self.active_notifications[pos],
+                        CloseReason::Undefined,
+                    );
+                    self.closed_notifications.push(closed);
+                    self.ids.release(notification.replaces_id);
+                }
+                // Keep the app identity until the update's own is resolved
+                let previous = &self.active_notifications[pos];
+                if notification.app_identity.is_none()
+                    && notification.desktop_entry() == previous.desktop_entry()
+                {
+                    notification.app_identity = previous.app_identity.clone();
+                }
+                self.ids.reserve(notification.id);
+                self.active_notifications[pos] = notification;
+                return NotificationAction::Updated;
+            }
         }
 
         // Check if this replaces an existing notification
         if notification.replaces_id != 0 {
-            self.remove_notification(notification.replaces_id);
+            if notification.replaces_id == notification.id {
+                // Same notification updated in place, it never closed
+                self.retire_notification(notification.replaces_id, CloseReason::Undefined);
+            } else {
+                self.close_notification(notification.replaces_id, CloseReason::Undefined);
+            }
         }
 
         // Apply filters
         if !self.should_display(&notification) {
             // Add to history only, don't show
+            self.ids.release(notification.id);
             self.add_to_history(notification);
             return NotificationAction::AddedToHistoryOnly;
         }
 
         // Add to active notifications (will be added to history when dismissed)
+        self.ids.reserve(notification.id);
         self.active_notifications.push_back(notification);
 
         // Enforce maximum active notifications (FIFO)
         // Evicted notifications go to history if not transient
         while self.active_notifications.len() > MAX_ACTIVE_NOTIFICATIONS {
-            if let Some(evicted) = self.active_notifications.pop_front() {
+            if let Some(mut evicted) = self.active_notifications.pop_front() {
+                self.ids.release(evicted.id);
+                self.closed_notifications
+                    .push(ClosedNotification::new(&evicted, CloseReason::Undefined));
+                evicted.close_reason = Some(CloseReason::Undefined);
                 if !evicted.is_transient() {
                     self.add_to_history(evicted);
                 }
@@ -160,35 +220,138 @@ impl NotificationManager {
         NotificationAction::Displayed
     }
 
+    /// Attach the app identity resolved from a notification's desktop entry
+    ///
+    /// Desktop entries are resolved off the UI thread once the notification
+    /// was added. The notification is matched by ID and timestamp, so the
+    /// result for a notification that was replaced since is dropped. An
+    /// active notification whose resolved name is filtered out moves to
+    /// history, as it would have on arrival.
+    ///
+    /// Returns true if an active notification took the identity and is
+    /// still shown.
+    pub fn set_app_identity(
+        &mut self,
+        id: u32,
+        timestamp: DateTime<Local>,
+        identity: AppIdentity,
+    ) -> bool {
+        let is_match = |n: &Notification| n.id == id && n.timestamp == timestamp;
+
+        let Some(pos) = self.active_notifications.iter().position(is_match) else {
+            // Filtered on arrival: name it in history
+            if let Some(notification) = self
+                .notification_history
+                .iter_mut()
+                .rev()
+                .find(|n| is_match(n))
+            {
+                notification.app_identity = Some(identity);
+            }
+            return false;
+        };
+
+        self.active_notifications[pos].app_identity = Some(identity);
+        if self.app_filter(&self.active_notifications[pos]) != Some(false) {
+            return true;
+        }
+
+        if let Some(hidden) = self.active_notifications.remove(pos) {
+            self.ids.release(id);
+            self.add_to_history(hidden);
+        }
+        false
+    }
+
+    /// Find the active notification a synchronous notification replaces
+    ///
+    /// Matches the `x-canonical-private-synchronous` tag within the same app.
+    fn synchronous_predecessor(&self, notification: &Notification) -> Option<u32> {
+        let tag = notification.synchronous_tag()?;
+        self.active_notifications
+            .iter()
+            .rev()
+            .find(|n| n.app_name == notification.app_name && n.synchronous_tag() == Some(tag))
+            .map(|n| n.id)
+    }
+
     /// Remove a notification by ID
     ///
     /// Removes from active notifications and adds to history if not already there.
+    /// The removal is reported as dismissed by the user.
     pub fn remove_notification(&mut self, id: u32) -> bool {
-        if let Some(pos) = self.active_notifications.iter().position(|n| n.id == id) {
-            if let Some(notification) = self.active_notifications.remove(pos) {
-                // Add to history if transient flag not set
-                if !notification.is_transient() {
-                    self.add_to_history(notification);
-                }
+        self.close_notification(id, CloseReason::Dismissed)
+    }
+
+    /// Close a notification by ID for the given reason
+    ///
+    /// Moves the notification to history (unless transient), tagged with the
+    /// reason, and records the closure for `take_closed_notifications()`.
+    /// Returns false if no active notification has this ID.
+    pub fn close_notification(&mut self, id: u32, reason: CloseReason) -> bool {
+        let Some(closed) = self
+            .get_notification(id)
+            .map(|n| ClosedNotification::new(n, reason))
+        else {
+            return false;
+        };
+        self.retire_notification(id, reason);
+        self.closed_notifications.push(closed);
+        true
+    }
+
+    /// Remove a notification that was closed outside the applet
+    ///
+    /// Like `close_notification()`, but records no closure to report: whoever
+    /// closed it (the sending app or another daemon) already emitted
+    /// NotificationClosed. The reason is still kept on the history entry.
+    pub fn withdraw_notification(&mut self, id: u32, reason: CloseReason) -> bool {
+        self.retire_notification(id, reason)
+    }
+
+    /// Handle an action invoked on a notification
+    ///
+    /// Per the spec, a notification closes once one of its actions is invoked
+    /// unless it is resident. The closure is reported as dismissed.
+    pub fn invoke_action(&mut self, id: u32) -> ActionOutcome {
+        match self.get_notification(id) {
+            None => ActionOutcome::NotFound,
+            Some(notification) if notification.is_resident() => ActionOutcome::Kept,
+            Some(_) => {
+                self.close_notification(id, CloseReason::Dismissed);
+                ActionOutcome::Closed
             }
-            true
-        } else {
-            false
         }
     }
 
     /// Clear all active notifications
+    ///
+    /// Every cleared notification is reported as dismissed by the user.
     pub fn clear_all(&mut self) {
         // Move all active to history (unless transient)
         // Collect first to avoid double mutable borrow
         let notifications: Vec<_> = self.active_notifications.drain(..).collect();
-        for notification in notifications {
+        for mut notification in notifications {
+            self.ids.release(notification.id);
+            self.closed_notifications.push(ClosedNotification::new(
+                &notification,
+                CloseReason::Dismissed,
+            ));
+            notification.close_reason = Some(CloseReason::Dismissed);
             if !notification.is_transient() {
                 self.add_to_history(notification);
             }
         }
     }
 
+    /// Take the closures recorded since the last call
+    ///
+    /// The application reports each one to the sending app with a
+    /// NotificationClosed signal.
+    pub fn take_closed_notifications(&mut self) -> Vec<ClosedNotification> {
+        std::mem::take(&mut self.closed_notifications)
+    }
+
     /// Clear notification history
     pub fn clear_history(&mut self) {
         self.notification_history.clear();
@@ -272,6 +435,16 @@ impl NotificationManager {
         age > Duration::seconds(timeout_seconds)
     }
 
+    /// Get a handle to the notification ID counter
+    ///
+    /// The returned allocator shares its counter with this manager, so IDs
+    /// handed out by the D-Bus server never collide with manager-assigned IDs.
+    /// The manager releases an ID once its notification is no longer active,
+    /// so the allocator also tells which IDs are in use.
+    pub fn id_allocator(&self) -> IdAllocator {
+        self.ids.clone()
+    }
+
     /// Set Do Not Disturb mode
     pub fn set_do_not_disturb(&mut self, enabled: bool) {
         self.do_not_disturb = enabled;
@@ -285,6 +458,8 @@ impl NotificationManager {
     /// Set application filter
     ///
     /// If should_show is false, notifications from this app will only go to history.
+    /// The name is matched against the desktop entry ID, the resolved app name
+    /// and the raw `app_name`, in that order.
     pub fn set_app_filter(&mut self, app_name: String, should_show: bool) {
         self.app_filters.insert(app_name, should_show);
     }
@@ -343,7 +518,7 @@ impl NotificationManager {
         // Critical notifications bypass DND mode
         if urgency == Urgency::Critical {
             // Still check app-specific filter for critical
-            if let Some(&should_show) = self.app_filters.get(&notification.app_name) {
+            if let Some(should_show) = self.app_filter(notification) {
                 return should_show;
             }
             return true;
@@ -355,7 +530,7 @@ impl NotificationManager {
         }
 
         // Check app-specific filter
-        if let Some(&should_show) = self.app_filters.get(&notification.app_name) {
+        if let Some(should_show) = self.app_filter(notification) {
             return should_show;
         }
 
@@ -363,6 +538,36 @@ impl NotificationManager {
         true
     }
 
+    /// Find the app filter matching a notification, if any
+    fn app_filter(&self, notification: &Notification) -> Option<bool> {
+        let identity = notification.app_identity.as_ref();
+        [
+            identity.map(|identity| identity.desktop_entry.as_str()),
+            identity.map(|identity| identity.name.as_str()),
+            Some(notification.app_name.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|key| self.app_filters.get(key).copied())
+    }
+
+    /// Move an active notification to history without recording a closure
+    fn retire_notification(&mut self, id: u32, reason: CloseReason) -> bool {
+        if let Some(pos) = self.active_notifications.iter().position(|n| n.id == id) {
+            if let Some(mut notification) = self.active_notifications.remove(pos) {
+                self.ids.release(id);
+                notification.close_reason = Some(reason);
+                // Add to history if transient flag not set
+                if !notification.is_transient() {
+                    self.add_to_history(notification);
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+
     /// Add notification to history
     ///
     /// Maintains circular buffer with MAX_HISTORY_SIZE limit.
@@ -415,14 +620,15 @@ impl NotificationManager {
 
     /// Get notifications grouped by application
     ///
-    /// Returns a map of app_name -> list of notifications.
+    /// Returns a map of app name -> list of notifications, using the name
+    /// resolved from the desktop entry when there is one.
     /// Useful for displaying grouped notifications in UI.
     pub fn get_notifications_by_app(&self) -> HashMap<String, Vec<&Notification>> {
         let mut grouped: HashMap<String, Vec<&Notification>> = HashMap::new();
 
         for notification in &self.active_notifications {
             grouped
-                .entry(notification.app_name.clone())
+                .entry(notification.display_name().to_string())
                 .or_default()
                 .push(notification);
         }
@@ -439,11 +645,156 @@ impl NotificationManager {
     }
 }
 
+/// A notification that left the active list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClosedNotification {
+    /// ID of the closed notification
+    pub id: u32,
+
+    /// Why it was closed (reported in NotificationClosed)
+    pub reason: CloseReason,
+
+    /// Unique bus name of the app that sent it, if known
+    pub sender: Option<String>,
+
+    /// Whether it came through the desktop portal or org.gtk.Notifications
+    ///
+    /// Such notifications have no freedesktop ID the app knows, so no
+    /// NotificationClosed is reported for them.
+    pub portal: bool,
+}
+
+impl ClosedNotification {
+    /// Record the closure of `notification`
+    fn new(notification: &Notification, reason: CloseReason) -> Self {
+        Self {
+            id: notification.id,
+            reason,
+            sender: notification.sender.clone(),
+            portal: notification.portal.is_some(),
+        }
+    }
+
+    /// Decide how the closure reaches the sending app
+    ///
+    /// In daemon mode the applet owns the notification and emits
+    /// NotificationClosed itself. In listener mode another daemon owns it:
+    /// the daemon is asked to close notifications the user dismissed and
+    /// reports the closure itself, while expiry, replacement and eviction
+    /// only concern the applet's own list.
+    pub fn report(&self, daemon_mode: bool) -> ClosureReport {
+        if self.portal {
+            ClosureReport::None
+        } else if daemon_mode {
+            ClosureReport::Signal
+        } else if self.reason == CloseReason::Dismissed {
+            ClosureReport::CloseOnDaemon
+        } else {
+            ClosureReport::None
+        }
+    }
+}
+
+/// How a closure is reported, see `ClosedNotification::report()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClosureReport {
+    /// Emit NotificationClosed to the sender
+    Signal,
+    /// Call CloseNotification on the daemon that owns the notification
+    CloseOnDaemon,
+    /// Nothing to report
+    None,
+}
+
+/// Outcome of invoking an action on a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionOutcome {
+    /// The notification was closed
+    Closed,
+    /// The notification is resident and stays open
+    Kept,
+    /// No active notification has this ID
+    NotFound,
+}
+
+/// Shared notification ID counter
+///
+/// Cloning an allocator shares the underlying state. IDs start at 1 and
+/// skip 0 on wrap-around, since 0 means "no ID" in the notification spec.
+/// An allocated ID stays in use until it is released, and IDs in use are
+/// skipped after a wrap-around, so two live notifications never share one.
+#[derive(Debug, Clone)]
+pub struct IdAllocator(Arc<IdState>);
+
+#[derive(Debug)]
+struct IdState {
+    /// Next ID to try
+    next: AtomicU32,
+
+    /// IDs allocated or reserved and not yet released
+    in_use: Mutex<HashSet<u32>>,
+}
+
+impl Default for IdAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdAllocator {
+    /// Create a new allocator starting at ID 1
+    pub fn new() -> Self {
+        Self::starting_at(1)
+    }
+
+    fn starting_at(next: u32) -> Self {
+        Self(Arc::new(IdState {
+            next: AtomicU32::new(next),
+            in_use: Mutex::new(HashSet::new()),
+        }))
+    }
+
+    /// Allocate the next free notification ID (never 0)
+    pub fn next_id(&self) -> u32 {
+        let mut in_use = self.in_use();
+        loop {
+            let id = self.0.next.fetch_add(1, Ordering::Relaxed);
+            if id != 0 && in_use.insert(id) {
+                return id;
+            }
+        }
+    }
+
+    /// Check if an ID was allocated or reserved and not released since
+    pub fn is_in_use(&self, id: u32) -> bool {
+        self.in_use().contains(&id)
+    }
+
+    /// Mark an ID assigned elsewhere (e.g. by another daemon) as in use
+    pub fn reserve(&self, id: u32) {
+        self.in_use().insert(id);
+    }
+
+    /// Return an ID whose notification is gone
+    pub fn release(&self, id: u32) {
+        self.in_use().remove(&id);
+    }
+
+    fn in_use(&self) -> std::sync::MutexGuard<'_, HashSet<u32>> {
+        self.0
+            .in_use
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
 /// Action taken when adding a notification
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NotificationAction {
     /// Notification was displayed to the user
     Displayed,
+    /// An active notification was replaced in place
+    Updated,
     /// Notification was added to history only (filtered)
     AddedToHistoryOnly,
 }
@@ -451,25 +802,22 @@ pub enum NotificationAction {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::dbus::{NotificationHints, Urgency};
+    use crate::dbus::Urgency;
 
     fn create_test_notification(app_name: &str, summary: &str) -> Notification {
-        Notification {
-            id: 0,
-            app_name: app_name.to_string(),
-            replaces_id: 0,
-            app_icon: String::new(),
-            summary: summary.to_string(),
-            body: String::new(),
-            actions: Vec::new(),
-            hints: NotificationHints {
-                urgency: Urgency::Normal,
-                ..Default::default()
-            },
-            raw_hints: HashMap::new(),
-            expire_timeout: 0,
-            timestamp: Local::now(),
-        }
+        Notification::from_notify_args(
+            0,
+            (
+                app_name.to_string(),
+                0,
+                String::new(),
+                summary.to_string(),
+                String::new(),
+                Vec::new(),
+                HashMap::new(),
+                0,
+            ),
+        )
     }
 
     #[test]
@@ -879,6 +1227,402 @@ mod tests {
         assert_eq!(action, NotificationAction::AddedToHistoryOnly);
     }
 
+    #[test]
+    fn test_closed_notifications_record_reason() {
+        let mut manager = NotificationManager::new();
+
+        for summary in ["Dismissed", "Expired", "Cleared"] {
+            manager.add_notification(create_test_notification("test", summary));
+        }
+
+        assert!(manager.remove_notification(1));
+        assert!(manager.close_notification(2, CloseReason::Expired));
+        assert!(!manager.close_notification(2, CloseReason::Expired));
+        manager.clear_all();
+        assert!(!manager.withdraw_notification(3, CloseReason::Closed));
+
+        assert_eq!(
+            manager.take_closed_notifications(),
+            vec![
+                ClosedNotification {
+                    id: 1,
+                    reason: CloseReason::Dismissed,
+                    sender: None,
+                    portal: false,
+                },
+                ClosedNotification {
+                    id: 2,
+                    reason: CloseReason::Expired,
+                    sender: None,
+                    portal: false,
+                },
+                ClosedNotification {
+                    id: 3,
+                    reason: CloseReason::Dismissed,
+                    sender: None,
+                    portal: false,
+                },
+            ]
+        );
+        // Draining clears the queue
+        assert!(manager.take_closed_notifications().is_empty());
+
+        // History entries keep the reason
+        let reasons: Vec<_> = manager.history().iter().map(|n| n.close_reason).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                Some(CloseReason::Dismissed),
+                Some(CloseReason::Expired),
+                Some(CloseReason::Dismissed)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_closed_notifications_carry_sender() {
+        let mut manager = NotificationManager::new();
+
+        let mut first = create_test_notification("test", "First");
+        first.sender = Some(":1.42".to_string());
+        manager.add_notification(first);
+
+        let mut replacement = create_test_notification("test", "Replacement");
+        replacement.replaces_id = 1;
+        replacement.sender = Some(":1.43".to_string());
+        manager.add_notification(replacement);
+        manager.clear_all();
+
+        // Each closure goes to the app that sent that notification
+        let senders: Vec<_> = manager
+            .take_closed_notifications()
+            .into_iter()
+            .map(|closed| (closed.id, closed.sender))
+            .collect();
+        assert_eq!(
+            senders,
+            vec![
+                (1, Some(":1.42".to_string())),
+                (2, Some(":1.43".to_string()))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_withdrawn_notifications_not_reported() {
+        let mut manager = NotificationManager::new();
+        manager.add_notification(create_test_notification("test", "Withdrawn"));
+
+        assert!(manager.withdraw_notification(1, CloseReason::Expired));
+        assert_eq!(manager.active_count(), 0);
+        assert_eq!(manager.history().len(), 1);
+        assert_eq!(
+            manager.history()[0].close_reason,
+            Some(CloseReason::Expired)
+        );
+        assert!(manager.take_closed_notifications().is_empty());
+    }
+
+    #[test]
+    fn test_portal_closures_are_marked() {
+        let mut manager = NotificationManager::new();
+
+        let mut sandboxed = create_test_notification("test", "Sandboxed");
+        sandboxed.portal = Some(crate::dbus::PortalOrigin {
+            app_id: "org.example.App".to_string(),
+            portal_id: "download".to_string(),
+            actions: HashMap::new(),
+        });
+        manager.add_notification(sandboxed);
+        manager.add_notification(create_test_notification("test", "Direct"));
+        manager.clear_all();
+
+        let portal: Vec<_> = manager
+            .take_closed_notifications()
+            .into_iter()
+            .map(|closed| (closed.id, closed.portal, closed.report(true)))
+            .collect();
+
+        // The portal app never saw a freedesktop ID, so nothing is reported
+        assert_eq!(
+            portal,
+            vec![
+                (1, true, ClosureReport::None),
+                (2, false, ClosureReport::Signal)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replacement_closure_depends_on_id() {
+        let mut manager = NotificationManager::new();
+        manager.add_notification(create_test_notification("test", "Original"));
+
+        // Updated in place: the sender keeps using the same ID
+        let mut update = create_test_notification("test", "Update");
+        update.id = 1;
+        update.replaces_id = 1;
+        manager.add_notification(update);
+        assert!(manager.take_closed_notifications().is_empty());
+
+        // Replaced under a new ID: the old one is gone for good
+        let mut replacement = create_test_notification("test", "Replacement");
+        replacement.replaces_id = 1;
+        manager.add_notification(replacement);
+        assert_eq!(
+            manager.take_closed_notifications(),
+            vec![ClosedNotification {
+                id: 1,
+                reason: CloseReason::Undefined,
+                sender: None,
+                portal: false,
+            }]
+        );
+        assert_eq!(manager.active_count(), 1);
+    }
+
+    #[test]
+    fn test_replacement_updates_in_place() {
+        let mut manager = NotificationManager::new();
+        manager.add_notification(create_test_notification("test", "First"));
+        manager.add_notification(create_test_notification("test", "Second"));
+
+        let mut update = create_test_notification("test", "Progress");
+        update.id = 1;
+        update.replaces_id = 1;
+        let action = manager.add_notification(update);
+
+        assert_eq!(action, NotificationAction::Updated);
+        assert_eq!(manager.active_count(), 2);
+        let first = manager.get_notification_at(0).unwrap();
+        assert_eq!((first.id, first.summary.as_str()), (1, "Progress"));
+        // Superseded versions don't pile up in history
+        assert!(manager.history().is_empty());
+    }
+
+    #[test]
+    fn test_synchronous_notifications_replace_by_tag() {
+        let mut manager = NotificationManager::new();
+        let volume = |summary: &str| {
+            let mut notif = create_test_notification("osd", summary);
+            notif.hints.synchronous = Some("volume".to_string());
+            notif
+        };
+
+        manager.add_notification(volume("Volume 40%"));
+        manager.add_notification(create_test_notification("osd", "Unrelated"));
+        let action = manager.add_notification(volume("Volume 50%"));
+
+        assert_eq!(action, NotificationAction::Updated);
+        assert_eq!(manager.active_count(), 2);
+        let first = manager.get_notification_at(0).unwrap();
+        assert_eq!(first.summary, "Volume 50%");
+        assert_eq!(manager.take_closed_notifications()[0].id, 1);
+
+        // Other tags and other apps don't replace each other
+        let mut brightness = volume("Brightness 80%");
+        brightness.hints.synchronous = Some("brightness".to_string());
+        manager.add_notification(brightness);
+        let mut other_app = volume("Volume 60%");
+        other_app.app_name = "mixer".to_string();
+        manager.add_notification(other_app);
+        assert_eq!(manager.active_count(), 4);
+    }
+
+    #[test]
+    fn test_replacement_of_missing_notification_is_displayed() {
+        let mut manager = NotificationManager::new();
+
+        let mut replacement = create_test_notification("test", "Replacement");
+        replacement.replaces_id = 42;
+        let action = manager.add_notification(replacement);
+
+        assert_eq!(action, NotificationAction::Displayed);
+        assert_eq!(manager.active_count(), 1);
+    }
+
+    fn mail_identity() -> AppIdentity {
+        AppIdentity {
+            desktop_entry: "org.example.Mail".to_string(),
+            name: "Mail".to_string(),
+            icon: Some("mail-client".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_desktop_entry_identity() {
+        let mut manager = NotificationManager::new();
+
+        let mut notif = create_test_notification("mail-daemon", "New mail");
+        notif.hints.desktop_entry = Some("org.example.Mail".to_string());
+        manager.add_notification(notif.clone());
+
+        // Resolved after the notification was added
+        assert_eq!(
+            manager.get_notification(1).unwrap().display_name(),
+            "mail-daemon"
+        );
+        assert!(manager.set_app_identity(1, notif.timestamp, mail_identity()));
+
+        let shown = manager.get_notification(1).unwrap();
+        assert_eq!(shown.display_name(), "Mail");
+        assert_eq!(
+            shown.app_identity.as_ref().unwrap().icon.as_deref(),
+            Some("mail-client")
+        );
+        assert!(manager.get_notifications_by_app().contains_key("Mail"));
+
+        // Results for an older version of the notification are dropped
+        let mut update = notif.clone();
+        update.id = 1;
+        update.replaces_id = 1;
+        update.timestamp = notif.timestamp + Duration::seconds(1);
+        manager.add_notification(update.clone());
+        assert_eq!(manager.get_notification(1).unwrap().display_name(), "Mail");
+        assert!(!manager.set_app_identity(
+            1,
+            notif.timestamp,
+            AppIdentity {
+                name: "Stale".to_string(),
+                ..mail_identity()
+            }
+        ));
+        assert_eq!(manager.get_notification(1).unwrap().display_name(), "Mail");
+
+        // Filters match the resolved name as well as the raw app_name
+        manager.set_app_filter("Mail".to_string(), false);
+        let mut filtered = notif.clone();
+        filtered.id = 0;
+        filtered.app_identity = Some(mail_identity());
+        assert_eq!(
+            manager.add_notification(filtered),
+            NotificationAction::AddedToHistoryOnly
+        );
+    }
+
+    #[test]
+    fn test_resolved_identity_applies_filters() {
+        let mut manager = NotificationManager::new();
+        manager.set_app_filter("Mail".to_string(), false);
+
+        let mut notif = create_test_notification("mail-daemon", "New mail");
+        notif.hints.desktop_entry = Some("org.example.Mail".to_string());
+        assert_eq!(
+            manager.add_notification(notif.clone()),
+            NotificationAction::Displayed
+        );
+
+        // Hidden once its name is known, without a closure to report
+        assert!(!manager.set_app_identity(1, notif.timestamp, mail_identity()));
+        assert_eq!(manager.active_count(), 0);
+        assert!(manager.take_closed_notifications().is_empty());
+        assert_eq!(manager.history().back().unwrap().display_name(), "Mail");
+    }
+
+    #[test]
+    fn test_invoke_action_closes_non_resident() {
+        let mut manager = NotificationManager::new();
+        manager.add_notification(create_test_notification("test", "Plain"));
+
+        let mut resident = create_test_notification("test", "Resident");
+        resident.hints.resident = true;
+        manager.add_notification(resident);
+
+        assert_eq!(manager.invoke_action(1), ActionOutcome::Closed);
+        assert_eq!(
+            manager.take_closed_notifications(),
+            vec![ClosedNotification {
+                id: 1,
+                reason: CloseReason::Dismissed,
+                sender: None,
+                portal: false,
+            }]
+        );
+        assert_eq!(
+            manager.history().back().unwrap().close_reason,
+            Some(CloseReason::Dismissed)
+        );
+
+        assert_eq!(manager.invoke_action(2), ActionOutcome::Kept);
+        assert!(manager.get_notification(2).is_some());
+        assert!(manager.take_closed_notifications().is_empty());
+
+        assert_eq!(manager.invoke_action(1), ActionOutcome::NotFound);
+    }
+
+    #[test]
+    fn test_evicted_notifications_reported_closed() {
+        let mut manager = NotificationManager::new();
+
+        for i in 0..MAX_ACTIVE_NOTIFICATIONS + 2 {
+            let notif = create_test_notification("test", &format!("Notification {}", i));
+            manager.add_notification(notif);
+        }
+
+        let closed = manager.take_closed_notifications();
+        assert_eq!(closed.len(), 2);
+        assert!(closed
+            .iter()
+            .all(|closed| closed.reason == CloseReason::Undefined));
+        assert_eq!(closed[0].id, 1);
+    }
+
+    #[test]
+    fn test_id_allocator_shared_with_manager() {
+        let mut manager = NotificationManager::new();
+        let allocator = manager.id_allocator();
+
+        let external_id = allocator.next_id();
+        manager.add_notification(create_test_notification("test", "Test"));
+
+        let manager_id = manager
+            .get_notification_at(0)
+            .expect("notification should exist")
+            .id;
+        assert_ne!(external_id, manager_id);
+        assert_eq!(manager_id, external_id + 1);
+    }
+
+    #[test]
+    fn test_id_allocator_skips_zero() {
+        let allocator = IdAllocator::starting_at(u32::MAX);
+        assert_eq!(allocator.next_id(), u32::MAX);
+        assert_eq!(allocator.next_id(), 1);
+    }
+
+    #[test]
+    fn test_id_allocator_skips_ids_in_use() {
+        let allocator = IdAllocator::starting_at(u32::MAX);
+        allocator.reserve(1);
+        assert_eq!(allocator.next_id(), u32::MAX);
+        assert_eq!(allocator.next_id(), 2);
+
+        allocator.release(1);
+        assert!(!allocator.is_in_use(1));
+        assert!(allocator.is_in_use(2));
+    }
+
+    #[test]
+    fn test_ids_released_when_notifications_leave() {
+        let mut manager = NotificationManager::new();
+        let allocator = manager.id_allocator();
+
+        manager.add_notification(create_test_notification("test", "First"));
+        manager.add_notification(create_test_notification("test", "Second"));
+        assert!(allocator.is_in_use(1) && allocator.is_in_use(2));
+
+        manager.remove_notification(1);
+        assert!(!allocator.is_in_use(1));
+
+        // Hidden by Do Not Disturb: never active
+        manager.set_do_not_disturb(true);
+        manager.add_notification(create_test_notification("test", "Quiet"));
+        assert!(!allocator.is_in_use(3));
+
+        manager.clear_all();
+        assert!(!allocator.is_in_use(2));
+    }
+
     #[test]
     fn test_set_min_urgency_level_clamping() {
         let mut manager = NotificationManager::new();
@@ -211,25 +211,26 @@ impl Default for HistoryStorage {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::dbus::NotificationHints;
     use chrono::Local;
     use std::collections::HashMap;
     use tempfile::TempDir;
 
     fn create_test_notification(summary: &str, age_days: i64) -> Notification {
-        Notification {
-            id: 1,
-            app_name: "test".to_string(),
-            replaces_id: 0,
-            app_icon: String::new(),
-            summary: summary.to_string(),
-            body: String::new(),
-            actions: Vec::new(),
-            hints: NotificationHints::default(),
-            raw_hints: HashMap::new(),
-            expire_timeout: 0,
-            timestamp: Local::now() - chrono::Duration::days(age_days),
-        }
+        let mut notification = Notification::from_notify_args(
+            1,
+            (
+                "test".to_string(),
+                0,
+                String::new(),
+                summary.to_string(),
+                String::new(),
+                Vec::new(),
+                HashMap::new(),
+                0,
+            ),
+        );
+        notification.timestamp = Local::now() - chrono::Duration::days(age_days);
+        notification
     }
 
     fn create_test_storage() -> (HistoryStorage, TempDir) {
@@ -269,6 +270,34 @@ mod tests {
         assert_eq!(loaded[1].summary, "Test 2");
     }
 
+    #[test]
+    fn test_close_reason_persisted() {
+        let (storage, _temp) = create_test_storage();
+
+        let mut notification = create_test_notification("Closed", 0);
+        notification.close_reason = Some(crate::dbus::CloseReason::Expired);
+        storage.save(&VecDeque::from([notification])).unwrap();
+
+        let loaded = storage.load();
+        assert_eq!(
+            loaded[0].close_reason,
+            Some(crate::dbus::CloseReason::Expired)
+        );
+
+        // History saved before close reasons were recorded still loads
+        let content = std::fs::read_to_string(&storage.storage_path).unwrap();
+        let legacy: String = content
+            .lines()
+            .filter(|line| !line.contains("close_reason"))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        std::fs::write(&storage.storage_path, legacy).unwrap();
+
+        let loaded = storage.load();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].close_reason, None);
+    }
+
     #[test]
     fn test_load_corrupted_returns_empty() {
         let (storage, _temp) = create_test_storage();
@@ -0,0 +1,437 @@
+// Notification sound module
+//
+// Decides which sound a notification plays and hands the file to a
+// SoundSink. The sink is a trait so playback can be swapped out, e.g. for a
+// recording sink in tests on machines without audio.
+
+pub mod theme;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::dbus::{Notification, Urgency};
+
+pub use theme::SoundTheme;
+
+/// Sound played for normal urgency notifications without a sound hint
+const NORMAL_SOUND: &str = "message-new-instant";
+
+/// Sound played for critical notifications without a sound hint
+const CRITICAL_SOUND: &str = "dialog-warning";
+
+/// Command line players tried in order by `CommandSink`
+const PLAYERS: [&str; 2] = ["pw-play", "paplay"];
+
+/// Shortest time between two sounds started by `CommandSink`
+///
+/// A burst of notifications plays one sound instead of spawning a player
+/// for each.
+const MIN_SOUND_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Destination for notification sounds
+pub trait SoundSink: std::fmt::Debug + Send + Sync {
+    /// Start playing a sound file without blocking
+    ///
+    /// A sink may skip a sound, e.g. because another one just started, and
+    /// report that as an error of kind `WouldBlock`.
+    fn play(&self, path: &Path) -> std::io::Result<()>;
+}
+
+/// Plays sounds through PipeWire (`pw-play`) or PulseAudio (`paplay`)
+///
+/// Skips sounds starting within MIN_SOUND_INTERVAL of the previous one.
+#[derive(Debug, Default)]
+pub struct CommandSink {
+    /// When the last sound was started
+    last_started: Mutex<Option<Instant>>,
+}
+
+impl CommandSink {
+    /// Record a sound starting at `now`, unless the last one is too recent
+    fn try_start(&self, now: Instant) -> bool {
+        let mut last_started = self
+            .last_started
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if last_started.is_some_and(|last| now.duration_since(last) < MIN_SOUND_INTERVAL) {
+            return false;
+        }
+        *last_started = Some(now);
+        true
+    }
+}
+
+impl SoundSink for CommandSink {
+    fn play(&self, path: &Path) -> std::io::Result<()> {
+        if !self.try_start(Instant::now()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                "another sound started just before",
+            ));
+        }
+
+        let mut last_error = None;
+
+        for player in PLAYERS {
+            match std::process::Command::new(player).arg(path).spawn() {
+                Ok(mut child) => {
+                    // Reap the player when it exits
+                    std::thread::spawn(move || child.wait());
+                    return Ok(());
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => last_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound)))
+    }
+}
+
+/// Plays notification sounds according to their hints
+///
+/// Cheap to clone; clones share the sink.
+#[derive(Debug, Clone)]
+pub struct SoundPlayer {
+    /// Where sounds are played
+    sink: Arc<dyn SoundSink>,
+
+    /// Theme used to resolve sound names
+    theme: SoundTheme,
+
+    /// Whether sounds are enabled (`play_sound` in the config)
+    enabled: bool,
+}
+
+impl Default for SoundPlayer {
+    fn default() -> Self {
+        Self::new(Arc::new(CommandSink::default()), SoundTheme::default())
+    }
+}
+
+impl SoundPlayer {
+    /// Create a disabled player with the given sink and theme
+    pub fn new(sink: Arc<dyn SoundSink>, theme: SoundTheme) -> Self {
+        Self {
+            sink,
+            theme,
+            enabled: false,
+        }
+    }
+
+    /// Enable or disable sounds
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Check if sounds are enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Switch to the sound theme called `name`
+    ///
+    /// Keeps the current theme, and its cached lookups, if the name is
+    /// unchanged.
+    pub fn set_theme(&mut self, name: &str) {
+        if self.theme.name() != name {
+            tracing::debug!("Using sound theme {:?}", name);
+            self.theme = self.theme.with_name(name);
+        }
+    }
+
+    /// Get the sound theme in use
+    pub fn theme(&self) -> &SoundTheme {
+        &self.theme
+    }
+
+    /// Play the sound for a newly displayed notification
+    ///
+    /// Nothing is played when sounds are disabled, in Do Not Disturb mode
+    /// (critical notifications included), or when the notification sets
+    /// `suppress-sound`. Returns true if a sound was played.
+    pub fn play_for(&self, notification: &Notification, do_not_disturb: bool) -> bool {
+        if !self.enabled || do_not_disturb || notification.hints.suppress_sound {
+            return false;
+        }
+
+        let Some(path) = self.resolve(notification) else {
+            return false;
+        };
+
+        match self.sink.play(&path) {
+            Ok(()) => {
+                tracing::debug!("Playing {:?} for notification {}", path, notification.id);
+                true
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                tracing::debug!("Skipping sound for notification {}: {}", notification.id, e);
+                false
+            }
+            Err(e) => {
+                tracing::warn!("Failed to play notification sound {:?}: {}", path, e);
+                false
+            }
+        }
+    }
+
+    /// Find the sound file for a notification
+    ///
+    /// Order: the `sound-file` hint, then the `sound-name` hint, then the
+    /// default sound for the urgency (none for low urgency).
+    pub fn resolve(&self, notification: &Notification) -> Option<PathBuf> {
+        let hints = &notification.hints;
+
+        if let Some(file) = hints.sound_file.as_deref() {
+            let path = if file.starts_with("file://") {
+                file_uri_path(file)
+            } else {
+                Some(PathBuf::from(file))
+            };
+            if let Some(path) = path.filter(|path| path.is_absolute() && path.is_file()) {
+                return Some(path);
+            }
+            tracing::debug!("Ignoring missing or relative sound-file {:?}", file);
+        }
+
+        if let Some(path) = hints
+            .sound_name
+            .as_deref()
+            .and_then(|name| self.theme.lookup(name))
+        {
+            return Some(path);
+        }
+
+        default_sound(notification.urgency()).and_then(|name| self.theme.lookup(name))
+    }
+}
+
+/// Convert a file:// URI to a path, decoding escapes like `%20`
+fn file_uri_path(uri: &str) -> Option<PathBuf> {
+    url::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+/// Default sound name for an urgency level
+fn default_sound(urgency: Urgency) -> Option<&'static str> {
+    match urgency {
+        Urgency::Low => None,
+        Urgency::Normal => Some(NORMAL_SOUND),
+        Urgency::Critical => Some(CRITICAL_SOUND),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use crate::dbus::NotificationHints;
+
+    /// Sink that records what would have been played
+    #[derive(Debug, Default)]
+    struct RecordingSink(Mutex<Vec<PathBuf>>);
+
+    impl SoundSink for RecordingSink {
+        fn play(&self, path: &Path) -> std::io::Result<()> {
+            self.0.lock().unwrap().push(path.to_path_buf());
+            Ok(())
+        }
+    }
+
+    impl RecordingSink {
+        fn played(&self) -> Vec<PathBuf> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    /// Theme directory with the default sounds and a "bell" sound
+    fn sound_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let stereo = dir.path().join("freedesktop/stereo");
+        std::fs::create_dir_all(&stereo).unwrap();
+        for name in [NORMAL_SOUND, CRITICAL_SOUND, "bell"] {
+            std::fs::write(stereo.join(format!("{}.oga", name)), b"").unwrap();
+        }
+        dir
+    }
+
+    fn player(dir: &tempfile::TempDir) -> (SoundPlayer, Arc<RecordingSink>) {
+        let sink = Arc::new(RecordingSink::default());
+        let theme = SoundTheme::with_search_dirs("freedesktop", vec![dir.path().to_path_buf()]);
+        let mut player = SoundPlayer::new(sink.clone(), theme);
+        player.set_enabled(true);
+        (player, sink)
+    }
+
+    fn notification(hints: NotificationHints) -> Notification {
+        let mut notification = Notification::from_notify_args(
+            1,
+            (
+                "test".to_string(),
+                0,
+                String::new(),
+                "Summary".to_string(),
+                String::new(),
+                vec![],
+                Default::default(),
+                -1,
+            ),
+        );
+        notification.hints = hints;
+        notification
+    }
+
+    fn stereo(dir: &tempfile::TempDir, name: &str) -> PathBuf {
+        dir.path()
+            .join("freedesktop/stereo")
+            .join(format!("{}.oga", name))
+    }
+
+    #[test]
+    fn test_default_sound_per_urgency() {
+        let dir = sound_dir();
+        let (player, sink) = player(&dir);
+
+        for urgency in [Urgency::Low, Urgency::Normal, Urgency::Critical] {
+            player.play_for(
+                &notification(NotificationHints {
+                    urgency,
+                    ..Default::default()
+                }),
+                false,
+            );
+        }
+
+        // Low urgency notifications are silent
+        assert_eq!(
+            sink.played(),
+            vec![stereo(&dir, NORMAL_SOUND), stereo(&dir, CRITICAL_SOUND)]
+        );
+    }
+
+    #[test]
+    fn test_sound_name_hint() {
+        let dir = sound_dir();
+        let (player, sink) = player(&dir);
+
+        assert!(player.play_for(
+            &notification(NotificationHints {
+                sound_name: Some("bell".to_string()),
+                ..Default::default()
+            }),
+            false,
+        ));
+        assert_eq!(sink.played(), vec![stereo(&dir, "bell")]);
+    }
+
+    #[test]
+    fn test_configured_theme() {
+        let dir = sound_dir();
+        let custom = dir.path().join("custom/stereo");
+        std::fs::create_dir_all(&custom).unwrap();
+        std::fs::write(custom.join("bell.oga"), b"").unwrap();
+        let (mut player, sink) = player(&dir);
+        let bell = notification(NotificationHints {
+            sound_name: Some("bell".to_string()),
+            ..Default::default()
+        });
+
+        player.set_theme("custom");
+        assert!(player.play_for(&bell, false));
+        assert_eq!(sink.played(), vec![custom.join("bell.oga")]);
+
+        // Sounds the theme lacks come from freedesktop
+        assert_eq!(
+            player.resolve(&notification(NotificationHints::default())),
+            Some(stereo(&dir, NORMAL_SOUND))
+        );
+        assert_eq!(player.theme().name(), "custom");
+    }
+
+    #[test]
+    fn test_sound_file_takes_precedence() {
+        let dir = sound_dir();
+        let (player, sink) = player(&dir);
+        let file = dir.path().join("custom.wav");
+        std::fs::write(&file, b"").unwrap();
+
+        player.play_for(
+            &notification(NotificationHints {
+                sound_file: Some(format!("file://{}", file.display())),
+                sound_name: Some("bell".to_string()),
+                ..Default::default()
+            }),
+            false,
+        );
+
+        // A missing sound-file falls back to sound-name
+        player.play_for(
+            &notification(NotificationHints {
+                sound_file: Some("/nonexistent/sound.wav".to_string()),
+                sound_name: Some("bell".to_string()),
+                ..Default::default()
+            }),
+            false,
+        );
+
+        assert_eq!(sink.played(), vec![file, stereo(&dir, "bell")]);
+    }
+
+    #[test]
+    fn test_sound_file_uri_is_decoded() {
+        let dir = sound_dir();
+        let (player, _) = player(&dir);
+        let file = dir.path().join("new message.wav");
+        std::fs::write(&file, b"").unwrap();
+
+        let uri = url::Url::from_file_path(&file).unwrap().to_string();
+        assert!(uri.contains("%20"));
+        let resolved = player.resolve(&notification(NotificationHints {
+            sound_file: Some(uri),
+            ..Default::default()
+        }));
+        assert_eq!(resolved, Some(file));
+    }
+
+    #[test]
+    fn test_command_sink_rate_limit() {
+        let sink = CommandSink::default();
+        let now = Instant::now();
+
+        assert!(sink.try_start(now));
+        assert!(!sink.try_start(now + Duration::from_millis(100)));
+        assert!(sink.try_start(now + MIN_SOUND_INTERVAL));
+
+        // A sound just started, so the next play is skipped without a player
+        let skipped = sink.play(Path::new("/nonexistent/sound.oga")).unwrap_err();
+        assert_eq!(skipped.kind(), std::io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn test_silenced() {
+        let dir = sound_dir();
+        let (mut player, sink) = player(&dir);
+        let critical = notification(NotificationHints {
+            urgency: Urgency::Critical,
+            ..Default::default()
+        });
+
+        // Do Not Disturb silences even critical notifications
+        assert!(!player.play_for(&critical, true));
+
+        assert!(!player.play_for(
+            &notification(NotificationHints {
+                urgency: Urgency::Critical,
+                suppress_sound: true,
+                ..Default::default()
+            }),
+            false,
+        ));
+
+        player.set_enabled(false);
+        assert!(!player.play_for(&critical, false));
+
+        assert!(sink.played().is_empty());
+    }
+}
@@ -0,0 +1,373 @@
+// Sound theme lookup
+//
+// Resolves sound names to files following the freedesktop.org Sound Theme
+// Specification: themes live in $XDG_DATA_DIRS/sounds/<theme>/, inherit
+// from each other through index.theme, and fall back to "freedesktop".
+// The active theme is the desktop's sound theme setting, read through the
+// settings portal.
+// Reference: https://specifications.freedesktop.org/sound-theme-spec/latest/
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ashpd::desktop::settings::Settings;
+
+use crate::desktop_entry::xdg_data_dirs;
+
+/// Theme every lookup falls back to, per the spec
+pub const FALLBACK_THEME: &str = "freedesktop";
+
+/// Most sound names kept in a theme's lookup cache
+///
+/// Names come from the `sound-name` hint, so the cache starts over once it
+/// holds this many.
+const MAX_CACHED_SOUNDS: usize = 64;
+
+/// How long a sound name that wasn't found stays cached
+const MISS_TTL: Duration = Duration::from_secs(60);
+
+/// Output profile looked up before the theme root
+const OUTPUT_PROFILE: &str = "stereo";
+
+/// Sound file extensions, in order of preference
+const EXTENSIONS: [&str; 3] = ["oga", "ogg", "wav"];
+
+/// A cached lookup
+#[derive(Debug, Clone)]
+enum CachedSound {
+    Found(PathBuf),
+    Missing { since: Instant },
+}
+
+/// A sound theme and the directories it is searched in
+///
+/// Lookups walk the theme directories on disk, so their results are cached
+/// by sound name. Clones share the cache.
+#[derive(Debug, Clone)]
+pub struct SoundTheme {
+    /// Theme name (e.g. "freedesktop")
+    name: String,
+
+    /// `sounds` directories, in priority order
+    search_dirs: Vec<PathBuf>,
+
+    /// Sound name -> resolved file
+    cache: Arc<Mutex<HashMap<String, CachedSound>>>,
+}
+
+impl Default for SoundTheme {
+    fn default() -> Self {
+        Self::new(FALLBACK_THEME)
+    }
+}
+
+impl SoundTheme {
+    /// Create a theme searched in the XDG data directories
+    ///
+    /// Searches `$XDG_DATA_HOME/sounds`, then `sounds` in each of
+    /// `$XDG_DATA_DIRS` (defaulting to /usr/local/share:/usr/share).
+    pub fn new(name: impl Into<String>) -> Self {
+        Self::with_search_dirs(
+            name,
+            xdg_data_dirs()
+                .into_iter()
+                .map(|dir| dir.join("sounds"))
+                .collect(),
+        )
+    }
+
+    /// Create a theme searched in the given `sounds` directories
+    pub fn with_search_dirs(name: impl Into<String>, search_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            search_dirs,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Get the theme name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Create a theme named `name` searched in the same directories
+    ///
+    /// The new theme starts with an empty cache.
+    pub fn with_name(&self, name: impl Into<String>) -> Self {
+        Self::with_search_dirs(name, self.search_dirs.clone())
+    }
+
+    /// Find the file for a sound name
+    ///
+    /// Tries the name in this theme and the themes it inherits from, then
+    /// the same for each less specific name (`message-new-instant`, then
+    /// `message-new`, then `message`), and finally unthemed sounds.
+    pub fn lookup(&self, sound_name: &str) -> Option<PathBuf> {
+        self.lookup_at(sound_name, Instant::now())
+    }
+
+    /// Find the file for a sound name, expiring cached misses as of `now`
+    fn lookup_at(&self, sound_name: &str, now: Instant) -> Option<PathBuf> {
+        let mut cache = self
+            .cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        match cache.get(sound_name) {
+            Some(CachedSound::Found(path)) => return Some(path.clone()),
+            Some(CachedSound::Missing { since }) if now.duration_since(*since) < MISS_TTL => {
+                return None;
+            }
+            _ => {}
+        }
+
+        let found = self.find(sound_name);
+        if cache.len() >= MAX_CACHED_SOUNDS && !cache.contains_key(sound_name) {
+            cache.clear();
+        }
+        let cached = match &found {
+            Some(path) => CachedSound::Found(path.clone()),
+            None => CachedSound::Missing { since: now },
+        };
+        cache.insert(sound_name.to_string(), cached);
+        found
+    }
+
+    /// Search the theme directories for a sound name
+    fn find(&self, sound_name: &str) -> Option<PathBuf> {
+        let themes = self.inheritance_chain();
+
+        let mut name = sound_name;
+        loop {
+            for theme in &themes {
+                if let Some(path) = self.find_in_theme(theme, name) {
+                    return Some(path);
+                }
+            }
+
+            match name.rsplit_once('-') {
+                Some((less_specific, _)) => name = less_specific,
+                None => break,
+            }
+        }
+
+        // Sounds installed outside any theme
+        self.search_dirs
+            .iter()
+            .find_map(|dir| find_sound_file(dir, sound_name))
+    }
+
+    /// Themes to search, starting with this one and ending with the fallback
+    fn inheritance_chain(&self) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = vec![self.name.clone()];
+
+        while let Some(theme) = queue.pop() {
+            if !seen.insert(theme.clone()) {
+                continue;
+            }
+
+            // Parents are searched in the order index.theme lists them
+            let mut parents = self.parents(&theme);
+            parents.reverse();
+            queue.extend(parents);
+            chain.push(theme);
+        }
+
+        if !seen.contains(FALLBACK_THEME) {
+            chain.push(FALLBACK_THEME.to_string());
+        }
+
+        chain
+    }
+
+    /// Read the `Inherits` key from the first index.theme found for a theme
+    fn parents(&self, theme: &str) -> Vec<String> {
+        let Some(index) = self
+            .search_dirs
+            .iter()
+            .map(|dir| dir.join(theme).join("index.theme"))
+            .find_map(|path| std::fs::read_to_string(path).ok())
+        else {
+            return Vec::new();
+        };
+
+        index
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("Inherits="))
+            .flat_map(|parents| parents.split(','))
+            .map(|parent| parent.trim().to_string())
+            .filter(|parent| !parent.is_empty())
+            .collect()
+    }
+
+    /// Look for a sound in one theme across all search directories
+    fn find_in_theme(&self, theme: &str, name: &str) -> Option<PathBuf> {
+        self.search_dirs.iter().find_map(|dir| {
+            let theme_dir = dir.join(theme);
+            find_sound_file(&theme_dir.join(OUTPUT_PROFILE), name)
+                .or_else(|| find_sound_file(&theme_dir, name))
+        })
+    }
+}
+
+/// Read the desktop's sound theme setting
+///
+/// Queries the settings portal for `org.gnome.desktop.sound` `theme-name`.
+/// Returns None when the portal or the setting is unavailable.
+pub async fn desktop_sound_theme() -> Option<String> {
+    let theme = async {
+        Settings::new()
+            .await?
+            .read::<String>("org.gnome.desktop.sound", "theme-name")
+            .await
+    };
+
+    match theme.await {
+        Ok(theme) if !theme.is_empty() => Some(theme),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::debug!("Failed to read the desktop sound theme: {}", e);
+            None
+        }
+    }
+}
+
+/// Find `<name>.<ext>` in a directory, trying each supported extension
+fn find_sound_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn touch(path: PathBuf) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+
+    fn test_theme(root: &Path) -> SoundTheme {
+        SoundTheme::with_search_dirs("custom", vec![root.to_path_buf()])
+    }
+
+    #[test]
+    fn test_lookup_in_theme() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(dir.path().join("custom/stereo/bell.oga"));
+
+        assert_eq!(
+            test_theme(dir.path()).lookup("bell"),
+            Some(dir.path().join("custom/stereo/bell.oga"))
+        );
+    }
+
+    #[test]
+    fn test_lookup_less_specific_name() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(dir.path().join("custom/stereo/message.wav"));
+
+        assert_eq!(
+            test_theme(dir.path()).lookup("message-new-instant"),
+            Some(dir.path().join("custom/stereo/message.wav"))
+        );
+    }
+
+    #[test]
+    fn test_lookup_follows_inheritance() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("custom")).unwrap();
+        fs::write(
+            dir.path().join("custom/index.theme"),
+            "[Sound Theme]\nName=Custom\nInherits=parent\n",
+        )
+        .unwrap();
+        touch(dir.path().join("parent/stereo/bell.oga"));
+        touch(dir.path().join("freedesktop/stereo/dialog-warning.oga"));
+
+        let theme = test_theme(dir.path());
+        assert_eq!(
+            theme.lookup("bell"),
+            Some(dir.path().join("parent/stereo/bell.oga"))
+        );
+        // Everything falls back to the freedesktop theme
+        assert_eq!(
+            theme.lookup("dialog-warning"),
+            Some(dir.path().join("freedesktop/stereo/dialog-warning.oga"))
+        );
+    }
+
+    #[test]
+    fn test_exact_name_searched_in_all_themes_first() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(dir.path().join("custom/stereo/message.oga"));
+        touch(
+            dir.path()
+                .join("freedesktop/stereo/message-new-instant.oga"),
+        );
+
+        // The exact name in the fallback theme wins over a less specific one
+        assert_eq!(
+            test_theme(dir.path()).lookup("message-new-instant"),
+            Some(
+                dir.path()
+                    .join("freedesktop/stereo/message-new-instant.oga")
+            )
+        );
+    }
+
+    #[test]
+    fn test_inheritance_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("custom")).unwrap();
+        fs::create_dir_all(dir.path().join("other")).unwrap();
+        fs::write(dir.path().join("custom/index.theme"), "Inherits=other\n").unwrap();
+        fs::write(dir.path().join("other/index.theme"), "Inherits=custom\n").unwrap();
+
+        assert_eq!(test_theme(dir.path()).lookup("bell"), None);
+    }
+
+    #[test]
+    fn test_lookup_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let theme = test_theme(dir.path());
+        let bell = dir.path().join("custom/stereo/bell.oga");
+        let now = Instant::now();
+
+        // Misses are cached for a while
+        assert_eq!(theme.lookup_at("bell", now), None);
+        touch(bell.clone());
+        assert_eq!(theme.lookup_at("bell", now), None);
+        assert_eq!(theme.lookup_at("bell", now + MISS_TTL), Some(bell.clone()));
+
+        // Hits stay cached, also in clones
+        fs::remove_file(&bell).unwrap();
+        assert_eq!(
+            theme.clone().lookup_at("bell", now + MISS_TTL * 2),
+            Some(bell)
+        );
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let theme = test_theme(dir.path());
+        for i in 0..MAX_CACHED_SOUNDS * 2 {
+            theme.lookup(&format!("made-up-{i}"));
+            assert!(theme.cache.lock().unwrap().len() <= MAX_CACHED_SOUNDS);
+        }
+    }
+
+    #[test]
+    fn test_lookup_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(test_theme(dir.path()).lookup("bell"), None);
+    }
+}
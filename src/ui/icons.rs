@@ -0,0 +1,223 @@
+// Icon resolution module
+//
+// Resolves notification icon strings to files. `app_icon` and `image-path`
+// may hold a file:// URI, an absolute path or a themed icon name; themed
+// names are looked up with the freedesktop.org icon theme spec.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Most icon strings kept in the cache
+///
+/// Apps may send a different path or name with every notification; the
+/// cache starts over once it holds this many.
+const MAX_CACHED_ICONS: usize = 256;
+
+/// How long an icon that wasn't found stays cached
+///
+/// The file may appear later, e.g. once an app finishes writing it.
+const MISS_TTL: Duration = Duration::from_secs(60);
+
+/// A cached resolution
+#[derive(Debug, Clone)]
+enum CachedIcon {
+    Found(PathBuf),
+    Missing { since: Instant },
+}
+
+/// Resolves icon strings to files, caching the results
+///
+/// Themed lookups read icon theme directories, so resolve icons off the UI
+/// thread.
+#[derive(Debug, Clone)]
+pub struct IconResolver {
+    /// Icon theme to search first (falls back to hicolor)
+    theme: String,
+
+    /// Icon size in logical pixels
+    size: u16,
+
+    /// Display scale factor
+    scale: u16,
+
+    /// Icon string -> resolved file
+    cache: HashMap<String, CachedIcon>,
+}
+
+impl IconResolver {
+    /// Create a resolver for icons of the given size and scale
+    pub fn new(theme: impl Into<String>, size: u16, scale: u16) -> Self {
+        Self {
+            theme: theme.into(),
+            size,
+            scale: scale.max(1),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Switch to another icon theme, forgetting icons of the previous one
+    pub fn set_theme(&mut self, theme: impl Into<String>) {
+        let theme = theme.into();
+        if theme != self.theme {
+            self.theme = theme;
+            self.cache.clear();
+        }
+    }
+
+    /// Resolve an icon string to a file
+    ///
+    /// Accepts `file://` URIs, absolute paths and themed icon names. Paths
+    /// must point to an existing file. Returns None if nothing is found.
+    pub fn resolve(&mut self, icon: &str) -> Option<PathBuf> {
+        self.resolve_at(icon, Instant::now())
+    }
+
+    /// Resolve an icon string, expiring cached misses as of `now`
+    fn resolve_at(&mut self, icon: &str, now: Instant) -> Option<PathBuf> {
+        if icon.is_empty() {
+            return None;
+        }
+
+        match self.cache.get(icon) {
+            Some(CachedIcon::Found(path)) => return Some(path.clone()),
+            Some(CachedIcon::Missing { since }) if now.duration_since(*since) < MISS_TTL => {
+                return None;
+            }
+            _ => {}
+        }
+
+        let resolved = if icon.starts_with("file://") {
+            file_uri_path(icon)
+        } else if icon.starts_with('/') {
+            Some(PathBuf::from(icon))
+        } else {
+            self.lookup_themed(icon)
+        }
+        .filter(|path| path.is_file());
+
+        if self.cache.len() >= MAX_CACHED_ICONS && !self.cache.contains_key(icon) {
+            self.cache.clear();
+        }
+        let cached = match &resolved {
+            Some(path) => CachedIcon::Found(path.clone()),
+            None => {
+                tracing::debug!("Could not resolve notification icon '{}'", icon);
+                CachedIcon::Missing { since: now }
+            }
+        };
+        self.cache.insert(icon.to_string(), cached);
+        resolved
+    }
+
+    /// Look up a themed icon at the display scale, then at scale 1
+    fn lookup_themed(&self, name: &str) -> Option<PathBuf> {
+        let lookup = |scale| {
+            freedesktop_icons::lookup(name)
+                .with_theme(&self.theme)
+                .with_size(self.size)
+                .with_scale(scale)
+                .find()
+        };
+
+        let scaled = if self.scale > 1 {
+            lookup(self.scale)
+        } else {
+            None
+        };
+        scaled.or_else(|| lookup(1))
+    }
+}
+
+/// Convert a file:// URI to a path, decoding escapes like `%20`
+fn file_uri_path(uri: &str) -> Option<PathBuf> {
+    url::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver() -> IconResolver {
+        IconResolver::new("hicolor", 32, 1)
+    }
+
+    #[test]
+    fn test_resolve_absolute_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("icon.png");
+        std::fs::write(&path, b"").unwrap();
+
+        assert_eq!(
+            resolver().resolve(path.to_str().unwrap()),
+            Some(path.clone())
+        );
+        assert_eq!(resolver().resolve("/nonexistent/icon.png"), None);
+    }
+
+    #[test]
+    fn test_resolve_file_uri() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("my icon.png");
+        std::fs::write(&path, b"").unwrap();
+
+        let uri = url::Url::from_file_path(&path).unwrap().to_string();
+        assert!(uri.contains("%20"));
+        assert_eq!(resolver().resolve(&uri), Some(path));
+    }
+
+    #[test]
+    fn test_resolve_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("late.png");
+        let icon = path.to_str().unwrap();
+        let mut resolver = resolver();
+        let now = Instant::now();
+
+        // Misses are cached for a while, then looked up again
+        assert_eq!(resolver.resolve_at(icon, now), None);
+        std::fs::write(&path, b"").unwrap();
+        assert_eq!(
+            resolver.resolve_at(icon, now + Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(
+            resolver.resolve_at(icon, now + MISS_TTL),
+            Some(path.clone())
+        );
+
+        // Hits stay cached
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resolver.resolve_at(icon, now + MISS_TTL * 2), Some(path));
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let mut resolver = resolver();
+        for i in 0..MAX_CACHED_ICONS * 2 {
+            resolver.resolve(&format!("/nonexistent/icon-{i}.png"));
+            assert!(resolver.cache.len() <= MAX_CACHED_ICONS);
+        }
+    }
+
+    #[test]
+    fn test_theme_change_clears_cache() {
+        let mut resolver = resolver();
+        resolver.resolve("/nonexistent/icon.png");
+
+        resolver.set_theme("hicolor");
+        assert_eq!(resolver.cache.len(), 1);
+        resolver.set_theme("Cosmic");
+        assert!(resolver.cache.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_empty_and_unknown_name() {
+        let mut resolver = resolver();
+        assert_eq!(resolver.resolve(""), None);
+        assert_eq!(
+            resolver.resolve("no-such-icon-cosmic-applet-notifications"),
+            None
+        );
+    }
+}
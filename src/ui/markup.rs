@@ -0,0 +1,438 @@
+// Body markup module
+//
+// Parses the markup subset allowed in notification bodies by the
+// freedesktop.org spec (<b>, <i>, <u>, <a href>, <img>) into styled spans.
+// Anything outside the whitelist is dropped, keeping only its text.
+
+use crate::dbus::sanitize::is_stripped;
+use crate::ui::url_parser::{parse_text, validate_url, TextSegment};
+
+/// Text style applied by markup tags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpanStyle {
+    /// Inside <b>
+    pub bold: bool,
+    /// Inside <i>
+    pub italic: bool,
+    /// Inside <u>
+    pub underline: bool,
+}
+
+/// A run of text with a single style and optional link target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// Text to display (entities already decoded)
+    pub text: String,
+    /// Style from enclosing tags
+    pub style: SpanStyle,
+    /// Validated link target, from <a href> or a detected URL
+    pub link: Option<String>,
+}
+
+/// Parse a notification body into styled spans
+///
+/// Tags are matched loosely, as senders often produce unbalanced markup:
+/// closing tags only end a style that is open, and unclosed tags end with
+/// the body. A `<` that doesn't start a tag is kept as literal text.
+///
+/// Link targets go through the same scheme checks as detected URLs, so
+/// `<a href="javascript:...">` renders as plain text. Bare URLs outside
+/// links are detected as in plain-text bodies. Images are replaced by
+/// their alt text.
+///
+/// # Examples
+///
+/// ```
+/// use cosmic_applet_notifications::ui::markup::parse_markup;
+///
+/// let spans = parse_markup("<b>Build</b> finished");
+/// assert!(spans[0].style.bold);
+/// assert_eq!(spans[1].text, " finished");
+/// ```
+pub fn parse_markup(body: &str) -> Vec<Span> {
+    let mut parser = Parser::default();
+    let mut rest = body;
+
+    while let Some(start) = rest.find('<') {
+        let tag = &rest[start + 1..];
+        let Some(end) = tag.find(['<', '>']) else {
+            break;
+        };
+
+        if tag[end..].starts_with('<') {
+            // Not a tag, e.g. the first `<` in "1 < 2 <b>"
+            parser.push_text(&rest[..start + 1]);
+            rest = tag;
+            continue;
+        }
+
+        parser.push_text(&rest[..start]);
+        parser.handle_tag(&tag[..end]);
+        rest = &tag[end + 1..];
+    }
+    parser.push_text(rest);
+
+    parser.spans
+}
+
+/// Get the first link target in a notification body
+///
+/// Considers both `<a href>` links and bare URLs.
+pub fn first_link(body: &str) -> Option<String> {
+    parse_markup(body).into_iter().find_map(|span| span.link)
+}
+
+/// Parser state: open tag depths and the spans built so far
+#[derive(Debug, Default)]
+struct Parser {
+    bold: usize,
+    italic: usize,
+    underline: usize,
+    /// Targets of open <a> tags, None for links without a valid href
+    links: Vec<Option<String>>,
+    spans: Vec<Span>,
+}
+
+impl Parser {
+    fn style(&self) -> SpanStyle {
+        SpanStyle {
+            bold: self.bold > 0,
+            italic: self.italic > 0,
+            underline: self.underline > 0,
+        }
+    }
+
+    /// Add raw text from between tags
+    fn push_text(&mut self, raw: &str) {
+        if raw.is_empty() {
+            return;
+        }
+
+        let text = decode_entities(raw);
+        match self.links.last() {
+            Some(link) => self.push_span(text, link.clone()),
+            None => {
+                // Detect bare URLs the same way plain-text bodies do
+                for segment in parse_text(&text) {
+                    match segment {
+                        TextSegment::Text(text) => self.push_span(text, None),
+                        TextSegment::Link { text, url } => self.push_span(text, Some(url)),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add a span, merging it into the previous one when they match
+    fn push_span(&mut self, text: String, link: Option<String>) {
+        if text.is_empty() {
+            return;
+        }
+
+        let style = self.style();
+        if let Some(last) = self.spans.last_mut() {
+            if last.style == style && last.link == link {
+                last.text.push_str(&text);
+                return;
+            }
+        }
+
+        self.spans.push(Span { text, style, link });
+    }
+
+    /// Apply a tag, given the text between `<` and `>`
+    fn handle_tag(&mut self, tag: &str) {
+        let tag = tag.trim();
+        let (closing, tag) = match tag.strip_prefix('/') {
+            Some(tag) => (true, tag),
+            None => (false, tag),
+        };
+        let tag = tag.strip_suffix('/').unwrap_or(tag);
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+
+        match (name.to_ascii_lowercase().as_str(), closing) {
+            ("b", false) => self.bold += 1,
+            ("b", true) => self.bold = self.bold.saturating_sub(1),
+            ("i", false) => self.italic += 1,
+            ("i", true) => self.italic = self.italic.saturating_sub(1),
+            ("u", false) => self.underline += 1,
+            ("u", true) => self.underline = self.underline.saturating_sub(1),
+            ("a", false) => {
+                let href = attribute(attributes, "href").and_then(|href| validate_url(&href));
+                self.links.push(href);
+            }
+            ("a", true) => {
+                self.links.pop();
+            }
+            ("img", false) => {
+                if let Some(alt) = attribute(attributes, "alt") {
+                    let link = self.links.last().cloned().flatten();
+                    self.push_span(alt, link);
+                }
+            }
+            (name, _) => {
+                tracing::trace!("Dropping unsupported markup tag <{}>", name);
+            }
+        }
+    }
+}
+
+/// Find an attribute value in a tag, e.g. `href` in `href="https://..."`
+///
+/// Accepts double, single or unquoted values. Entities in the value are decoded.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes.trim_start();
+
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+
+        let Some(after_eq) = rest.strip_prefix('=') else {
+            // Attribute without a value
+            if key.eq_ignore_ascii_case(name) {
+                return Some(String::new());
+            }
+            continue;
+        };
+        let after_eq = after_eq.trim_start();
+
+        let (value, remaining) = match after_eq.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let inner = &after_eq[1..];
+                let end = inner.find(quote).unwrap_or(inner.len());
+                (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+            }
+            _ => {
+                let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                (&after_eq[..end], &after_eq[end..])
+            }
+        };
+
+        if key.eq_ignore_ascii_case(name) {
+            return Some(decode_entities(value));
+        }
+        rest = remaining.trim_start();
+    }
+
+    None
+}
+
+/// Decode XML character entities
+///
+/// Handles the five predefined entities and numeric references. References
+/// to characters the sanitization stage strips (control and bidi-override
+/// characters) decode to nothing. Anything else, such as a bare `&`, is kept
+/// as-is.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+
+        match entity {
+            Some((c, end)) => {
+                // Entities must not smuggle in bidi overrides or control characters
+                if !is_stripped(c) {
+                    decoded.push(c);
+                }
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+/// Decode a single entity name (without `&` and `;`)
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let code =
+                if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                    u32::from_str_radix(hex, 16).ok()?
+                } else {
+                    name.strip_prefix('#')?.parse().ok()?
+                };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, style: SpanStyle, link: Option<&str>) -> Span {
+        Span {
+            text: text.to_string(),
+            style,
+            link: link.map(str::to_string),
+        }
+    }
+
+    const PLAIN: SpanStyle = SpanStyle {
+        bold: false,
+        italic: false,
+        underline: false,
+    };
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(
+            parse_markup("Hello world"),
+            vec![span("Hello world", PLAIN, None)]
+        );
+        assert!(parse_markup("").is_empty());
+    }
+
+    #[test]
+    fn test_styles() {
+        let spans = parse_markup("<b>bold <i>both</i></b> <u>under</u>");
+        let bold = SpanStyle {
+            bold: true,
+            ..PLAIN
+        };
+        let both = SpanStyle {
+            bold: true,
+            italic: true,
+            ..PLAIN
+        };
+        let under = SpanStyle {
+            underline: true,
+            ..PLAIN
+        };
+
+        assert_eq!(
+            spans,
+            vec![
+                span("bold ", bold, None),
+                span("both", both, None),
+                span(" ", PLAIN, None),
+                span("under", under, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tags_case_insensitive_and_unbalanced() {
+        let spans = parse_markup("</b><B>loud</B> quiet <i>open");
+        assert!(spans[0].style.bold);
+        assert_eq!(spans[1], span(" quiet ", PLAIN, None));
+        assert!(spans[2].style.italic);
+    }
+
+    #[test]
+    fn test_explicit_link() {
+        let spans = parse_markup(r#"See <a href="https://example.com/a?b=1&amp;c=2">docs</a>"#);
+        assert_eq!(
+            spans,
+            vec![
+                span("See ", PLAIN, None),
+                span("docs", PLAIN, Some("https://example.com/a?b=1&c=2")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dangerous_link_rendered_as_text() {
+        let spans = parse_markup("<a href='javascript:alert(1)'>click</a>");
+        assert_eq!(spans, vec![span("click", PLAIN, None)]);
+
+        let spans = parse_markup("<a href=file:///etc/passwd>open</a>");
+        assert_eq!(spans, vec![span("open", PLAIN, None)]);
+    }
+
+    #[test]
+    fn test_bare_urls_detected() {
+        let spans = parse_markup("<b>Go to www.example.com</b>");
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[1].link.as_deref(), Some("https://www.example.com"));
+        assert!(spans[1].style.bold);
+    }
+
+    #[test]
+    fn test_unsupported_tags_dropped() {
+        let spans = parse_markup(r#"<span color="red">red</span> <script>x</script>"#);
+        assert_eq!(spans, vec![span("red x", PLAIN, None)]);
+    }
+
+    #[test]
+    fn test_img_alt_text() {
+        let spans = parse_markup(r#"<img src="/tmp/a.png" alt="[photo]"/> sent"#);
+        assert_eq!(spans, vec![span("[photo] sent", PLAIN, None)]);
+
+        // No alt text: the image is dropped
+        assert_eq!(
+            parse_markup(r#"<img src="/tmp/a.png"/>"#),
+            Vec::<Span>::new()
+        );
+    }
+
+    #[test]
+    fn test_entities() {
+        assert_eq!(
+            parse_markup("a &lt; b &amp;&amp; c &#62; d &#x263A; Tom & Jerry &bogus;"),
+            vec![span("a < b && c > d ☺ Tom & Jerry &bogus;", PLAIN, None)]
+        );
+    }
+
+    #[test]
+    fn test_entities_cannot_encode_bidi_overrides() {
+        assert_eq!(
+            parse_markup("Invoice&#x202E;fdp.exe&#8238;&#7; ok"),
+            vec![span("Invoicefdp.exe ok", PLAIN, None)]
+        );
+    }
+
+    #[test]
+    fn test_unclosed_angle_bracket_is_literal() {
+        assert_eq!(
+            parse_markup("1 < 2 is <b>true"),
+            vec![
+                span("1 < 2 is ", PLAIN, None),
+                span(
+                    "true",
+                    SpanStyle {
+                        bold: true,
+                        ..PLAIN
+                    },
+                    None
+                ),
+            ]
+        );
+        assert_eq!(parse_markup("x < y"), vec![span("x < y", PLAIN, None)]);
+    }
+
+    #[test]
+    fn test_first_link() {
+        assert_eq!(
+            first_link(r#"<a href="https://a.example">a</a> https://b.example"#),
+            Some("https://a.example".to_string())
+        );
+        assert_eq!(
+            first_link("see https://b.example"),
+            Some("https://b.example".to_string())
+        );
+        assert_eq!(first_link("<b>nothing</b>"), None);
+    }
+}
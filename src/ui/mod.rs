@@ -3,6 +3,8 @@
 // This module contains UI components for displaying notifications.
 
 pub mod animation;
+pub mod icons;
+pub mod markup;
 pub mod positioning;
 pub mod theme;
 pub mod url_parser;
@@ -165,6 +165,9 @@ impl ComponentSize {
 
     /// Border width for urgency indicator (left border)
     pub const URGENCY_BORDER_WIDTH: f32 = 3.0;
+
+    /// Height of the progress bar for `value` hint notifications
+    pub const PROGRESS_BAR_HEIGHT: f32 = 6.0;
 }
 
 /// Urgency level visual styling
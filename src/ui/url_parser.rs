@@ -103,7 +103,7 @@ pub fn parse_text(text: &str) -> Vec<TextSegment> {
 ///
 /// Returns None for dangerous or malformed URLs.
 /// Normalizes URLs by adding scheme when missing (www.example.com -> https://www.example.com).
-fn validate_url(url: &str) -> Option<String> {
+pub(crate) fn validate_url(url: &str) -> Option<String> {
     // Reject dangerous URL schemes
     let url_lower = url.to_lowercase();
     if url_lower.starts_with("javascript:")
@@ -147,6 +147,58 @@ pub fn extract_first_url(text: &str) -> Option<String> {
         .and_then(validate_url)
 }
 
+/// Validate an attachment URL from the `x-kde-urls` hint
+///
+/// Unlike links found in text, attachments are declared by the sender, so
+/// `file:` URLs are accepted as long as they don't point to something that
+/// would run when opened (a desktop entry or an executable file). Other URLs
+/// go through the same checks as links.
+pub fn validate_attachment_url(url: &str) -> Option<String> {
+    if !url.to_lowercase().starts_with("file:") {
+        return validate_url(url);
+    }
+
+    let path = url::Url::parse(url).ok()?.to_file_path().ok()?;
+    if path.extension().is_some_and(|ext| ext == "desktop") || is_executable(&path) {
+        tracing::warn!("Rejected runnable attachment: {}", url);
+        return None;
+    }
+    Some(url.to_string())
+}
+
+/// Check whether a path is a regular file with an execute bit set
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path)
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &std::path::Path) -> bool {
+    false
+}
+
+/// Get a short label for an attachment URL
+///
+/// Files are labelled with their name and links with their host, falling
+/// back to the whole URL.
+pub fn attachment_label(url: &str) -> String {
+    let Ok(parsed) = url::Url::parse(url) else {
+        return url.to_string();
+    };
+
+    let label = match parsed.to_file_path() {
+        Ok(path) => path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned()),
+        Err(()) => parsed.host_str().map(str::to_string),
+    };
+    label.unwrap_or_else(|| url.to_string())
+}
+
 /// Open a URL using the system's default handler
 ///
 /// Uses xdg-open on Linux to open URLs in the default browser/application.
@@ -253,6 +305,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_attachment_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let document = dir.path().join("report 2.pdf");
+        std::fs::write(&document, b"").unwrap();
+        let document_url = url::Url::from_file_path(&document).unwrap().to_string();
+        assert_eq!(
+            validate_attachment_url(&document_url),
+            Some(document_url.clone())
+        );
+        assert_eq!(attachment_label(&document_url), "report 2.pdf");
+
+        // Files that would run when opened are refused
+        let entry = url::Url::from_file_path(dir.path().join("app.desktop")).unwrap();
+        assert_eq!(validate_attachment_url(entry.as_str()), None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let script = dir.path().join("run.sh");
+            std::fs::write(&script, b"").unwrap();
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+            let script_url = url::Url::from_file_path(&script).unwrap();
+            assert_eq!(validate_attachment_url(script_url.as_str()), None);
+        }
+
+        // Other URLs are checked like links
+        assert_eq!(
+            validate_attachment_url("https://example.com/photo.jpg"),
+            Some("https://example.com/photo.jpg".to_string())
+        );
+        assert_eq!(
+            attachment_label("https://example.com/photo.jpg"),
+            "example.com"
+        );
+        assert_eq!(validate_attachment_url("javascript:alert(1)"), None);
+    }
+
     #[test]
     fn test_validate_url_dangerous_javascript() {
         let url = validate_url("javascript:alert('xss')");
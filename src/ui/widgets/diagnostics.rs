@@ -0,0 +1,81 @@
+// Diagnostics widget
+//
+// Displays the notification listener's connection state and health counters,
+// collapsed by default. Used to debug "missing notification" reports.
+// Follows COSMIC design patterns for consistent appearance.
+
+use cosmic::iced::Length;
+use cosmic::widget::{button, column, row, text};
+use cosmic::Element;
+
+use crate::dbus::{ListenerStatus, MetricsSnapshot};
+use crate::ui::theme::Spacing;
+
+/// Create a diagnostics widget
+///
+/// Displays, when expanded:
+/// - Listener connection state (`None` in daemon mode, where there is no listener)
+/// - Received and parsed message counts
+/// - Messages lost or degraded: parse failures, receive errors,
+///   unpaired evictions and reply timeouts
+/// - Reconnect count
+/// - Message queue stalls and peak depth
+pub fn diagnostics<'a, Message>(
+    metrics: MetricsSnapshot,
+    status: Option<ListenerStatus>,
+    expanded: bool,
+    on_toggle: Message,
+) -> Element<'a, Message>
+where
+    Message: Clone + 'a + 'static,
+{
+    let mut content = column().spacing(Spacing::s()).padding(Spacing::m());
+
+    // Section header with the expand/collapse button
+    let header = row()
+        .push(text::title3("Diagnostics").width(Length::Fill))
+        .push(button::text(if expanded { "Hide" } else { "Show" }).on_press(on_toggle))
+        .align_y(cosmic::iced::Alignment::Center);
+
+    content = content.push(header);
+
+    if !expanded {
+        return content.into();
+    }
+
+    let listener = match status {
+        Some(ListenerStatus::Connected) => "Connected",
+        Some(ListenerStatus::Disconnected) => "Reconnecting",
+        None => "Not used (daemon mode)",
+    };
+
+    let counters = [
+        ("Listener", listener.to_string()),
+        ("Messages received", metrics.received.to_string()),
+        ("Notification events", metrics.parsed.to_string()),
+        ("Parse failures", metrics.parse_failures.to_string()),
+        ("Receive errors", metrics.receive_errors.to_string()),
+        ("Unpaired evictions", metrics.unpaired_evictions.to_string()),
+        ("Reply timeouts", metrics.reply_timeouts.to_string()),
+        ("Reconnects", metrics.reconnects.to_string()),
+        ("Queue stalls", metrics.queue_stalls.to_string()),
+        ("Peak queue depth", metrics.peak_queue_depth.to_string()),
+    ];
+
+    for (label, value) in counters {
+        content = content.push(
+            row()
+                .push(text::body(label).width(Length::Fill))
+                .push(text::body(value))
+                .spacing(Spacing::xs()),
+        );
+    }
+
+    if metrics.problems() > 0 {
+        content = content.push(text::caption(
+            "Evicted and timed-out calls are still shown, with an ID the sending app doesn't know.",
+        ));
+    }
+
+    content.into()
+}
@@ -2,13 +2,15 @@
 //
 // Custom widgets for displaying notifications.
 
+pub mod diagnostics;
 pub mod filter_settings;
 pub mod notification_card;
 pub mod notification_list;
 pub mod position_settings;
 
 // Re-export commonly used functions
+pub use diagnostics::diagnostics;
 pub use filter_settings::filter_settings;
 pub use notification_card::notification_card;
-pub use notification_list::notification_list;
+pub use notification_list::{disconnected_banner, notification_list};
 pub use position_settings::position_settings;
@@ -4,22 +4,28 @@
 // Follows COSMIC design patterns for consistent appearance and behavior.
 
 use cosmic::iced::Length;
-use cosmic::widget::{button, column, container, icon, row, text};
+use cosmic::widget::{button, column, container, icon, progress_bar, row, text, text_input};
 use cosmic::Element;
 
 use crate::dbus::{Notification, Urgency};
 use crate::ui::animation::NotificationAnimation;
+use crate::ui::icons::IconResolver;
+use crate::ui::markup::{parse_markup, SpanStyle};
 use crate::ui::theme::{ComponentSize, SemanticColors, Spacing, UrgencyStyle};
-use crate::ui::url_parser::{parse_text, TextSegment};
+use crate::ui::url_parser::attachment_label;
 
 /// Create a notification card widget
 ///
 /// Displays notification information with:
 /// - Urgency indicator (colored left border)
-/// - App icon (if available)
+/// - Notification image or app icon (if available)
 /// - App name and timestamp
-/// - Summary and body text with clickable URLs
-/// - Action buttons
+/// - Summary and body text with markup and clickable URLs
+/// - A note when parts were shortened on arrival (see `dbus::sanitize`)
+/// - Attachment chips (from the `x-kde-urls` hint)
+/// - Progress bar (from the `value` hint)
+/// - Reply field, for senders that accept inline replies
+/// - Action buttons (the `default` action is invoked by clicking the card instead)
 /// - Dismiss button
 ///
 /// Uses COSMIC design patterns for consistent appearance.
@@ -28,11 +34,15 @@ use crate::ui::url_parser::{parse_text, TextSegment};
 pub fn notification_card<'a, Message>(
     notification: &'a Notification,
     animation: Option<&'a NotificationAnimation>,
+    icon: Option<&'a icon::Handle>,
     is_selected: bool,
     selected_action_index: Option<usize>,
     on_dismiss: impl Fn(u32) -> Message + 'a,
     on_url: impl Fn(String) -> Message + 'a + Clone,
     on_action: impl Fn(u32, String) -> Message + 'a + Clone,
+    reply_draft: Option<&'a str>,
+    on_reply_input: impl Fn(u32, String) -> Message + 'a,
+    on_reply_submit: impl Fn(u32) -> Message + 'a,
 ) -> Element<'a, Message>
 where
     Message: Clone + 'a + 'static,
@@ -44,12 +54,15 @@ where
         .spacing(Spacing::xs())
         .align_y(cosmic::iced::Alignment::Center);
 
-    // Resolve app icon with fallback to urgency icon
-    let app_icon: cosmic::widget::Icon = resolve_notification_icon(notification);
+    // Resolved image or app icon with fallback to urgency icon
+    let app_icon: cosmic::widget::Icon = resolve_notification_icon(notification, icon);
     header_row = header_row.push(app_icon);
 
-    // App name
-    header_row = header_row.push(text::body(&notification.app_name));
+    // App name, and where the notification comes from if the sender says
+    header_row = header_row.push(text::body(notification.display_name()));
+    if let Some(origin) = notification.origin_name() {
+        header_row = header_row.push(text::caption(format!("· {}", origin)));
+    }
 
     // Spacer to push timestamp and dismiss to the right
     header_row = header_row.push(cosmic::iced::widget::horizontal_space());
@@ -73,25 +86,57 @@ where
         .push(summary)
         .spacing(Spacing::xs());
 
-    // Add body text with clickable URLs if present
+    // Add body text with markup and clickable URLs if present
     if !notification.body.is_empty() {
-        let body_content = render_text_with_links(&notification.body, on_url);
+        let body_content = render_body(&notification.body, on_url.clone());
         content = content.push(body_content);
     }
 
-    // Add action buttons if present
-    if !notification.actions.is_empty() {
+    // Say when the sender's payload was cut down to size
+    if notification.truncated.any() {
+        content = content.push(text::caption(format!(
+            "Shortened: {}",
+            notification.truncated.parts().join(", ")
+        )));
+    }
+
+    // Attached files and links (`x-kde-urls`)
+    if !notification.attachments().is_empty() {
+        content = content.push(render_attachments(notification.attachments(), on_url));
+    }
+
+    // Progress bar for download/transfer style notifications
+    if let Some(value) = notification.progress() {
+        let bar = progress_bar(0.0..=100.0, f32::from(value))
+            .height(Length::Fixed(ComponentSize::PROGRESS_BAR_HEIGHT));
+        content = content.push(bar);
+    }
+
+    // Reply field for messaging apps (`inline-reply` action or KDE hint)
+    if notification.reply_destination().is_some() {
+        let reply_row = render_reply_field(
+            notification,
+            reply_draft.unwrap_or_default(),
+            on_reply_input,
+            on_reply_submit,
+        );
+        content = content.push(reply_row);
+    }
+
+    // Add action buttons if present (the default action has no button)
+    let button_actions = notification.button_actions();
+    if !button_actions.is_empty() {
         // Validate action index is within bounds
         let selected_action = if is_selected {
-            selected_action_index.filter(|&idx| idx < notification.actions.len())
+            selected_action_index.filter(|&idx| idx < button_actions.len())
         } else {
             None
         };
         let action_row = render_action_buttons(
-            &notification.actions,
+            &button_actions,
             notification_id,
             selected_action,
-            on_action,
+            on_action.clone(),
         );
         content = content.push(action_row);
     }
@@ -124,6 +169,16 @@ where
         .width(Length::Fill)
         .style(notification_style(notification.urgency(), is_selected));
 
+    // Clicking the card invokes the default action; buttons and links
+    // inside the card capture their own clicks
+    let card: Element<'a, Message> = match notification.default_action() {
+        Some(action) => cosmic::iced::widget::mouse_area(container)
+            .on_press(on_action(notification_id, action.key.clone()))
+            .interaction(cosmic::iced::mouse::Interaction::Pointer)
+            .into(),
+        None => container.into(),
+    };
+
     // Log animation state if present
     // TODO: Apply visual transformations (opacity, translation, scale) when iced supports it
     // Currently iced/libcosmic has limited transform/opacity support for Elements
@@ -142,7 +197,7 @@ where
         );
     }
 
-    container.into()
+    card
 }
 
 /// Create a container style for urgency indicator bar (left border)
@@ -208,44 +263,113 @@ fn notification_style(
     }
 }
 
-/// Render text with clickable URL links
+/// Render the notification body as rich text
 ///
-/// Parses the text for URLs and creates a row with text segments and link buttons.
-fn render_text_with_links<'a, Message>(
-    text_content: &str,
+/// Parses body markup into styled spans; links (explicit or detected URLs)
+/// are underlined and emit the URL message when clicked.
+fn render_body<'a, Message>(
+    body: &str,
     url_message: impl Fn(String) -> Message + 'a,
 ) -> Element<'a, Message>
 where
     Message: Clone + 'a + 'static,
 {
-    let segments = parse_text(text_content);
+    let spans: Vec<_> = parse_markup(body)
+        .into_iter()
+        .map(|markup_span| {
+            let underline = markup_span.style.underline || markup_span.link.is_some();
+            let span = cosmic::iced::widget::span(markup_span.text)
+                .font(span_font(markup_span.style))
+                .underline(underline);
+
+            match markup_span.link {
+                Some(url) => span.link(url_message(url)),
+                None => span,
+            }
+        })
+        .collect();
 
-    // Create a wrapping row for text segments and links
-    let mut content_row = row()
-        .spacing(Spacing::xxs())
-        .align_y(cosmic::iced::Alignment::Center);
+    cosmic::iced::widget::rich_text(spans).into()
+}
 
-    for segment in segments {
-        match segment {
-            TextSegment::Text(txt) => {
-                // Add plain text using COSMIC body style
-                content_row = content_row.push(text::body(txt));
-            }
-            TextSegment::Link {
-                text: link_text,
-                url,
-            } => {
-                // Add clickable link button with link styling
-                let link_button = button::link(link_text.clone())
-                    .on_press(url_message(url))
-                    .padding([0, Spacing::xxs()]);
-
-                content_row = content_row.push(link_button);
-            }
-        }
+/// Font for a markup span style
+fn span_font(style: SpanStyle) -> cosmic::iced::Font {
+    let mut font = if style.bold {
+        cosmic::font::bold()
+    } else {
+        cosmic::font::default()
+    };
+    if style.italic {
+        font.style = cosmic::iced::font::Style::Italic;
     }
+    font
+}
+
+/// Render attachments as chips that open the file or link
+///
+/// Attachments were validated when the notification arrived (see
+/// `dbus::sanitize`), and are checked again when a chip is pressed.
+fn render_attachments<'a, Message>(
+    urls: &'a [String],
+    on_url: impl Fn(String) -> Message + 'a,
+) -> Element<'a, Message>
+where
+    Message: Clone + 'a + 'static,
+{
+    let mut chips = row().spacing(Spacing::xs());
 
-    content_row.into()
+    for url in urls {
+        let icon_name = if url.starts_with("file:") {
+            "text-x-generic-symbolic"
+        } else {
+            "web-browser-symbolic"
+        };
+        let chip = button::standard(attachment_label(url))
+            .leading_icon(icon::from_name(icon_name))
+            .on_press(on_url(url.clone()))
+            .padding([Spacing::xxs(), Spacing::s()]);
+        chips = chips.push(chip);
+    }
+
+    chips.into()
+}
+
+/// Render the inline reply field and its send button
+///
+/// Enter in the field sends the reply, as does the button. The button is
+/// disabled while the draft is blank.
+fn render_reply_field<'a, Message>(
+    notification: &'a Notification,
+    draft: &'a str,
+    on_input: impl Fn(u32, String) -> Message + 'a,
+    on_submit: impl Fn(u32) -> Message + 'a,
+) -> Element<'a, Message>
+where
+    Message: Clone + 'a + 'static,
+{
+    let notification_id = notification.id;
+    let submit = on_submit(notification_id);
+    let can_send = !draft.trim().is_empty();
+
+    let input = text_input(notification.reply_placeholder().unwrap_or("Reply…"), draft)
+        .on_input(move |text| on_input(notification_id, text))
+        .on_submit({
+            let submit = submit.clone();
+            move |_| submit.clone()
+        })
+        .width(Length::Fill);
+
+    let send_button = button::suggested(notification.reply_submit_label().unwrap_or("Send"))
+        .on_press_maybe(can_send.then_some(submit))
+        .padding([Spacing::xxs(), Spacing::s()]);
+
+    row()
+        .push(input)
+        .push(send_button)
+        .spacing(Spacing::xs())
+        .padding([Spacing::s(), 0, 0, 0])
+        .align_y(cosmic::iced::Alignment::Center)
+        .into()
 }
 
 /// Render action buttons for notification actions
@@ -254,7 +378,7 @@ where
 /// The first action is styled as suggested (primary), others as standard.
 /// The selected action (if any) is highlighted with accent styling.
 fn render_action_buttons<'a, Message>(
-    actions: &[crate::dbus::NotificationAction],
+    actions: &[&crate::dbus::NotificationAction],
     notification_id: u32,
     selected_action_index: Option<usize>,
     on_action: impl Fn(u32, String) -> Message + 'a + Clone,
@@ -296,27 +420,67 @@ where
     action_row.into()
 }
 
-/// Resolve notification icon with fallback
+/// Build the icon handle for a notification
 ///
-/// Attempts to load the application icon, falling back to urgency indicator if unavailable.
+/// Follows the priority order of the notification specification:
+/// 1. Decoded `image-data` hint
+/// 2. `image-path` hint
+/// 3. Application icon from `app_icon` field
+/// 4. Icon from the app's desktop entry
 ///
-/// # Priority
-/// 1. Application icon from `app_icon` field (if valid name/path)
-/// 2. Urgency icon based on notification level (fallback)
-fn resolve_notification_icon(notification: &Notification) -> cosmic::widget::Icon {
-    // Determine icon name with fallback chain
-    let icon_name = if !notification.app_icon.is_empty() {
-        // Use provided app icon
-        notification.app_icon.as_str()
-    } else {
-        // Fallback to urgency-based icon
-        UrgencyStyle::icon_name(notification.urgency())
+/// Paths, file:// URIs and themed names go through the icon resolver; a
+/// source that doesn't resolve falls through to the next one. Decoding and
+/// lookups are costly, so call this once per notification, off the UI
+/// thread, and pass the handle to `notification_card` instead of calling it
+/// while rendering.
+/// Returns None when nothing resolves (the card shows the urgency icon).
+pub fn notification_icon(
+    notification: &Notification,
+    resolver: &mut IconResolver,
+) -> Option<icon::Handle> {
+    if let Some(handle) = image_handle(notification) {
+        return Some(handle);
+    }
+
+    let identity_icon = notification
+        .app_identity
+        .as_ref()
+        .and_then(|identity| identity.icon.as_deref());
+
+    [
+        notification.hints.image_path.as_deref(),
+        Some(notification.app_icon.as_str()),
+        identity_icon,
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|icon| resolver.resolve(icon))
+    .map(icon::from_path)
+}
+
+/// Build an image handle from the notification's image-data hint
+fn image_handle(notification: &Notification) -> Option<icon::Handle> {
+    let image = notification.hints.image_data.as_ref()?;
+    let pixels = image.to_rgba()?;
+    Some(icon::from_raster_pixels(
+        image.width as u32,
+        image.height as u32,
+        pixels,
+    ))
+}
+
+/// Show the resolved notification icon, falling back to the urgency icon
+fn resolve_notification_icon(
+    notification: &Notification,
+    icon: Option<&icon::Handle>,
+) -> cosmic::widget::Icon {
+    let handle = match icon {
+        Some(handle) => handle.clone(),
+        // Create icon - libcosmic will handle missing icons gracefully
+        None => icon::from_name(UrgencyStyle::icon_name(notification.urgency())).handle(),
     };
 
-    // Create icon - libcosmic will handle missing icons gracefully
-    icon::from_name(icon_name)
-        .size(ComponentSize::NOTIFICATION_ICON)
-        .into()
+    icon::icon(handle).size(ComponentSize::NOTIFICATION_ICON)
 }
 
 /// Format timestamp for display
@@ -6,7 +6,7 @@
 use std::collections::{HashMap, VecDeque};
 
 use cosmic::iced::Length;
-use cosmic::widget::{column, container, icon, scrollable, text};
+use cosmic::widget::{button, column, container, icon, row, scrollable, text};
 use cosmic::Element;
 
 use crate::dbus::Notification;
@@ -16,18 +16,23 @@ use crate::ui::widgets::notification_card;
 
 /// Create a notification list widget
 ///
-/// Displays notifications in a scrollable column with clickable URLs and action buttons.
-/// Shows empty state message when no notifications are present.
+/// Displays notifications in a scrollable column with clickable URLs, action buttons
+/// and reply fields, below a "Clear all" button. Shows empty state message when no notifications are present.
 ///
 /// Performance: Accepts a reference to avoid copying notification data on every frame.
 pub fn notification_list<'a, Message>(
     notifications: &'a VecDeque<Notification>,
     notification_animations: &'a HashMap<u32, NotificationAnimation>,
+    notification_icons: &'a HashMap<u32, icon::Handle>,
     selected_index: Option<usize>,
     selected_action_index: Option<usize>,
     on_dismiss: impl Fn(u32) -> Message + 'a + Clone,
+    on_clear_all: Message,
     on_url: impl Fn(String) -> Message + 'a + Clone,
     on_action: impl Fn(u32, String) -> Message + 'a + Clone,
+    reply_drafts: &'a HashMap<u32, String>,
+    on_reply_input: impl Fn(u32, String) -> Message + 'a + Clone,
+    on_reply_submit: impl Fn(u32) -> Message + 'a + Clone,
 ) -> Element<'a, Message>
 where
     Message: Clone + 'a + 'static,
@@ -73,18 +78,48 @@ where
             col.push(notification_card::notification_card(
                 notification,
                 animation,
+                notification_icons.get(&notification.id),
                 is_selected,
                 action_index,
                 on_dismiss.clone(),
                 on_url.clone(),
                 on_action.clone(),
+                reply_drafts.get(&notification.id).map(String::as_str),
+                on_reply_input.clone(),
+                on_reply_submit.clone(),
             ))
         },
     );
 
-    // Wrap in scrollable container
-    scrollable(cards)
-        .width(Length::Fill)
-        .height(Length::Fill)
+    // Header row: "Clear all" aligned to the end
+    let header = row()
+        .push(cosmic::iced::widget::horizontal_space())
+        .push(button::text("Clear all").on_press(on_clear_all))
+        .padding([0, Spacing::xs()]);
+
+    // Wrap cards in scrollable container, keeping the header in view
+    column()
+        .push(header)
+        .push(scrollable(cards).width(Length::Fill).height(Length::Fill))
+        .into()
+}
+
+/// Banner shown while the notification listener is reconnecting to the session bus
+pub fn disconnected_banner<'a, Message>() -> Element<'a, Message>
+where
+    Message: 'a,
+{
+    let status_icon: cosmic::widget::Icon = icon::from_name("network-offline-symbolic")
+        .size(ComponentSize::STATUS_ICON)
+        .into();
+
+    row()
+        .push(status_icon)
+        .push(text::caption(
+            "Disconnected from the session bus, reconnecting…",
+        ))
+        .spacing(Spacing::xs())
+        .align_y(cosmic::iced::Alignment::Center)
+        .padding([Spacing::xs(), Spacing::s()])
         .into()
 }
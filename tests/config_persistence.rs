@@ -19,6 +19,7 @@ fn create_test_config() -> AppletConfig {
         do_not_disturb: false,
         default_timeout: Some(5000),
         play_sound: false,
+        sound_theme: None,
         show_preview: true,
         history_enabled: true,
         max_history_items: 100,
@@ -26,6 +27,8 @@ fn create_test_config() -> AppletConfig {
         app_filters: HashMap::new(),
         min_urgency_level: 0,
         animations: AnimationConfig::default(),
+        daemon_mode: false,
+        gtk_notifications: false,
     }
 }
 
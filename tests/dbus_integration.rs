@@ -8,21 +8,26 @@ use cosmic_applet_notifications::dbus::{
 };
 use std::collections::HashMap;
 
+/// Notification as received from a plain `Notify` call
+fn test_notification() -> Notification {
+    Notification::from_notify_args(
+        1,
+        (
+            "test".to_string(),
+            0,
+            String::new(),
+            "Test".to_string(),
+            String::new(),
+            vec![],
+            HashMap::new(),
+            0,
+        ),
+    )
+}
+
 #[test]
 fn test_notification_default() {
-    let notification = Notification {
-        id: 1,
-        app_name: "test".to_string(),
-        replaces_id: 0,
-        app_icon: "".to_string(),
-        summary: "Test".to_string(),
-        body: "".to_string(),
-        actions: vec![],
-        hints: NotificationHints::default(),
-        raw_hints: HashMap::new(),
-        expire_timeout: 0,
-        timestamp: chrono::Local::now(),
-    };
+    let notification = test_notification();
 
     assert_eq!(notification.id, 1);
     assert_eq!(notification.app_name, "test");
@@ -132,19 +137,8 @@ fn test_notification_with_actions() {
         },
     ];
 
-    let notification = Notification {
-        id: 1,
-        app_name: "test".to_string(),
-        replaces_id: 0,
-        app_icon: "".to_string(),
-        summary: "Test".to_string(),
-        body: "".to_string(),
-        actions: actions.clone(),
-        hints: NotificationHints::default(),
-        raw_hints: HashMap::new(),
-        expire_timeout: 0,
-        timestamp: chrono::Local::now(),
-    };
+    let mut notification = test_notification();
+    notification.actions = actions.clone();
 
     assert_eq!(notification.actions.len(), 2);
     assert_eq!(notification.actions[0].key, "view");
@@ -153,19 +147,10 @@ fn test_notification_with_actions() {
 
 #[test]
 fn test_notification_with_body() {
-    let notification = Notification {
-        id: 1,
-        app_name: "test".to_string(),
-        replaces_id: 0,
-        app_icon: "".to_string(),
-        summary: "Test Summary".to_string(),
-        body: "This is a test notification with a body".to_string(),
-        actions: vec![],
-        hints: NotificationHints::default(),
-        raw_hints: HashMap::new(),
-        expire_timeout: 5000,
-        timestamp: chrono::Local::now(),
-    };
+    let mut notification = test_notification();
+    notification.summary = "Test Summary".to_string();
+    notification.body = "This is a test notification with a body".to_string();
+    notification.expire_timeout = 5000;
 
     assert!(!notification.body.is_empty());
     assert_eq!(notification.body, "This is a test notification with a body");
@@ -174,19 +159,10 @@ fn test_notification_with_body() {
 
 #[test]
 fn test_notification_replacement() {
-    let notification = Notification {
-        id: 2,
-        app_name: "test".to_string(),
-        replaces_id: 1,
-        app_icon: "".to_string(),
-        summary: "Replacement".to_string(),
-        body: "".to_string(),
-        actions: vec![],
-        hints: NotificationHints::default(),
-        raw_hints: HashMap::new(),
-        expire_timeout: 0,
-        timestamp: chrono::Local::now(),
-    };
+    let mut notification = test_notification();
+    notification.id = 2;
+    notification.replaces_id = 1;
+    notification.summary = "Replacement".to_string();
 
     assert_eq!(notification.replaces_id, 1);
     assert_eq!(notification.id, 2);
@@ -194,19 +170,8 @@ fn test_notification_replacement() {
 
 #[test]
 fn test_notification_with_icon() {
-    let notification = Notification {
-        id: 1,
-        app_name: "test".to_string(),
-        replaces_id: 0,
-        app_icon: "dialog-information".to_string(),
-        summary: "Test".to_string(),
-        body: "".to_string(),
-        actions: vec![],
-        hints: NotificationHints::default(),
-        raw_hints: HashMap::new(),
-        expire_timeout: 0,
-        timestamp: chrono::Local::now(),
-    };
+    let mut notification = test_notification();
+    notification.app_icon = "dialog-information".to_string();
 
     assert_eq!(notification.app_icon, "dialog-information");
 }
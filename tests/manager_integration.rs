@@ -4,28 +4,27 @@
 // history management, and interaction with configuration.
 
 use cosmic_applet_notifications::{
-    dbus::{Notification, NotificationHints, Urgency},
-    manager::{NotificationAction, NotificationManager},
+    dbus::{CloseReason, Notification, Urgency},
+    manager::{ClosureReport, NotificationAction, NotificationManager},
 };
 use std::collections::HashMap;
 
 fn create_notification(app_name: &str, summary: &str, urgency: Urgency) -> Notification {
-    Notification {
-        id: 0,
-        app_name: app_name.to_string(),
-        replaces_id: 0,
-        app_icon: String::new(),
-        summary: summary.to_string(),
-        body: String::new(),
-        actions: Vec::new(),
-        hints: NotificationHints {
-            urgency,
-            ..Default::default()
-        },
-        raw_hints: HashMap::new(),
-        expire_timeout: 0,
-        timestamp: chrono::Local::now(),
-    }
+    let mut notification = Notification::from_notify_args(
+        0,
+        (
+            app_name.to_string(),
+            0,
+            String::new(),
+            summary.to_string(),
+            String::new(),
+            Vec::new(),
+            HashMap::new(),
+            0,
+        ),
+    );
+    notification.hints.urgency = urgency;
+    notification
 }
 
 #[test]
@@ -219,6 +218,69 @@ fn test_manager_expiration() {
     assert_eq!(expired.len(), 1);
 }
 
+#[test]
+fn test_manager_expired_notifications_reported_closed() {
+    let mut manager = NotificationManager::new();
+
+    let mut old_notif = create_notification("mail", "Old", Urgency::Normal);
+    old_notif.expire_timeout = 5000;
+    old_notif.timestamp = chrono::Local::now() - chrono::Duration::seconds(10);
+    manager.add_notification(old_notif);
+    manager.add_notification(create_notification("mail", "Recent", Urgency::Normal));
+
+    // Same flow as the application's Tick handler
+    for id in manager.get_expired_notifications() {
+        manager.close_notification(id, CloseReason::Expired);
+    }
+
+    let closed = manager.take_closed_notifications();
+    assert_eq!(closed.len(), 1);
+    assert_eq!(closed[0].reason, CloseReason::Expired);
+    assert_eq!(manager.active_count(), 1);
+    assert_eq!(manager.history().len(), 1);
+}
+
+#[test]
+fn test_manager_closure_reports_by_mode() {
+    let mut manager = NotificationManager::new();
+
+    let mut old_notif = create_notification("mail", "Old", Urgency::Normal);
+    old_notif.expire_timeout = 5000;
+    old_notif.timestamp = chrono::Local::now() - chrono::Duration::seconds(10);
+    manager.add_notification(old_notif);
+    manager.add_notification(create_notification("mail", "Read", Urgency::Normal));
+
+    for id in manager.get_expired_notifications() {
+        manager.close_notification(id, CloseReason::Expired);
+    }
+    let read = manager.get_notification_at(0).unwrap().id;
+    manager.remove_notification(read);
+
+    let reports: Vec<_> = manager
+        .take_closed_notifications()
+        .iter()
+        .map(|closed| (closed.reason, closed.report(true), closed.report(false)))
+        .collect();
+
+    // Daemon mode signals every closure; listener mode only asks the
+    // owning daemon to close what the user dismissed
+    assert_eq!(
+        reports,
+        vec![
+            (
+                CloseReason::Expired,
+                ClosureReport::Signal,
+                ClosureReport::None
+            ),
+            (
+                CloseReason::Dismissed,
+                ClosureReport::Signal,
+                ClosureReport::CloseOnDaemon
+            ),
+        ]
+    );
+}
+
 #[test]
 fn test_manager_never_expire_notifications() {
     let mut manager = NotificationManager::new();